@@ -2,32 +2,92 @@
 //!
 //! This module contains the main application state and egui-based UI.
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Component, Path, PathBuf};
+#[cfg(target_os = "windows")]
 use std::process::Command;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
 use eframe::egui;
 use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
 
-use crate::file_ops::{FileOperations, OperationResult};
-use crate::scanner::{format_size, DuplicateGroup, FileEntry, ScanResult, Scanner, ScannerConfig};
+use filexsorter::audio::{self, AudioPlayer};
+use filexsorter::export;
+use filexsorter::file_ops::{
+    self, FileOperations, MoveLayout, OperationResult, RenameItem, RenamePattern,
+};
+use filexsorter::filter::{self, GroupQuery, GroupSort};
+use filexsorter::folders;
+use filexsorter::hash_cache::{self, HashCache};
+use filexsorter::highlight::Highlighter;
+use filexsorter::media::{self, MediaInfo};
+use filexsorter::scanner::{
+    format_size, is_protected, normalized_extension, parse_extension_list, DetectionMode,
+    DuplicateGroup, FileEntry, FolderWaste, GroupKind, HashAlgorithm, KeepCriterion, KeepStrategy,
+    MatchConfidence, ScanPhase, ScanProgress, ScanResult, Scanner, ScannerConfig, MAX_FILE_SIZE,
+};
+use filexsorter::session::{SavedScan, Staleness};
+use filexsorter::similarity::MAX_SIMILARITY_THRESHOLD;
+use filexsorter::volume::{self, DiskSpace, VolumeFreeing, VolumePath};
+use filexsorter::watcher::DuplicateWatcher;
 
 // Security constants
 const MAX_IMAGE_DIMENSIONS: u32 = 16384;
 const MAX_IMAGE_BYTES: u64 = 100 * 1024 * 1024;
 const MAX_CACHED_IMAGES: usize = 50;
+/// Texture memory the preview cache, animations included, may hold before evicting
+const MAX_CACHED_IMAGE_BYTES: usize = 512 * 1024 * 1024;
+/// Animated GIF previews kept as decoded frames
+const MAX_CACHED_ANIMATIONS: usize = 5;
+/// Decoded frame data allowed per GIF; longer animations loop over their start
+const MAX_ANIMATION_BYTES: usize = 128 * 1024 * 1024;
+
+const DEFAULT_PREVIEW_MAX_SIZE: u32 = 1024;
+/// Longest path shown for the file a scan is working on
+const CURRENT_FILE_CHARS: usize = 60;
+/// Edge length of confirmation dialog and group grid thumbnails
+const THUMBNAIL_SIZE: u32 = 96;
+/// Group grid thumbnails kept before the least recently used are dropped
+const MAX_GROUP_THUMBNAILS: usize = 200;
+/// New thumbnails decoded per frame, so opening a grid doesn't stall the UI
+const THUMBNAILS_PER_FRAME: usize = 2;
+
+// Persistence keys
+const SCAN_PROFILES_KEY: &str = "scan_profiles";
+const AUTO_EXPAND_KEY: &str = "auto_expand_groups";
+const REMEMBER_SCAN_KEY: &str = "remember_last_scan";
+const FILE_FLAGS_KEY: &str = "file_flags";
+const RECYCLE_BIN_KEY: &str = "use_recycle_bin";
+const FOLDERS_KEY: &str = "selected_folders";
+const SCAN_CONFIG_KEY: &str = "scan_config";
+const PREVIEW_PANEL_KEY: &str = "show_preview_panel";
+const PREVIEW_WIDTH_KEY: &str = "preview_panel_width";
+const THEME_KEY: &str = "theme";
+const HASH_CACHE_KEY: &str = "use_hash_cache";
+const OPERATION_LOG_KEY: &str = "keep_operation_log";
+
+/// Number of largest groups expanded when results arrive
+const DEFAULT_AUTO_EXPAND_GROUPS: usize = 3;
+
+/// Pattern the rename dialog starts with
+const DEFAULT_RENAME_PATTERN: &str = "{name}_{index}";
+
+/// Empty files that are there on purpose, e.g. keeping a folder in git or
+/// marking a Python package, so "Select all" leaves them out
+const MARKER_FILE_NAMES: &[&str] = &[".gitkeep", ".keep", "__init__.py", "py.typed", ".nomedia"];
 
 /// Shared state for background scanning
 struct ScanState {
     result: Mutex<Option<ScanResult>>,
     is_complete: AtomicBool,
-    progress_current: AtomicUsize,
-    progress_total: AtomicUsize,
+    progress: ScanProgress,
     cancel_flag: AtomicBool,
+    /// Hash cache the scan opened, handed back to be reused by later scans
+    opened_cache: Mutex<Option<Arc<Mutex<HashCache>>>>,
 }
 
 impl ScanState {
@@ -35,15 +95,15 @@ impl ScanState {
         Self {
             result: Mutex::new(None),
             is_complete: AtomicBool::new(false),
-            progress_current: AtomicUsize::new(0),
-            progress_total: AtomicUsize::new(0),
+            progress: ScanProgress::default(),
             cancel_flag: AtomicBool::new(false),
+            opened_cache: Mutex::new(None),
         }
     }
 }
 
 /// File type for preview purposes
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum FileType {
     Image,
     Video,
@@ -53,6 +113,35 @@ enum FileType {
     Other,
 }
 
+impl FileType {
+    /// Categories offered as group filter chips
+    const FILTERS: [FileType; 5] = [
+        FileType::Image,
+        FileType::Video,
+        FileType::Audio,
+        FileType::Text,
+        FileType::Other,
+    ];
+
+    /// The filter chip this type falls under; GIFs count as images
+    fn category(self) -> FileType {
+        match self {
+            FileType::Gif => FileType::Image,
+            other => other,
+        }
+    }
+
+    fn filter_label(self) -> &'static str {
+        match self {
+            FileType::Image | FileType::Gif => "Images",
+            FileType::Video => "Video",
+            FileType::Audio => "Audio",
+            FileType::Text => "Text",
+            FileType::Other => "Other",
+        }
+    }
+}
+
 /// File preview information
 #[derive(Clone)]
 struct FilePreview {
@@ -65,10 +154,332 @@ struct FilePreview {
     dimensions: Option<(u32, u32)>,
 }
 
+/// Frame textures of an animated GIF preview
+struct GifAnimation {
+    frames: Vec<egui::TextureHandle>,
+    delays: Vec<std::time::Duration>,
+    /// UI clock time the animation started from
+    started: f64,
+}
+
+/// A GIF preview's decoding state, cached per path
+enum GifPreview {
+    /// Frames being decoded on a worker thread
+    Decoding(mpsc::Receiver<Option<Vec<(egui::ColorImage, std::time::Duration)>>>),
+    Ready(GifAnimation),
+    /// A single frame, or one that couldn't be decoded, so shown as a still image
+    Still,
+}
+
+/// Audio/video metadata and video thumbnail state, cached per path
+enum MediaPreview {
+    Loading(mpsc::Receiver<Option<(MediaInfo, Option<image::RgbaImage>)>>),
+    Ready(MediaInfo, Option<egui::TextureHandle>),
+    Unavailable,
+}
+
+/// A named, reusable set of scan options
+#[derive(Clone, Serialize, Deserialize)]
+struct ScanProfile {
+    name: String,
+    config: ScannerConfig,
+}
+
 #[derive(Clone)]
 enum ConfirmationDialog {
     DeleteFiles(Vec<PathBuf>),
-    MoveFiles(Vec<PathBuf>, PathBuf),
+    /// `(source, target folder)` pairs and the chosen destination
+    MoveFiles(Vec<(PathBuf, PathBuf)>, PathBuf),
+    /// `(duplicate, keeper)` pairs to replace with hard links
+    LinkToKeeper(Vec<(PathBuf, PathBuf)>),
+    /// `(current, new)` path pairs planned from `rename_pattern`
+    RenameFiles(Vec<(PathBuf, PathBuf)>),
+    /// Duplicated folders to delete, each with the files the scan matched in it
+    DeleteFolders(Vec<(PathBuf, HashSet<PathBuf>)>),
+}
+
+/// Size of the selection and what deleting it would free on each drive,
+/// kept until the selection changes so the status bar doesn't hit the disk every frame
+struct SelectionSpace {
+    /// Selected files with the sizes the scan recorded
+    files: Vec<(PathBuf, u64)>,
+    size: u64,
+    volumes: Vec<VolumeFreeing>,
+}
+
+impl SelectionSpace {
+    fn measure(files: Vec<(PathBuf, u64)>) -> Self {
+        Self {
+            size: files.iter().map(|(_, size)| size).sum(),
+            volumes: volume::space_freed_by(&files),
+            files,
+        }
+    }
+}
+
+/// Free-space projection for a pending move or delete
+enum SpaceCheck {
+    /// The destination of a move
+    Move {
+        space: DiskSpace,
+        /// Bytes that will be copied onto the destination volume
+        incoming: u64,
+        /// Bytes already on the destination volume, which the move won't free
+        same_volume: u64,
+    },
+    /// Each drive a delete takes files from. Recycled files stay on their
+    /// drive until the bin is emptied, so only erasing them frees space.
+    Delete(Vec<VolumeFreeing>),
+}
+
+impl SpaceCheck {
+    fn for_move(paths: &[PathBuf], dest: &Path) -> Option<Self> {
+        let space = volume::disk_space(dest)?;
+        let (mut incoming, mut same_volume) = (0, 0);
+        for path in paths {
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if volume::same_volume(path, dest) {
+                same_volume += size;
+            } else {
+                incoming += size;
+            }
+        }
+        Some(Self::Move {
+            space,
+            incoming,
+            same_volume,
+        })
+    }
+
+    fn for_delete<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> Self {
+        let sizes: Vec<(PathBuf, u64)> = paths
+            .into_iter()
+            .filter_map(|p| fs::metadata(p).ok().map(|m| (p.clone(), m.len())))
+            .collect();
+        Self::Delete(volume::space_freed_by(&sizes))
+    }
+}
+
+/// Review marker a user can attach to a file without selecting it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FileFlag {
+    color: FlagColor,
+    note: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum FlagColor {
+    #[default]
+    Yellow,
+    Green,
+    Red,
+    Blue,
+}
+
+impl FlagColor {
+    const ALL: [FlagColor; 4] = [
+        FlagColor::Yellow,
+        FlagColor::Green,
+        FlagColor::Red,
+        FlagColor::Blue,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            FlagColor::Yellow => "Check this",
+            FlagColor::Green => "Probably keep",
+            FlagColor::Red => "Probably delete",
+            FlagColor::Blue => "Other",
+        }
+    }
+
+    fn color32(&self) -> egui::Color32 {
+        match self {
+            FlagColor::Yellow => egui::Color32::YELLOW,
+            FlagColor::Green => egui::Color32::GREEN,
+            FlagColor::Red => egui::Color32::RED,
+            FlagColor::Blue => egui::Color32::LIGHT_BLUE,
+        }
+    }
+}
+
+/// Color scheme of the window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum Theme {
+    /// Follow the system's light/dark app setting
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    fn label(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+
+    fn preference(self) -> egui::ThemePreference {
+        match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark => egui::ThemePreference::Dark,
+        }
+    }
+}
+
+/// Unit for the size filter inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeUnit {
+    Kb,
+    Mb,
+    Gb,
+}
+
+impl SizeUnit {
+    const ALL: [SizeUnit; 3] = [SizeUnit::Kb, SizeUnit::Mb, SizeUnit::Gb];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SizeUnit::Kb => "KB",
+            SizeUnit::Mb => "MB",
+            SizeUnit::Gb => "GB",
+        }
+    }
+
+    fn bytes(&self) -> u64 {
+        match self {
+            SizeUnit::Kb => 1024,
+            SizeUnit::Mb => 1024 * 1024,
+            SizeUnit::Gb => 1024 * 1024 * 1024,
+        }
+    }
+
+    /// Largest unit `size` is a whole multiple of
+    fn fitting(size: u64) -> Self {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|unit| size >= unit.bytes() && size.is_multiple_of(unit.bytes()))
+            .unwrap_or(SizeUnit::Kb)
+    }
+}
+
+/// Thumbnail grids for image-only groups, cached apart from the preview textures
+#[derive(Default)]
+struct GroupThumbnails {
+    /// Hashes of groups currently shown as a grid
+    shown: HashSet<String>,
+    /// Each thumbnail with the pass it was last drawn in; `None` marks a
+    /// file that couldn't be decoded
+    textures: HashMap<PathBuf, (Option<egui::TextureHandle>, u64)>,
+    frame: u64,
+    budget: usize,
+}
+
+impl GroupThumbnails {
+    fn get(&mut self, ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+        let pass = ctx.cumulative_pass_nr();
+        if let Some((cached, last_used)) = self.textures.get_mut(path) {
+            *last_used = pass;
+            return cached.clone();
+        }
+        if self.frame != pass {
+            self.frame = pass;
+            self.budget = THUMBNAILS_PER_FRAME;
+        }
+        if self.budget == 0 {
+            ctx.request_repaint();
+            return None;
+        }
+        self.budget -= 1;
+
+        let texture = FileXSorterApp::decode_image(path, THUMBNAIL_SIZE).map(|image| {
+            ctx.load_texture(
+                format!("grid:{}", path.display()),
+                image,
+                egui::TextureOptions::LINEAR,
+            )
+        });
+        if self.textures.len() >= MAX_GROUP_THUMBNAILS {
+            // Drop the one drawn longest ago, so thumbnails on screen stay
+            let oldest = self
+                .textures
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                self.textures.remove(&oldest);
+            }
+        }
+        self.textures
+            .insert(path.to_path_buf(), (texture.clone(), pass));
+        texture
+    }
+
+    fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
+
+/// Per-list state a group is drawn against
+struct GroupView<'a> {
+    selected_files: &'a [(usize, usize)],
+    flags: &'a mut HashMap<PathBuf, FileFlag>,
+    flagged_only: bool,
+    protected: &'a [PathBuf],
+    /// Hashes of groups currently expanded
+    expanded: &'a mut HashSet<String>,
+    /// Running re-verifications, or `None` where re-verifying isn't offered
+    reverifying: Option<&'a HashMap<String, ReverifyJob>>,
+    /// Files the folder watcher found after the scan
+    new_files: Option<&'a HashSet<PathBuf>>,
+    /// Files known to no longer exist
+    missing: Option<&'a HashSet<PathBuf>>,
+    /// Offer "Set as keeper" on the other files of each group
+    set_keeper: bool,
+    thumbnails: &'a mut GroupThumbnails,
+    /// Row moved with the arrow keys, and whether to scroll it into view
+    focused: Option<(usize, usize)>,
+    scroll_to_focus: bool,
+    /// Last checkbox clicked, where a shift-click range starts
+    anchor: Option<(usize, usize)>,
+}
+
+/// A click inside a group's file rows
+enum GroupAction {
+    Select(usize, usize, bool),
+    Preview(FileEntry),
+    Reveal(PathBuf),
+    Reverify(DuplicateGroup),
+    /// Make a file its group's keeper
+    SetKeeper(usize, usize),
+    /// Select these files of a group, replacing its current selection
+    SelectDuplicates(usize, Vec<usize>),
+    /// Select or unselect a shift-clicked range of a group's files
+    SelectRange(usize, Vec<usize>, bool),
+    /// Ask to delete these copies of one group
+    DeleteDuplicates(Vec<PathBuf>),
+}
+
+/// Background re-hash of one group, delivering its regrouped files
+type ReverifyJob = mpsc::Receiver<Vec<DuplicateGroup>>;
+
+/// A scan result shown in its own viewport
+struct ResultWindow {
+    id: egui::ViewportId,
+    title: String,
+    result: ScanResult,
+    selected_files: Vec<(usize, usize)>,
+    /// Last checkbox clicked, for shift-click ranges
+    anchor: Option<(usize, usize)>,
+    expanded_groups: HashSet<String>,
+    open: bool,
 }
 
 #[derive(Clone)]
@@ -81,7 +492,24 @@ enum MessageType {
 /// Application state
 pub struct FileXSorterApp {
     selected_folders: Vec<PathBuf>,
-    recursive_scan: bool,
+    loose_files: Vec<PathBuf>,
+    scan_config: ScannerConfig,
+    min_size_unit: SizeUnit,
+    max_size_unit: SizeUnit,
+    /// Comma-separated extensions as typed
+    extensions_text: String,
+    /// Whether `extensions_text` lists the only extensions to scan rather than ones to skip
+    extensions_include: bool,
+    /// Comma-separated excluded folder names as typed
+    exclude_names_text: String,
+    scan_profiles: Vec<ScanProfile>,
+    profile_name: String,
+    all_files_query: String,
+    /// Narrows the main group list, see `GroupQuery`
+    group_query: String,
+    /// Groups whose keeper falls in one of these categories are hidden
+    hidden_file_types: HashSet<FileType>,
+    group_sort: GroupSort,
     is_scanning: bool,
     scan_result: Option<ScanResult>,
     scan_state: Arc<ScanState>,
@@ -89,11 +517,84 @@ pub struct FileXSorterApp {
     selected_files: Vec<(usize, usize)>,
     preview_file: Option<FilePreview>,
     show_preview_panel: bool,
+    theme: Theme,
+    /// Width the preview panel was last dragged to, restored on startup
     preview_panel_width: f32,
+    preview_max_size: u32,
     loaded_images: HashMap<PathBuf, egui::TextureHandle>,
+    /// Least recently previewed first
     image_access_order: VecDeque<PathBuf>,
+    failed_images: HashSet<PathBuf>,
+    /// Most recently shown last; `None` marks a GIF that isn't animated
+    gif_animations: VecDeque<(PathBuf, GifPreview)>,
+    /// Loaded on the first text preview
+    highlighter: Option<Highlighter>,
+    /// Coloured text of the current preview, built for dark mode or not;
+    /// `None` inside when the file type has no grammar
+    preview_highlight: Option<(bool, Option<egui::text::LayoutJob>)>,
+    thumbnails: HashMap<PathBuf, Option<egui::TextureHandle>>,
+    reverify_jobs: HashMap<String, ReverifyJob>,
+    /// Keep checking the scanned folders for new copies after a scan
+    watch_folders: bool,
+    watcher: Option<DuplicateWatcher>,
+    /// Files added to groups by the watcher since the last scan
+    new_duplicates: HashSet<PathBuf>,
+    /// Files in a loaded session that were gone when it was loaded
+    missing_files: HashSet<PathBuf>,
+    /// Group hashes from a previous export; matching groups count as already reviewed
+    baseline: Option<HashSet<String>>,
+    show_seen_groups: bool,
+    /// Save completed results to disk and restore them on startup
+    remember_last_scan: bool,
+    /// Reuse hashes of unchanged files from earlier scans
+    use_hash_cache: bool,
+    /// Opened on the first scan that uses it
+    hash_cache: Option<Arc<Mutex<HashCache>>>,
+    /// Result changed since it was last written to disk
+    session_dirty: bool,
+    /// Unix timestamp of when the current result was produced
+    scan_finished_at: i64,
+    /// Set while showing a result restored from a previous session
+    restored_scan: Option<Staleness>,
+    /// Folders of the result a running single-folder rescan will replace
+    rescanned_folders: Option<Vec<PathBuf>>,
+    window_active: bool,
+    media_previews: HashMap<PathBuf, MediaPreview>,
+    audio_player: AudioPlayer,
+    /// Audio files that failed to play, whose play button is disabled
+    unplayable_audio: HashMap<PathBuf, String>,
     file_ops: FileOperations,
     show_confirmation_dialog: Option<ConfirmationDialog>,
+    space_check: Option<SpaceCheck>,
+    selection_space: Option<SelectionSpace>,
+    /// List the planned operations in the confirmation dialog
+    preview_operations: bool,
+    /// Dry-run results for the open dialog, filled in while previewing
+    operation_preview: Option<Vec<OperationResult>>,
+    /// The user accepted that a delete removes every copy of some groups
+    confirm_all_copies: bool,
+    move_layout: MoveLayout,
+    rename_pattern: String,
+    /// Why `rename_pattern` can't be applied to the selection
+    rename_error: Option<String>,
+    keeper_pattern: String,
+    /// Empty files ticked for deletion
+    selected_empty_files: HashSet<PathBuf>,
+    result_windows: Vec<ResultWindow>,
+    next_window_id: usize,
+    file_flags: HashMap<PathBuf, FileFlag>,
+    show_flagged_only: bool,
+    hide_protected_groups: bool,
+    group_thumbnails: GroupThumbnails,
+    /// Row the keyboard shortcuts act on
+    focused_row: Option<(usize, usize)>,
+    scroll_to_focus: bool,
+    /// Let "Invert" select keepers too, which can select every copy of a group
+    invert_includes_keepers: bool,
+    auto_expand_groups: usize,
+    expanded_groups: HashSet<String>,
+    /// Wasted space by folder, refreshed when a scan finishes or files are removed
+    folder_breakdown: Vec<FolderWaste>,
     status_message: Option<(String, MessageType)>,
 }
 
@@ -101,7 +602,19 @@ impl Default for FileXSorterApp {
     fn default() -> Self {
         Self {
             selected_folders: Vec::new(),
-            recursive_scan: true,
+            loose_files: Vec::new(),
+            scan_config: ScannerConfig::default(),
+            min_size_unit: SizeUnit::Kb,
+            max_size_unit: SizeUnit::Gb,
+            extensions_text: String::new(),
+            extensions_include: true,
+            exclude_names_text: String::new(),
+            scan_profiles: Vec::new(),
+            profile_name: String::new(),
+            all_files_query: String::new(),
+            group_query: String::new(),
+            hidden_file_types: HashSet::new(),
+            group_sort: GroupSort::default(),
             is_scanning: false,
             scan_result: None,
             scan_state: Arc::new(ScanState::new()),
@@ -109,23 +622,169 @@ impl Default for FileXSorterApp {
             selected_files: Vec::new(),
             preview_file: None,
             show_preview_panel: true,
+            theme: Theme::default(),
             preview_panel_width: 220.0,
+            preview_max_size: DEFAULT_PREVIEW_MAX_SIZE,
             loaded_images: HashMap::new(),
             image_access_order: VecDeque::new(),
+            failed_images: HashSet::new(),
+            gif_animations: VecDeque::new(),
+            highlighter: None,
+            preview_highlight: None,
+            thumbnails: HashMap::new(),
+            reverify_jobs: HashMap::new(),
+            watch_folders: false,
+            watcher: None,
+            new_duplicates: HashSet::new(),
+            missing_files: HashSet::new(),
+            baseline: None,
+            show_seen_groups: false,
+            remember_last_scan: false,
+            use_hash_cache: true,
+            hash_cache: None,
+            session_dirty: false,
+            scan_finished_at: 0,
+            restored_scan: None,
+            rescanned_folders: None,
+            window_active: true,
+            media_previews: HashMap::new(),
+            audio_player: AudioPlayer::default(),
+            unplayable_audio: HashMap::new(),
             file_ops: FileOperations::new(),
             show_confirmation_dialog: None,
+            space_check: None,
+            selection_space: None,
+            preview_operations: false,
+            confirm_all_copies: false,
+            operation_preview: None,
+            move_layout: MoveLayout::default(),
+            rename_pattern: DEFAULT_RENAME_PATTERN.to_string(),
+            rename_error: None,
+            keeper_pattern: String::new(),
+            selected_empty_files: HashSet::new(),
+            result_windows: Vec::new(),
+            next_window_id: 0,
+            file_flags: HashMap::new(),
+            show_flagged_only: false,
+            hide_protected_groups: false,
+            group_thumbnails: GroupThumbnails::default(),
+            focused_row: None,
+            scroll_to_focus: false,
+            invert_includes_keepers: false,
+            auto_expand_groups: DEFAULT_AUTO_EXPAND_GROUPS,
+            expanded_groups: HashSet::new(),
+            folder_breakdown: Vec::new(),
             status_message: None,
         }
     }
 }
 
 impl FileXSorterApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self::default()
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            app.scan_profiles = eframe::get_value(storage, SCAN_PROFILES_KEY).unwrap_or_default();
+            app.auto_expand_groups =
+                eframe::get_value(storage, AUTO_EXPAND_KEY).unwrap_or(DEFAULT_AUTO_EXPAND_GROUPS);
+            app.remember_last_scan = eframe::get_value(storage, REMEMBER_SCAN_KEY).unwrap_or(false);
+            app.use_hash_cache = eframe::get_value(storage, HASH_CACHE_KEY).unwrap_or(true);
+            if eframe::get_value(storage, OPERATION_LOG_KEY).unwrap_or(false) {
+                app.file_ops.log_file = FileOperations::default_log_path();
+            }
+            app.file_ops.use_recycle_bin =
+                eframe::get_value(storage, RECYCLE_BIN_KEY).unwrap_or(true);
+            if let Some(config) = eframe::get_value(storage, SCAN_CONFIG_KEY) {
+                app.scan_config = config;
+                app.sync_filter_inputs();
+            }
+            app.show_preview_panel = eframe::get_value(storage, PREVIEW_PANEL_KEY).unwrap_or(true);
+            if let Some(width) = eframe::get_value(storage, PREVIEW_WIDTH_KEY) {
+                app.preview_panel_width = width;
+            }
+            app.theme = eframe::get_value(storage, THEME_KEY).unwrap_or_default();
+            // Folders deleted or unplugged since last time are dropped quietly
+            let folders: Vec<VolumePath> =
+                eframe::get_value(storage, FOLDERS_KEY).unwrap_or_default();
+            app.selected_folders = folders
+                .iter()
+                .map(VolumePath::resolve)
+                .filter(|f| Self::is_valid_folder(f))
+                .collect();
+            if app.remember_last_scan {
+                app.file_flags = eframe::get_value(storage, FILE_FLAGS_KEY).unwrap_or_default();
+                app.restore_last_scan();
+            }
+        }
+        cc.egui_ctx.set_theme(app.theme.preference());
+        app
+    }
+
+    /// Load the saved scan from disk and flag how stale it looks
+    fn restore_last_scan(&mut self) {
+        let Some(path) = SavedScan::default_path() else {
+            return;
+        };
+        if !path.exists() {
+            return;
+        }
+        match SavedScan::load(&path) {
+            Ok(saved) => {
+                let staleness = saved.check_staleness();
+                self.selected_folders = saved.folders.iter().map(VolumePath::resolve).collect();
+                self.loose_files = saved.loose_files;
+                self.expanded_groups =
+                    Self::top_groups_by_waste(&saved.result, self.auto_expand_groups);
+                self.folder_breakdown = saved.result.wasted_by_folder();
+                let mut result = saved.result;
+                // Saved before results recorded their folders
+                if result.folders.is_empty() {
+                    result.folders = self.selected_folders.clone();
+                }
+                self.scan_result = Some(result);
+                self.apply_group_sort();
+                self.scan_finished_at = saved.saved_at;
+                self.restored_scan = Some(staleness);
+            }
+            Err(e) => {
+                self.status_message = Some((
+                    format!("Couldn't restore last scan: {}", e),
+                    MessageType::Error,
+                ));
+            }
+        }
+    }
+
+    /// Write the current result to disk if it changed since the last save
+    fn save_last_scan(&mut self) {
+        if !self.remember_last_scan || !self.session_dirty || self.is_scanning {
+            return;
+        }
+        let Some(path) = SavedScan::default_path() else {
+            return;
+        };
+        let outcome = match &self.scan_result {
+            Some(result) => SavedScan {
+                saved_at: self.scan_finished_at,
+                folders: self
+                    .selected_folders
+                    .iter()
+                    .map(|f| VolumePath::from_path(f))
+                    .collect(),
+                loose_files: self.loose_files.clone(),
+                result: result.clone(),
+            }
+            .save(&path),
+            None => fs::remove_file(&path).or(Ok(())),
+        };
+        match outcome {
+            Ok(()) => self.session_dirty = false,
+            Err(e) => log::warn!("Failed to save last scan: {}", e),
+        }
     }
 
+    /// Expects an extension from `normalized_extension`
     fn get_file_type(extension: &str) -> FileType {
-        match extension.to_lowercase().as_str() {
+        match extension {
             "png" | "jpg" | "jpeg" | "bmp" | "ico" | "webp" | "tiff" | "tif" => FileType::Image,
             "gif" => FileType::Gif,
             "mp4" | "avi" | "mkv" | "mov" | "wmv" | "flv" | "webm" | "m4v" | "mpeg" | "mpg" => {
@@ -171,8 +830,25 @@ impl FileXSorterApp {
         let _ = open::that(path);
     }
 
+    /// Open the folder holding `path` with the file selected; outside Windows
+    /// the file manager can't be told what to select, so just the folder opens
+    fn open_folder_and_select_file(path: &Path) -> std::io::Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            Command::new("explorer")
+                .arg("/select,")
+                .arg(filexsorter::paths::long_path(path))
+                .spawn()
+                .map(|_| ())
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            open::that(path.parent().unwrap_or(path))
+        }
+    }
+
     /// Open folder and select the specific file in Windows Explorer (with path validation)
-    fn open_folder_and_select_file_safe(&mut self, path: &PathBuf) {
+    fn open_folder_and_select_file_safe(&mut self, path: &Path) {
         let safe_path = match Self::sanitize_path_for_explorer(path) {
             Ok(p) => p,
             Err(e) => {
@@ -181,79 +857,141 @@ impl FileXSorterApp {
             }
         };
 
-        #[cfg(target_os = "windows")]
-        {
-            let _ = Command::new("explorer")
-                .arg("/select,")
-                .arg(&safe_path)
-                .spawn();
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            if let Some(parent) = safe_path.parent() {
-                let _ = open::that(parent);
-            }
+        if let Err(e) = Self::open_folder_and_select_file(&safe_path) {
+            self.status_message = Some((
+                format!("Failed to open {}: {}", safe_path.display(), e),
+                MessageType::Error,
+            ));
         }
     }
 
-    /// Open folder and select file (static version for use in render functions)
-    fn open_folder_and_select_file(path: &PathBuf) {
-        #[cfg(target_os = "windows")]
-        {
-            let _ = Command::new("explorer").arg("/select,").arg(path).spawn();
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            if let Some(parent) = path.parent() {
-                let _ = open::that(parent);
-            }
-        }
+    fn has_scan_inputs(&self) -> bool {
+        !self.selected_folders.is_empty() || !self.loose_files.is_empty()
     }
 
     fn start_scan(&mut self) {
-        if self.selected_folders.is_empty() {
+        if !self.has_scan_inputs() {
             self.status_message = Some((
-                "Please add at least one folder to scan.".to_string(),
+                "Please add at least one folder or drop files to scan.".to_string(),
                 MessageType::Error,
             ));
             return;
         }
+        self.scan(self.selected_folders.clone(), self.loose_files.clone());
+    }
+
+    /// Scan only `folder`, keeping the other folders' results. Their grouped
+    /// files are rescanned alongside it so groups spanning both stay whole;
+    /// copies of files that weren't duplicates before show up on the next
+    /// full scan.
+    fn rescan_folder(&mut self, folder: PathBuf) {
+        let mut loose_files = self.loose_files.clone();
+        let mut folders = None;
+        if let Some(result) = &self.scan_result {
+            folders = Some(result.folders.clone());
+            loose_files.extend(
+                result
+                    .duplicate_groups
+                    .iter()
+                    .flat_map(|group| &group.files)
+                    .map(|file| &file.path)
+                    .filter(|path| !path.starts_with(&folder) && !loose_files.contains(path))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            );
+        }
+        self.scan(vec![folder.clone()], loose_files);
+        if self.is_scanning {
+            self.rescanned_folders = folders;
+            self.status_message = Some((
+                format!("Rescanning {}...", folder.display()),
+                MessageType::Info,
+            ));
+        }
+    }
 
+    fn scan(&mut self, folders: Vec<PathBuf>, loose_files: Vec<PathBuf>) {
         self.is_scanning = true;
         self.scan_result = None;
+        self.watcher = None;
+        self.new_duplicates.clear();
+        self.missing_files.clear();
         self.selected_files.clear();
         self.preview_file = None;
         self.loaded_images.clear();
         self.image_access_order.clear();
+        self.failed_images.clear();
+        self.gif_animations.clear();
+        self.media_previews.clear();
+        self.reverify_jobs.clear();
+        self.group_thumbnails.clear();
+        self.selected_empty_files.clear();
+        self.focused_row = None;
+        self.restored_scan = None;
+        self.rescanned_folders = None;
         self.scan_state = Arc::new(ScanState::new());
 
-        let folders = self.selected_folders.clone();
-        let recursive = self.recursive_scan;
+        let message = if loose_files.is_empty() {
+            format!("Scanning {} folder(s)...", folders.len())
+        } else {
+            format!(
+                "Scanning {} folder(s) and {} loose file(s)...",
+                folders.len(),
+                loose_files.len()
+            )
+        };
+        let config = self.scan_config.clone();
         let scan_state = Arc::clone(&self.scan_state);
+        let hash_cache = self.hash_cache.clone().filter(|_| self.use_hash_cache);
+        // Loading a large cache takes a while, so the scan thread opens it
+        let open_cache = self.use_hash_cache && hash_cache.is_none();
 
-        let handle = thread::spawn(move || {
-            let config = ScannerConfig {
-                recursive,
-                min_size: 1,
-            };
-            let scanner = Scanner::new(config);
-            let result = scanner.scan_directories_with_progress(
-                &folders,
-                &scan_state.progress_current,
-                &scan_state.progress_total,
-                &scan_state.cancel_flag,
-            );
-            if let Ok(mut guard) = scan_state.result.lock() {
-                *guard = Some(result);
+        let spawned = thread::Builder::new()
+            .name("fxs-scan".into())
+            .spawn(move || {
+                let hash_cache = hash_cache.or_else(|| {
+                    let path = HashCache::default_path().filter(|_| open_cache)?;
+                    let cache = Arc::new(Mutex::new(HashCache::open(
+                        path,
+                        hash_cache::DEFAULT_CAPACITY,
+                    )));
+                    if let Ok(mut opened) = scan_state.opened_cache.lock() {
+                        *opened = Some(Arc::clone(&cache));
+                    }
+                    Some(cache)
+                });
+                let mut scanner = Scanner::new(config);
+                if let Some(cache) = &hash_cache {
+                    scanner = scanner.with_hash_cache(Arc::clone(cache));
+                }
+                let result = scanner.scan_paths_with_progress(
+                    &folders,
+                    &loose_files,
+                    &scan_state.progress,
+                    &scan_state.cancel_flag,
+                );
+                if let Some(Ok(mut cache)) = hash_cache.as_ref().map(|c| c.lock()) {
+                    if let Err(e) = cache.save() {
+                        log::warn!("Failed to save hash cache: {}", e);
+                    }
+                }
+                if let Ok(mut guard) = scan_state.result.lock() {
+                    *guard = Some(result);
+                }
+                scan_state.is_complete.store(true, Ordering::SeqCst);
+            });
+        let handle = match spawned {
+            Ok(handle) => handle,
+            Err(e) => {
+                self.is_scanning = false;
+                self.status_message =
+                    Some((format!("Failed to start scan: {}", e), MessageType::Error));
+                return;
             }
-            scan_state.is_complete.store(true, Ordering::SeqCst);
-        });
+        };
 
         self.scan_handle = Some(handle);
-        self.status_message = Some((
-            format!("Scanning {} folder(s)...", self.selected_folders.len()),
-            MessageType::Info,
-        ));
+        self.status_message = Some((message, MessageType::Info));
     }
 
     fn check_scan_complete(&mut self) {
@@ -264,13 +1002,44 @@ impl FileXSorterApp {
         if let Ok(mut guard) = self.scan_state.result.lock() {
             self.scan_result = guard.take();
         }
+        if let (Some(result), Some(folders)) =
+            (self.scan_result.as_mut(), self.rescanned_folders.take())
+        {
+            result.folders = folders;
+        }
+        if let Some(cache) = self
+            .scan_state
+            .opened_cache
+            .lock()
+            .ok()
+            .and_then(|mut opened| opened.take())
+        {
+            if self.use_hash_cache {
+                self.hash_cache = Some(cache);
+            }
+        }
         self.is_scanning = false;
+        let cancelled = self.scan_state.cancel_flag.load(Ordering::SeqCst);
+        if cancelled
+            && self
+                .scan_result
+                .as_ref()
+                .is_none_or(|r| r.duplicate_groups.is_empty())
+        {
+            self.scan_result = None;
+        }
+        self.apply_group_sort();
 
         if let Some(handle) = self.scan_handle.take() {
             let _ = handle.join();
         }
 
+        self.restart_watcher();
         if let Some(ref result) = self.scan_result {
+            self.scan_finished_at = chrono::Local::now().timestamp();
+            self.session_dirty = true;
+            self.expanded_groups = Self::top_groups_by_waste(result, self.auto_expand_groups);
+            self.folder_breakdown = result.wasted_by_folder();
             self.status_message = if result.duplicate_groups.is_empty() {
                 Some(("No duplicates found.".to_string(), MessageType::Success))
             } else {
@@ -284,35 +1053,147 @@ impl FileXSorterApp {
                     MessageType::Success,
                 ))
             };
+            let partial = if cancelled {
+                Some("scan cancelled")
+            } else if result.time_limit_reached {
+                Some("time limit reached")
+            } else {
+                None
+            };
+            if let (Some(reason), Some((message, kind))) = (partial, self.status_message.take()) {
+                self.status_message = Some((format!("{} (partial, {})", message, reason), kind));
+            }
+        } else if cancelled {
+            self.status_message = Some(("Scan cancelled.".to_string(), MessageType::Info));
         }
     }
 
+    /// Ask the scan to stop; `check_scan_complete` picks up the groups it found so far
     fn cancel_scan(&mut self) {
         self.scan_state.cancel_flag.store(true, Ordering::SeqCst);
-        self.is_scanning = false;
-        self.status_message = Some(("Scan cancelled.".to_string(), MessageType::Info));
+        self.status_message = Some(("Cancelling scan...".to_string(), MessageType::Info));
     }
 
     fn get_selected_paths(&self) -> Vec<PathBuf> {
-        let mut paths = Vec::new();
-        if let Some(ref result) = self.scan_result {
-            for (group_idx, file_idx) in &self.selected_files {
-                if let Some(group) = result.duplicate_groups.get(*group_idx) {
-                    if let Some(file) = group.files.get(*file_idx) {
-                        paths.push(file.path.clone());
+        match self.scan_result {
+            Some(ref result) => self.selected_paths(result, &self.selected_files),
+            None => Vec::new(),
+        }
+    }
+
+    /// Pair each selected duplicate with its group's keeper; keepers themselves are skipped
+    fn link_targets(&self, result: &ScanResult) -> Vec<(PathBuf, PathBuf)> {
+        let protected = &self.scan_config.protected_folders;
+        self.selected_files
+            .iter()
+            .filter(|(_, file_idx)| *file_idx > 0)
+            .filter_map(|(group_idx, file_idx)| {
+                let group = result.duplicate_groups.get(*group_idx)?;
+                // Similar images aren't the same content, so linking would replace one with another
+                if group.kind == GroupKind::SimilarImage {
+                    return None;
+                }
+                let file = group.files.get(*file_idx)?;
+                (!is_protected(&file.path, protected))
+                    .then(|| (file.path.clone(), group.files[0].path.clone()))
+            })
+            .collect()
+    }
+
+    /// Pair each selected path with the folder the current move layout puts it in
+    fn move_targets(&self, result: &ScanResult, dest: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let protected = &self.scan_config.protected_folders;
+        let base = match self.move_layout {
+            MoveLayout::PreserveStructure => {
+                let sources = self.selected_paths(result, &self.selected_files);
+                file_ops::common_base(sources.iter().map(PathBuf::as_path))
+            }
+            _ => None,
+        };
+        let mut moves = Vec::new();
+        for (group_idx, file_idx) in &self.selected_files {
+            if let Some(group) = result.duplicate_groups.get(*group_idx) {
+                if let Some(file) = group.files.get(*file_idx) {
+                    if is_protected(&file.path, protected) {
+                        continue;
                     }
+                    let dir = self.move_layout.target_dir(
+                        dest,
+                        &file.path,
+                        base.as_deref(),
+                        *group_idx,
+                        &group.hash,
+                    );
+                    moves.push((file.path.clone(), dir));
                 }
             }
         }
-        paths
+        moves
+    }
+
+    /// Plan renaming the selected files with `rename_pattern`
+    fn rename_targets(&self, result: &ScanResult) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        let pattern = RenamePattern::parse(&self.rename_pattern)?;
+        let protected = &self.scan_config.protected_folders;
+        let items: Vec<RenameItem> = self
+            .selected_files
+            .iter()
+            .filter_map(|&(group_idx, file_idx)| {
+                let group = result.duplicate_groups.get(group_idx)?;
+                let file = group.files.get(file_idx)?;
+                (!is_protected(&file.path, protected)).then(|| RenameItem {
+                    path: file.path.clone(),
+                    hash: group.hash.clone(),
+                    position: file_idx,
+                })
+            })
+            .collect();
+        file_ops::plan_renames(&pattern, &items)
+    }
+
+    /// Show the rename dialog for the current selection, or why it can't be renamed
+    fn open_rename_dialog(&mut self, result: &ScanResult) {
+        let renames = match self.rename_targets(result) {
+            Ok(renames) => {
+                self.rename_error = None;
+                renames
+            }
+            Err(e) => {
+                self.rename_error = Some(e);
+                Vec::new()
+            }
+        };
+        self.show_confirmation_dialog = Some(ConfirmationDialog::RenameFiles(renames));
+        self.operation_preview = None;
+    }
+
+    /// Resolve a selection to paths, dropping anything that has since become protected
+    fn selected_paths(&self, result: &ScanResult, selected: &[(usize, usize)]) -> Vec<PathBuf> {
+        Self::selected_entries(result, selected, &self.scan_config.protected_folders)
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// Selected files outside the protected folders
+    fn selected_entries<'a>(
+        result: &'a ScanResult,
+        selected: &'a [(usize, usize)],
+        protected: &'a [PathBuf],
+    ) -> impl Iterator<Item = &'a FileEntry> {
+        selected
+            .iter()
+            .filter_map(|(group_idx, file_idx)| {
+                result
+                    .duplicate_groups
+                    .get(*group_idx)?
+                    .files
+                    .get(*file_idx)
+            })
+            .filter(|file| !is_protected(&file.path, protected))
     }
 
     fn load_file_preview(&mut self, file: &FileEntry) {
-        let extension = file
-            .path
-            .extension()
-            .map(|e| e.to_string_lossy().to_lowercase())
-            .unwrap_or_default();
+        let extension = normalized_extension(&file.path);
         let file_type = Self::get_file_type(&extension);
 
         let preview_text = if file_type == FileType::Text && file.size < 50 * 1024 {
@@ -324,11 +1205,14 @@ impl FileXSorterApp {
         };
 
         let dimensions = if file_type == FileType::Image || file_type == FileType::Gif {
-            image::image_dimensions(&file.path).ok()
+            media::oriented_dimensions(&file.path).ok()
         } else {
             None
         };
 
+        // Explicitly re-opening a preview retries a previously failed decode
+        self.failed_images.remove(&file.path);
+        self.preview_highlight = None;
         self.preview_file = Some(FilePreview {
             path: file.path.clone(),
             name: file.name.clone(),
@@ -340,87 +1224,635 @@ impl FileXSorterApp {
         });
     }
 
+    /// The preview text coloured for its file type, built once per preview
+    /// and colour scheme; `None` for plain text
+    fn highlighted_text(
+        &mut self,
+        text: &str,
+        extension: &str,
+        dark: bool,
+    ) -> Option<egui::text::LayoutJob> {
+        if let Some((built_dark, job)) = &self.preview_highlight {
+            if *built_dark == dark {
+                return job.clone();
+            }
+        }
+        let spans = self
+            .highlighter
+            .get_or_insert_with(Highlighter::default)
+            .highlight(text, extension, dark);
+        let job = spans.map(|spans| {
+            let mut job = egui::text::LayoutJob::default();
+            for span in spans {
+                let [r, g, b] = span.color;
+                job.append(
+                    &span.text,
+                    0.0,
+                    egui::TextFormat {
+                        font_id: egui::FontId::monospace(10.0),
+                        color: egui::Color32::from_rgb(r, g, b),
+                        italics: span.italic,
+                        ..Default::default()
+                    },
+                );
+            }
+            job
+        });
+        self.preview_highlight = Some((dark, job.clone()));
+        job
+    }
+
     fn load_image_texture(
         &mut self,
         ctx: &egui::Context,
         path: &PathBuf,
         max_size: f32,
     ) -> Option<egui::TextureHandle> {
-        // Check cache first
+        // Check cache first, dropping handles whose texture the backend no longer knows
         if let Some(texture) = self.loaded_images.get(path) {
-            return Some(texture.clone());
-        }
-
-        // Security: Validate file size before loading
-        let metadata = match fs::metadata(path) {
-            Ok(m) => m,
-            Err(_) => return None,
-        };
-
-        if metadata.len() > MAX_IMAGE_BYTES {
-            return None;
-        }
-
-        // Security: Validate image dimensions before loading
-        if let Ok(dimensions) = image::image_dimensions(path) {
-            if dimensions.0 > MAX_IMAGE_DIMENSIONS || dimensions.1 > MAX_IMAGE_DIMENSIONS {
-                return None;
+            if ctx.tex_manager().read().meta(texture.id()).is_some() {
+                let texture = texture.clone();
+                self.touch_image(path);
+                return Some(texture);
             }
-        } else {
+            self.forget_image(path);
+        }
+        if self.failed_images.contains(path) {
             return None;
         }
 
-        // Load and resize image
-        if let Ok(img) = image::open(path) {
-            let img = img.thumbnail(max_size as u32, max_size as u32);
-            let img = img.to_rgba8();
-            let size = [img.width() as usize, img.height() as usize];
-            let pixels = img.into_raw();
-            let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &pixels);
+        if let Some(color_image) = Self::decode_image(path, max_size as u32) {
             let texture = ctx.load_texture(
                 path.to_string_lossy(),
                 color_image,
                 egui::TextureOptions::LINEAR,
             );
 
-            // LRU cache management
-            if self.loaded_images.len() >= MAX_CACHED_IMAGES {
-                if let Some(oldest) = self.image_access_order.pop_front() {
-                    self.loaded_images.remove(&oldest);
-                }
-            }
-
             self.loaded_images.insert(path.clone(), texture.clone());
             self.image_access_order.push_back(path.clone());
+            self.evict_images();
             Some(texture)
         } else {
+            // Remember the failure so a broken file isn't re-decoded every frame
+            self.failed_images.insert(path.clone());
             None
         }
     }
 
-    fn render_header(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            ui.heading("FileXSorter");
-            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label("v0.3.3");
-                ui.separator();
-                ui.checkbox(&mut self.show_preview_panel, "Preview");
-            });
-        });
-        ui.separator();
-    }
-
-    fn render_folder_selection(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            ui.label("Folders:");
-            if ui.button("Add").clicked() && !self.is_scanning {
-                if let Some(folder) = FileDialog::new().pick_folder() {
-                    if Self::is_valid_folder(&folder) && !self.selected_folders.contains(&folder) {
-                        self.selected_folders.push(folder);
-                    } else if !Self::is_valid_folder(&folder) {
-                        self.status_message =
-                            Some(("Invalid folder selected".to_string(), MessageType::Error));
-                    }
+    /// The frame of an animated GIF preview due now, or `None` for a still
+    /// image or while its frames are still being decoded
+    fn gif_frame(&mut self, ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+        let cached = match self.gif_animations.iter().position(|(p, _)| p == path) {
+            Some(index) => self.gif_animations.remove(index),
+            None => {
+                if self.gif_animations.len() >= MAX_CACHED_ANIMATIONS {
+                    self.gif_animations.pop_front();
+                }
+                Some((
+                    path.to_path_buf(),
+                    Self::decode_gif_animation(ctx, path, self.preview_max_size),
+                ))
+            }
+        };
+        self.gif_animations.extend(cached);
+
+        let (_, preview) = self.gif_animations.back_mut()?;
+        if let GifPreview::Decoding(rx) = preview {
+            match rx.try_recv() {
+                Ok(Some(frames)) => {
+                    *preview = GifPreview::Ready(Self::upload_gif_frames(ctx, path, frames));
+                    self.evict_images();
+                }
+                Ok(None) | Err(mpsc::TryRecvError::Disconnected) => *preview = GifPreview::Still,
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+
+        let (_, GifPreview::Ready(animation)) = self.gif_animations.back()? else {
+            return None;
+        };
+        let elapsed = (ctx.input(|i| i.time) - animation.started).max(0.0);
+        let (index, until_next) = media::frame_at(
+            &animation.delays,
+            std::time::Duration::from_secs_f64(elapsed),
+        );
+        ctx.request_repaint_after(until_next);
+        Some(animation.frames[index].clone())
+    }
+
+    /// Start decoding every frame of a GIF on a worker thread. It sends
+    /// `None` if the GIF has a single frame or can't be decoded.
+    fn decode_gif_animation(ctx: &egui::Context, path: &Path, max_size: u32) -> GifPreview {
+        let (tx, rx) = mpsc::channel();
+        let path = path.to_path_buf();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let frames = Self::image_within_limits(&path)
+                .then(|| {
+                    media::decode_gif_frames(&path, max_size, MAX_ANIMATION_BYTES)
+                        .map_err(|e| log::warn!("Can't decode {}: {}", path.display(), e))
+                        .ok()
+                })
+                .flatten()
+                .filter(|frames| frames.len() > 1)
+                .map(|frames| {
+                    frames
+                        .into_iter()
+                        .map(|(image, delay)| {
+                            let size = [image.width() as usize, image.height() as usize];
+                            let color_image =
+                                egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+                            (color_image, delay)
+                        })
+                        .collect()
+                });
+            let _ = tx.send(frames);
+            ctx.request_repaint();
+        });
+        GifPreview::Decoding(rx)
+    }
+
+    /// Turn decoded GIF frames into textures, starting the animation now
+    fn upload_gif_frames(
+        ctx: &egui::Context,
+        path: &Path,
+        frames: Vec<(egui::ColorImage, std::time::Duration)>,
+    ) -> GifAnimation {
+        let (frames, delays) = frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, (color_image, delay))| {
+                let texture = ctx.load_texture(
+                    format!("gif:{}#{}", path.display(), i),
+                    color_image,
+                    egui::TextureOptions::LINEAR,
+                );
+                (texture, delay)
+            })
+            .unzip();
+        GifAnimation {
+            frames,
+            delays,
+            started: ctx.input(|i| i.time),
+        }
+    }
+
+    /// Security: refuse files too large, by bytes or dimensions, to decode safely
+    fn image_within_limits(path: &Path) -> bool {
+        let small_enough = fs::metadata(path).is_ok_and(|m| m.len() <= MAX_IMAGE_BYTES);
+        small_enough
+            && image::image_dimensions(path)
+                .is_ok_and(|(w, h)| w <= MAX_IMAGE_DIMENSIONS && h <= MAX_IMAGE_DIMENSIONS)
+    }
+
+    /// Decode and downscale an image, refusing files that are too large to load safely
+    fn decode_image(path: &Path, max_size: u32) -> Option<egui::ColorImage> {
+        if !Self::image_within_limits(path) {
+            return None;
+        }
+
+        let img = media::open_oriented(path).ok()?;
+        let img = img.thumbnail(max_size, max_size).to_rgba8();
+        let size = [img.width() as usize, img.height() as usize];
+        Some(egui::ColorImage::from_rgba_unmultiplied(size, img.as_raw()))
+    }
+
+    /// Small texture for the delete confirmation strip.
+    ///
+    /// Decodes at most `budget` new images per call so the dialog stays responsive;
+    /// `None` in the map marks a file that couldn't be decoded.
+    fn load_thumbnail(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+        budget: &mut usize,
+    ) -> Option<egui::TextureHandle> {
+        if let Some(cached) = self.thumbnails.get(path) {
+            return cached.clone();
+        }
+        if *budget == 0 {
+            ctx.request_repaint();
+            return None;
+        }
+        *budget -= 1;
+
+        let texture = Self::decode_image(path, THUMBNAIL_SIZE).map(|image| {
+            ctx.load_texture(
+                format!("thumb:{}", path.display()),
+                image,
+                egui::TextureOptions::LINEAR,
+            )
+        });
+        self.thumbnails.insert(path.to_path_buf(), texture.clone());
+        texture
+    }
+
+    /// Thumbnails and a scrollable list of the files an operation will touch,
+    /// with any selected keepers called out
+    fn render_file_preview(&mut self, ui: &mut egui::Ui, paths: &[PathBuf]) {
+        const MAX_THUMBNAILS: usize = 12;
+        const MAX_LISTED: usize = 20;
+
+        let keepers = self.selected_keepers(paths);
+        if !keepers.is_empty() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "⚠ {} selected file(s) are marked [KEEP] in their group",
+                    keepers.len()
+                ))
+                .color(egui::Color32::RED),
+            );
+        }
+
+        let images: Vec<&PathBuf> = paths
+            .iter()
+            .filter(|p| {
+                matches!(
+                    Self::get_file_type(&normalized_extension(p)),
+                    FileType::Image | FileType::Gif
+                )
+            })
+            .collect();
+
+        if !images.is_empty() {
+            let mut budget = 2;
+            ui.horizontal_wrapped(|ui| {
+                for path in images.iter().take(MAX_THUMBNAILS) {
+                    let size = egui::vec2(THUMBNAIL_SIZE as f32, THUMBNAIL_SIZE as f32);
+                    let response = match self.load_thumbnail(ui.ctx(), path, &mut budget) {
+                        Some(texture) => ui.add(
+                            egui::Image::new(&texture)
+                                .max_size(size)
+                                .maintain_aspect_ratio(true),
+                        ),
+                        None => ui.add_sized(size, egui::Label::new("🖼")),
+                    };
+                    response.on_hover_text(path.display().to_string());
+                }
+                if images.len() > MAX_THUMBNAILS {
+                    ui.label(format!("+{} more", images.len() - MAX_THUMBNAILS));
+                }
+            });
+        }
+
+        // Images are listed too, since thumbnails alone don't say where a file lives
+        egui::ScrollArea::vertical()
+            .id_salt("confirm_file_list")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for path in paths.iter().take(MAX_LISTED) {
+                    let text = path.display().to_string();
+                    if keepers.contains(path) {
+                        ui.label(
+                            egui::RichText::new(format!("[KEEP] {}", text))
+                                .small()
+                                .strong()
+                                .color(egui::Color32::RED),
+                        )
+                        .on_hover_text("This is the copy the group keeps");
+                    } else {
+                        ui.label(egui::RichText::new(text).small());
+                    }
+                }
+                if paths.len() > MAX_LISTED {
+                    ui.label(
+                        egui::RichText::new(format!("and {} more", paths.len() - MAX_LISTED))
+                            .small(),
+                    );
+                }
+            });
+    }
+
+    /// Which of `paths` are the keeper of their group in any open result
+    fn selected_keepers(&self, paths: &[PathBuf]) -> HashSet<PathBuf> {
+        let keepers: HashSet<&PathBuf> = self
+            .scan_result
+            .iter()
+            .chain(self.result_windows.iter().map(|w| &w.result))
+            .flat_map(|r| &r.duplicate_groups)
+            .filter_map(|g| g.files.first().map(|f| &f.path))
+            .collect();
+        paths
+            .iter()
+            .filter(|p| keepers.contains(p))
+            .cloned()
+            .collect()
+    }
+
+    /// Mark a cached preview as the most recently used
+    fn touch_image(&mut self, path: &Path) {
+        if self.image_access_order.back().map(PathBuf::as_path) != Some(path) {
+            self.image_access_order.retain(|p| p != path);
+            self.image_access_order.push_back(path.to_path_buf());
+        }
+    }
+
+    /// Drop least recently used previews until the cache fits its count and
+    /// memory limits, always keeping the newest image and animation
+    fn evict_images(&mut self) {
+        let texture_bytes = |texture: &egui::TextureHandle| {
+            let [w, h] = texture.size();
+            w * h * 4
+        };
+        let animation_bytes = |preview: &GifPreview| match preview {
+            GifPreview::Ready(animation) => animation.frames.iter().map(texture_bytes).sum(),
+            _ => 0,
+        };
+        let mut bytes: usize = self
+            .loaded_images
+            .values()
+            .map(texture_bytes)
+            .sum::<usize>()
+            + self
+                .gif_animations
+                .iter()
+                .map(|(_, preview)| animation_bytes(preview))
+                .sum::<usize>();
+        // Animations take the most memory, so older ones go before any still image
+        while self.gif_animations.len() > 1 && bytes > MAX_CACHED_IMAGE_BYTES {
+            let Some((_, oldest)) = self.gif_animations.pop_front() else {
+                break;
+            };
+            bytes -= animation_bytes(&oldest);
+        }
+        while self.image_access_order.len() > 1
+            && (self.loaded_images.len() > MAX_CACHED_IMAGES || bytes > MAX_CACHED_IMAGE_BYTES)
+        {
+            let Some(oldest) = self.image_access_order.pop_front() else {
+                break;
+            };
+            if let Some(texture) = self.loaded_images.remove(&oldest) {
+                bytes -= texture_bytes(&texture);
+            }
+        }
+    }
+
+    fn forget_image(&mut self, path: &Path) {
+        self.loaded_images.remove(path);
+        self.image_access_order.retain(|p| p != path);
+        self.gif_animations.retain(|(p, _)| p != path);
+    }
+
+    /// Re-upload the current preview after the window comes back from minimize or
+    /// loses focus, since some backends drop textures in between
+    fn revalidate_preview(&mut self, ctx: &egui::Context) {
+        let (active, minimized) = ctx.input(|i| (i.focused, i.viewport().minimized));
+        let active = active && minimized != Some(true);
+        if active && !self.window_active {
+            self.failed_images.clear();
+            if let Some(path) = self.preview_file.as_ref().map(|p| p.path.clone()) {
+                self.forget_image(&path);
+            }
+            ctx.request_repaint();
+        }
+        self.window_active = active;
+    }
+
+    /// Start or poll background probing of a media file, extracting a frame for videos
+    fn poll_media_preview(&mut self, ctx: &egui::Context, path: &Path, extract_frame: bool) {
+        let max_width = self.preview_max_size;
+        let state = self
+            .media_previews
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let (tx, rx) = mpsc::channel();
+                let path = path.to_path_buf();
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    let info = if extract_frame {
+                        media::probe_video(&path)
+                    } else {
+                        media::probe_media(&path)
+                    };
+                    let result = info.map(|info| {
+                        let frame = if extract_frame && info.video_codec.is_some() {
+                            media::extract_video_frame(&path, &info, max_width)
+                        } else {
+                            None
+                        };
+                        (info, frame)
+                    });
+                    let _ = tx.send(result);
+                    ctx.request_repaint();
+                });
+                MediaPreview::Loading(rx)
+            });
+
+        if let MediaPreview::Loading(rx) = state {
+            match rx.try_recv() {
+                Ok(Some((info, frame))) => {
+                    let texture = frame.map(|img| {
+                        let size = [img.width() as usize, img.height() as usize];
+                        let color_image =
+                            egui::ColorImage::from_rgba_unmultiplied(size, &img.into_raw());
+                        ctx.load_texture(
+                            format!("video:{}", path.display()),
+                            color_image,
+                            egui::TextureOptions::LINEAR,
+                        )
+                    });
+                    *state = MediaPreview::Ready(info, texture);
+                }
+                Ok(None) | Err(mpsc::TryRecvError::Disconnected) => {
+                    *state = MediaPreview::Unavailable;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+    }
+
+    /// Only the previewed file may keep playing
+    fn stop_audio_if_deselected(&mut self) {
+        if let Some(playing) = self.audio_player.playing() {
+            if self.preview_file.as_ref().map(|p| p.path.as_path()) != Some(playing) {
+                self.audio_player.stop();
+            }
+        }
+    }
+
+    /// Play/stop button and progress bar for an audio preview
+    fn render_audio_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let playing = self.audio_player.playing() == Some(path);
+        ui.horizontal(|ui| {
+            if playing {
+                if ui.button("⏹ Stop").clicked() {
+                    self.audio_player.stop();
+                }
+            } else {
+                let error = self.unplayable_audio.get(path);
+                let playable = audio::can_play_audio(path) && error.is_none();
+                let response = ui
+                    .add_enabled(playable, egui::Button::new("▶ Play"))
+                    .on_disabled_hover_text(
+                        error.map_or("This audio format can't be played", String::as_str),
+                    );
+                if response.clicked() {
+                    if let Err(e) = self.audio_player.play(path) {
+                        log::warn!("Can't play {}: {}", path.display(), e);
+                        self.unplayable_audio
+                            .insert(path.to_path_buf(), format!("Can't play: {}", e));
+                    }
+                }
+            }
+
+            if playing {
+                let position = self.audio_player.position().as_secs_f64();
+                let bar = match self.audio_player.duration() {
+                    Some(total) if !total.is_zero() => egui::ProgressBar::new(
+                        (position / total.as_secs_f64()) as f32,
+                    )
+                    .text(format!(
+                        "{} / {}",
+                        media::format_duration(position),
+                        media::format_duration(total.as_secs_f64())
+                    )),
+                    _ => egui::ProgressBar::new(0.0)
+                        .animate(true)
+                        .text(media::format_duration(position)),
+                };
+                ui.add(bar);
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(250));
+            }
+        });
+    }
+
+    /// Tags, then a `codec | bitrate | duration` line for an audio or video file
+    fn render_media_details(ui: &mut egui::Ui, info: &MediaInfo) {
+        if let Some(title) = &info.title {
+            ui.label(egui::RichText::new(title).strong());
+        }
+        let artist_album: Vec<&str> = [&info.artist, &info.album]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !artist_album.is_empty() {
+            ui.label(egui::RichText::new(artist_album.join(" - ")).small());
+        }
+
+        let mut details = Vec::new();
+        if let (Some(w), Some(h)) = (info.width, info.height) {
+            details.push(format!("{}x{}", w, h));
+        }
+        let codecs: Vec<&str> = [&info.video_codec, &info.audio_codec]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !codecs.is_empty() {
+            details.push(codecs.join("/"));
+        }
+        if let Some(bits) = info.bit_rate {
+            details.push(media::format_bit_rate(bits));
+        }
+        if let Some(secs) = info.duration_secs {
+            details.push(media::format_duration(secs));
+        }
+        ui.label(egui::RichText::new(details.join(" | ")).small());
+    }
+
+    /// Show a texture scaled down to fit the given bounds
+    fn show_scaled_texture(
+        ui: &mut egui::Ui,
+        texture: &egui::TextureHandle,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        let size = texture.size_vec2();
+        let scale = (max_width / size.x).min(max_height / size.y).min(1.0);
+        ui.image(egui::load::SizedTexture::new(texture.id(), size * scale));
+    }
+
+    /// Replace the current result with one saved earlier
+    fn load_session(&mut self) {
+        let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).pick_file() else {
+            return;
+        };
+        let result = match export::load_results_json(&path) {
+            Ok(result) => result,
+            Err(e) => {
+                self.status_message = Some((e, MessageType::Error));
+                return;
+            }
+        };
+
+        self.missing_files = result
+            .duplicate_groups
+            .iter()
+            .flat_map(|g| &g.files)
+            .filter(|f| !f.path.exists())
+            .map(|f| f.path.clone())
+            .collect();
+        self.status_message = Some((
+            format!(
+                "Loaded {} group(s) from {}{}",
+                result.duplicate_groups.len(),
+                path.display(),
+                if self.missing_files.is_empty() {
+                    String::new()
+                } else {
+                    format!(" | {} file(s) no longer exist", self.missing_files.len())
+                }
+            ),
+            MessageType::Success,
+        ));
+        self.expanded_groups = Self::top_groups_by_waste(&result, self.auto_expand_groups);
+        self.folder_breakdown = result.wasted_by_folder();
+        self.scan_result = Some(result);
+        self.selected_files.clear();
+        self.selected_empty_files.clear();
+        self.reverify_jobs.clear();
+        self.focused_row = None;
+        self.apply_group_sort();
+        self.preview_file = None;
+        self.restored_scan = None;
+        self.new_duplicates.clear();
+        self.session_dirty = true;
+        self.restart_watcher();
+    }
+
+    fn render_header(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("FileXSorter");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label("v0.3.3");
+                let before = self.theme;
+                egui::ComboBox::from_id_salt("theme")
+                    .selected_text(format!("Theme: {}", self.theme.label()))
+                    .show_ui(ui, |ui| {
+                        for theme in Theme::ALL {
+                            ui.selectable_value(&mut self.theme, theme, theme.label());
+                        }
+                    });
+                if self.theme != before {
+                    ui.ctx().set_theme(self.theme.preference());
+                }
+                ui.separator();
+                ui.checkbox(&mut self.show_preview_panel, "Preview");
+                if ui
+                    .add_enabled(!self.is_scanning, egui::Button::new("Load Session..."))
+                    .on_hover_text("Open results saved with Save Session")
+                    .clicked()
+                {
+                    self.load_session();
+                }
+            });
+        });
+        ui.separator();
+    }
+
+    fn render_folder_selection(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Folders:");
+            if ui.button("Add").clicked() && !self.is_scanning {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    if Self::is_valid_folder(&folder) && !self.selected_folders.contains(&folder) {
+                        self.selected_folders.push(folder);
+                    } else if !Self::is_valid_folder(&folder) {
+                        self.status_message =
+                            Some(("Invalid folder selected".to_string(), MessageType::Error));
+                    }
                 }
             }
             if ui
@@ -442,12 +1874,26 @@ impl FileXSorterApp {
                 .show(ui, |ui| {
                     ui.horizontal(|ui| {
                         let mut to_remove = None;
+                        let mut to_rescan = None;
                         for (idx, folder) in self.selected_folders.iter().enumerate() {
                             ui.group(|ui| {
                                 ui.horizontal(|ui| {
                                     if ui.small_button("X").clicked() && !self.is_scanning {
                                         to_remove = Some(idx);
                                     }
+                                    if ui
+                                        .add_enabled(
+                                            !self.is_scanning,
+                                            egui::Button::new("⟳").small(),
+                                        )
+                                        .on_hover_text(
+                                            "Rescan only this folder; copies in other folders \
+                                             show up again after a full scan",
+                                        )
+                                        .clicked()
+                                    {
+                                        to_rescan = Some(folder.clone());
+                                    }
                                     ui.label(folder.display().to_string());
                                 });
                             });
@@ -455,333 +1901,3157 @@ impl FileXSorterApp {
                         if let Some(idx) = to_remove {
                             self.selected_folders.remove(idx);
                         }
+                        if let Some(folder) = to_rescan {
+                            self.rescan_folder(folder);
+                        }
                     });
                 });
         }
 
+        self.render_loose_files(ui);
+        self.render_protected_folders(ui);
+        self.render_excluded_folders(ui);
+        self.render_scan_options(ui);
+        self.render_size_filter(ui);
+        self.render_extension_filter(ui);
+        self.render_scan_profiles(ui);
+
         ui.horizontal(|ui| {
-            ui.checkbox(&mut self.recursive_scan, "Subfolders");
             if self.is_scanning {
-                if ui.button("Cancel").clicked() {
+                let cancelling = self.scan_state.cancel_flag.load(Ordering::Relaxed);
+                if ui
+                    .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                    .clicked()
+                {
                     self.cancel_scan();
                 }
                 ui.spinner();
-                let current = self.scan_state.progress_current.load(Ordering::Relaxed);
-                let total = self.scan_state.progress_total.load(Ordering::Relaxed);
-                ui.label(if total > 0 {
-                    format!("{}/{}", current, total)
-                } else {
-                    "Scanning...".into()
+                let progress = self.scan_state.progress.snapshot();
+                let phase = progress.phase;
+                ui.label(match phase {
+                    ScanPhase::Collecting => {
+                        format!("{}... {} found", phase.label(), progress.files_found)
+                    }
+                    ScanPhase::Hashing => format!(
+                        "{} {}/{} ({} files found, {} groups so far)",
+                        phase.label(),
+                        progress.processed,
+                        progress.total,
+                        progress.files_found,
+                        progress.groups_found
+                    ),
                 });
             } else if ui.button("Scan").clicked() {
                 self.start_scan();
             }
         });
+        // Hashing is parallel, so this is just one of the files in flight
+        let current_file = self
+            .is_scanning
+            .then(|| self.scan_state.progress.current_file())
+            .flatten();
+        if let Some(file) = current_file {
+            ui.label(
+                egui::RichText::new(shorten_path(&file, CURRENT_FILE_CHARS))
+                    .small()
+                    .color(egui::Color32::GRAY),
+            )
+            .on_hover_text(file.display().to_string());
+        }
     }
 
-    fn render_results_only(&mut self, ui: &mut egui::Ui, _ctx: &egui::Context) {
-        let result = match self.scan_result.clone() {
-            Some(r) => r,
-            None => {
-                if !self.is_scanning {
-                    ui.vertical_centered(|ui| {
-                        ui.add_space(50.0);
-                        ui.label("Add folders and click 'Scan' to find duplicates.");
+    /// Individually dropped files, compared alongside the folders
+    fn render_loose_files(&mut self, ui: &mut egui::Ui) {
+        if self.loose_files.is_empty() {
+            if self.selected_folders.is_empty() {
+                ui.label(
+                    egui::RichText::new("Tip: drop folders onto the window to scan them, or files to compare just those")
+                        .small()
+                        .color(egui::Color32::GRAY),
+                );
+            }
+            return;
+        }
+
+        let mut to_remove = None;
+        let mut clear = false;
+        egui::CollapsingHeader::new(format!("{} loose file(s)", self.loose_files.len()))
+            .id_salt("loose_files")
+            .show(ui, |ui| {
+                clear = ui.small_button("Clear all").clicked();
+                egui::ScrollArea::vertical()
+                    .id_salt("loose_files_list")
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for (idx, path) in self.loose_files.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("X").clicked() {
+                                    to_remove = Some(idx);
+                                }
+                                ui.label(egui::RichText::new(path.display().to_string()).small());
+                            });
+                        }
                     });
+            });
+        if self.is_scanning {
+            return;
+        }
+        if clear {
+            self.loose_files.clear();
+        } else if let Some(idx) = to_remove {
+            self.loose_files.remove(idx);
+        }
+    }
+
+    /// Add folders dropped onto the window to the scan list and files to the loose file set
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .collect()
+        });
+        if dropped.is_empty() || self.is_scanning {
+            return;
+        }
+
+        let (folders, rest): (Vec<PathBuf>, Vec<PathBuf>) =
+            dropped.into_iter().partition(|p| Self::is_valid_folder(p));
+        let (files, others): (Vec<PathBuf>, Vec<PathBuf>) =
+            rest.into_iter().partition(|p| p.is_file());
+        let mut added_folders = 0;
+        for folder in folders {
+            if !self.selected_folders.contains(&folder) {
+                self.selected_folders.push(folder);
+                added_folders += 1;
+            }
+        }
+        let mut added_files = 0;
+        for file in files {
+            if !self.loose_files.contains(&file) {
+                self.loose_files.push(file);
+                added_files += 1;
+            }
+        }
+
+        let added = format!(
+            "Added {} folder(s) and {} loose file(s)",
+            added_folders, added_files
+        );
+        self.status_message = Some(if others.is_empty() {
+            (added, MessageType::Info)
+        } else {
+            (
+                format!("{}; {} unreadable item(s) skipped", added, others.len()),
+                MessageType::Error,
+            )
+        });
+    }
+
+    /// Dim the window while files are dragged over it and say what dropping does
+    fn render_drop_hint(&self, ctx: &egui::Context) {
+        if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+            return;
+        }
+        let text = if self.is_scanning {
+            "Wait for the scan to finish before adding more"
+        } else {
+            "Drop folders to scan them, or files to compare"
+        };
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("drop_hint"),
+        ));
+        let screen = ctx.screen_rect();
+        painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(192));
+        painter.text(
+            screen.center(),
+            egui::Align2::CENTER_CENTER,
+            text,
+            egui::TextStyle::Heading.resolve(&ctx.style()),
+            egui::Color32::WHITE,
+        );
+    }
+
+    /// Folders whose files can't be selected for delete or move
+    fn render_protected_folders(&mut self, ui: &mut egui::Ui) {
+        let protected = &mut self.scan_config.protected_folders;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Protected:");
+            if ui.button("Add").clicked() {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    if !protected.contains(&folder) {
+                        protected.push(folder);
+                    }
                 }
-                return;
             }
+            let mut to_remove = None;
+            for (idx, folder) in protected.iter().enumerate() {
+                ui.group(|ui| {
+                    if ui.small_button("X").clicked() {
+                        to_remove = Some(idx);
+                    }
+                    ui.label(format!("🔒 {}", folder.display()));
+                });
+            }
+            if let Some(idx) = to_remove {
+                protected.remove(idx);
+            }
+        });
+    }
+
+    /// Folders pruned from the walk, by path or by name anywhere in the tree
+    fn render_excluded_folders(&mut self, ui: &mut egui::Ui) {
+        let config = &mut self.scan_config;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Excluded:");
+            if ui.button("Add").clicked() {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    if !config.exclude_paths.contains(&folder) {
+                        config.exclude_paths.push(folder);
+                    }
+                }
+            }
+            let mut to_remove = None;
+            for (idx, folder) in config.exclude_paths.iter().enumerate() {
+                ui.group(|ui| {
+                    if ui.small_button("X").clicked() {
+                        to_remove = Some(idx);
+                    }
+                    ui.label(format!("🚫 {}", folder.display()));
+                });
+            }
+            if let Some(idx) = to_remove {
+                config.exclude_paths.remove(idx);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Skip folders named:");
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.exclude_names_text)
+                    .hint_text("e.g. node_modules, .git")
+                    .desired_width(220.0),
+            );
+            if edit.changed() {
+                config.exclude_dir_names = self
+                    .exclude_names_text
+                    .split([',', ';'])
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+        });
+    }
+
+    fn render_scan_options(&mut self, ui: &mut egui::Ui) {
+        let config = &mut self.scan_config;
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut config.recursive, "Subfolders");
+            ui.checkbox(&mut config.follow_symlinks, "Follow symlinks")
+                .on_hover_text("Also walk into symlinked folders and junctions");
+            ui.checkbox(&mut config.verify_readable, "Verify readable")
+                .on_hover_text(
+                    "Open every file while collecting and skip unreadable ones (slower)",
+                );
+            ui.checkbox(&mut config.retain_all_files, "Keep file list")
+                .on_hover_text(
+                    "Retain every scanned file so it can be searched (uses more memory)",
+                );
+            ui.checkbox(&mut config.verify_bytes, "Verify bytes (slower)")
+                .on_hover_text("Compare matching files byte for byte before listing them");
+            ui.checkbox(&mut config.detect_partial_copies, "Partial copies")
+                .on_hover_text("Find truncated copies, e.g. interrupted downloads");
+            ui.checkbox(&mut config.prefer_first_folder, "Prefer first folder")
+                .on_hover_text("When files tie, keep the copy from the folder added first");
+            ui.checkbox(&mut config.skip_network_drives, "Skip network drives")
+                .on_hover_text(
+                    "Leave out folders on network shares instead of reading them slowly",
+                );
+            if cfg!(windows) {
+                ui.checkbox(&mut config.skip_hidden, "Skip hidden")
+                    .on_hover_text("Ignore files and folders marked hidden");
+                ui.checkbox(&mut config.skip_system, "Skip system files")
+                    .on_hover_text("Ignore system files such as desktop.ini and Thumbs.db");
+                ui.checkbox(&mut config.low_priority, "Low priority")
+                    .on_hover_text("Hash at below-normal priority so other apps stay responsive");
+            }
+            let mut limited = config.time_budget.is_some();
+            let mut minutes = config
+                .time_budget
+                .map_or(5, |budget| budget.as_secs() / 60)
+                .max(1);
+            ui.checkbox(&mut limited, "Stop hashing after")
+                .on_hover_text("Show what was found so far once the time runs out");
+            ui.add_enabled(
+                limited,
+                egui::DragValue::new(&mut minutes)
+                    .range(1..=600)
+                    .suffix(" min"),
+            );
+            config.time_budget = limited.then(|| std::time::Duration::from_secs(minutes * 60));
+            ui.checkbox(&mut config.verify_strong, "Strong hash")
+                .on_hover_text(
+                    "Confirm matches with the hash below. Off trusts the fast XXH3 hash \
+                     alone: quicker, but not proof against deliberate collisions",
+                );
+            ui.add_enabled_ui(config.verify_strong, |ui| {
+                egui::ComboBox::from_id_salt("hash_algorithm")
+                    .selected_text(format!("Hash: {}", config.hash_algorithm.label()))
+                    .show_ui(ui, |ui| {
+                        for algorithm in HashAlgorithm::ALL {
+                            ui.selectable_value(
+                                &mut config.hash_algorithm,
+                                algorithm,
+                                algorithm.label(),
+                            );
+                        }
+                    });
+            });
+            egui::ComboBox::from_id_salt("detection_mode")
+                .selected_text(format!("Detect: {}", config.detection_mode.label()))
+                .show_ui(ui, |ui| {
+                    for mode in DetectionMode::ALL {
+                        ui.selectable_value(&mut config.detection_mode, mode, mode.label());
+                    }
+                })
+                .response
+                .on_hover_text("Similar images also matches resized or re-saved copies of photos");
+            if config.detection_mode == DetectionMode::PerceptualImage {
+                ui.add(
+                    egui::Slider::new(
+                        &mut config.similarity_threshold,
+                        0..=MAX_SIMILARITY_THRESHOLD,
+                    )
+                    .text("max distance"),
+                )
+                .on_hover_text("How many of 64 perceptual hash bits may differ; lower is stricter");
+                egui::ComboBox::from_id_salt("similar_keep_strategy")
+                    .selected_text(format!(
+                        "Similar images keep: {}",
+                        config.similar_keep_strategy.label()
+                    ))
+                    .show_ui(ui, |ui| {
+                        for strategy in KeepStrategy::ALL {
+                            ui.selectable_value(
+                                &mut config.similar_keep_strategy,
+                                strategy,
+                                strategy.label(),
+                            );
+                        }
+                    });
+            }
+            egui::ComboBox::from_id_salt("keep_strategy")
+                .selected_text(format!("Keep: {}", config.keep_strategy.label()))
+                .show_ui(ui, |ui| {
+                    for strategy in KeepStrategy::ALL {
+                        ui.selectable_value(&mut config.keep_strategy, strategy, strategy.label());
+                    }
+                });
+            if ui
+                .checkbox(&mut self.remember_last_scan, "Remember results")
+                .on_hover_text(
+                    "Save the last scan to disk and restore it on startup (uses disk space)",
+                )
+                .changed()
+            {
+                self.session_dirty = self.remember_last_scan;
+                if !self.remember_last_scan {
+                    if let Some(path) = SavedScan::default_path() {
+                        let _ = fs::remove_file(path);
+                    }
+                }
+            }
+            if ui
+                .add_enabled(
+                    !self.is_scanning,
+                    egui::Checkbox::new(&mut self.use_hash_cache, "Cache hashes"),
+                )
+                .on_hover_text("Reuse hashes of files unchanged since an earlier scan")
+                .changed()
+                && !self.use_hash_cache
+            {
+                self.hash_cache = None;
+                if let Some(path) = HashCache::default_path() {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        });
+        if config.keep_strategy == KeepStrategy::Rules {
+            Self::render_keep_rules(ui, &mut config.keep_rules);
+        }
+    }
+
+    /// Minimum and maximum file size inputs, kept so that min <= max
+    fn render_size_filter(&mut self, ui: &mut egui::Ui) {
+        let config = &mut self.scan_config;
+        let mut min = config.min_size / self.min_size_unit.bytes();
+        let mut max = config.max_size.min(MAX_FILE_SIZE) / self.max_size_unit.bytes();
+        let (mut min_changed, mut max_changed) = (false, false);
+        ui.horizontal(|ui| {
+            ui.label("Size from");
+            min_changed |= ui
+                .add(
+                    egui::DragValue::new(&mut min)
+                        .range(0..=MAX_FILE_SIZE / self.min_size_unit.bytes()),
+                )
+                .on_hover_text("Smaller files are skipped; 0 only skips empty files")
+                .changed();
+            min_changed |= Self::size_unit_combo(ui, "min_size_unit", &mut self.min_size_unit);
+            ui.label("to");
+            max_changed |= ui
+                .add(
+                    egui::DragValue::new(&mut max)
+                        .range(1..=MAX_FILE_SIZE / self.max_size_unit.bytes()),
+                )
+                .on_hover_text(format!(
+                    "Larger files are skipped (at most {})",
+                    format_size(MAX_FILE_SIZE)
+                ))
+                .changed();
+            max_changed |= Self::size_unit_combo(ui, "max_size_unit", &mut self.max_size_unit);
+        });
+
+        if min_changed {
+            // Empty files are never worth reporting as duplicates
+            config.min_size = (min * self.min_size_unit.bytes()).max(1);
+            if config.min_size > config.max_size {
+                config.max_size = config.min_size;
+                self.max_size_unit = SizeUnit::fitting(config.max_size);
+            }
+        }
+        if max_changed {
+            config.max_size = (max * self.max_size_unit.bytes()).min(MAX_FILE_SIZE);
+            if config.max_size < config.min_size {
+                config.min_size = config.max_size;
+                self.min_size_unit = SizeUnit::fitting(config.min_size);
+            }
+        }
+    }
+
+    /// Returns true when the unit changed
+    fn size_unit_combo(ui: &mut egui::Ui, id: &str, unit: &mut SizeUnit) -> bool {
+        let before = *unit;
+        egui::ComboBox::from_id_salt(id)
+            .width(50.0)
+            .selected_text(unit.label())
+            .show_ui(ui, |ui| {
+                for option in SizeUnit::ALL {
+                    ui.selectable_value(unit, option, option.label());
+                }
+            });
+        *unit != before
+    }
+
+    /// Extension list typed by the user, either the only ones scanned or the ones skipped
+    fn render_extension_filter(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("extension_mode")
+                .selected_text(if self.extensions_include {
+                    "Only extensions"
+                } else {
+                    "Skip extensions"
+                })
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut self.extensions_include, true, "Only extensions")
+                        .changed();
+                    changed |= ui
+                        .selectable_value(&mut self.extensions_include, false, "Skip extensions")
+                        .changed();
+                });
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.extensions_text)
+                        .hint_text("e.g. jpg, png, gif")
+                        .desired_width(180.0),
+                )
+                .changed();
+            ui.checkbox(
+                &mut self.scan_config.include_no_extension,
+                "Files without extension",
+            )
+            .on_hover_text("Also scan files whose name has no extension");
+        });
+
+        if changed {
+            let list = parse_extension_list(&self.extensions_text);
+            let config = &mut self.scan_config;
+            if self.extensions_include {
+                config.include_extensions = (!list.is_empty()).then_some(list);
+                config.exclude_extensions.clear();
+            } else {
+                config.include_extensions = None;
+                config.exclude_extensions = list;
+            }
+        }
+    }
+
+    /// Refresh the filter inputs after the scan config was replaced
+    fn sync_filter_inputs(&mut self) {
+        let config = &self.scan_config;
+        self.min_size_unit = SizeUnit::fitting(config.min_size);
+        self.max_size_unit = SizeUnit::fitting(config.max_size.min(MAX_FILE_SIZE));
+        self.extensions_include = config.include_extensions.is_some();
+        self.extensions_text = config
+            .include_extensions
+            .as_ref()
+            .unwrap_or(&config.exclude_extensions)
+            .join(", ");
+        self.exclude_names_text = config.exclude_dir_names.join(", ");
+    }
+
+    /// Reorderable list of keeper tiebreakers; earlier rules take precedence
+    fn render_keep_rules(ui: &mut egui::Ui, rules: &mut Vec<KeepCriterion>) {
+        let mut swap = None;
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Keeper rules:");
+            for (idx, rule) in rules.iter().enumerate() {
+                ui.group(|ui| {
+                    ui.label(format!("{}. {}", idx + 1, rule.label()));
+                    if ui
+                        .add_enabled(idx > 0, egui::Button::new("◀").small())
+                        .clicked()
+                    {
+                        swap = Some(idx - 1);
+                    }
+                    if ui
+                        .add_enabled(idx + 1 < rules.len(), egui::Button::new("▶").small())
+                        .clicked()
+                    {
+                        swap = Some(idx);
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(idx);
+                    }
+                });
+            }
+            let unused: Vec<KeepCriterion> = KeepCriterion::ALL
+                .into_iter()
+                .filter(|c| !rules.contains(c))
+                .collect();
+            if !unused.is_empty() {
+                egui::ComboBox::from_id_salt("add_keep_rule")
+                    .selected_text("Add rule")
+                    .show_ui(ui, |ui| {
+                        for criterion in unused {
+                            if ui.selectable_label(false, criterion.label()).clicked() {
+                                rules.push(criterion);
+                            }
+                        }
+                    });
+            }
+        });
+        if let Some(idx) = swap {
+            rules.swap(idx, idx + 1);
+        }
+        if let Some(idx) = remove {
+            rules.remove(idx);
+        }
+    }
+
+    fn render_scan_profiles(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            let mut apply = None;
+            egui::ComboBox::from_id_salt("scan_profile")
+                .selected_text(if self.profile_name.is_empty() {
+                    "(none)"
+                } else {
+                    self.profile_name.as_str()
+                })
+                .show_ui(ui, |ui| {
+                    for (idx, profile) in self.scan_profiles.iter().enumerate() {
+                        if ui
+                            .selectable_label(profile.name == self.profile_name, &profile.name)
+                            .clicked()
+                        {
+                            apply = Some(idx);
+                        }
+                    }
+                });
+            if let Some(idx) = apply {
+                let profile = &self.scan_profiles[idx];
+                self.scan_config = profile.config.clone();
+                self.profile_name = profile.name.clone();
+                self.sync_filter_inputs();
+            }
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.profile_name)
+                    .hint_text("Profile name")
+                    .desired_width(120.0),
+            );
+
+            let name = self.profile_name.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Save the current scan options under this name")
+                .clicked()
+            {
+                let config = self.scan_config.clone();
+                match self.scan_profiles.iter_mut().find(|p| p.name == name) {
+                    Some(profile) => profile.config = config,
+                    None => self.scan_profiles.push(ScanProfile {
+                        name: name.clone(),
+                        config,
+                    }),
+                }
+                self.status_message =
+                    Some((format!("Saved profile '{}'", name), MessageType::Success));
+            }
+
+            let exists = self.scan_profiles.iter().any(|p| p.name == name);
+            if ui
+                .add_enabled(exists, egui::Button::new("Delete"))
+                .clicked()
+            {
+                self.scan_profiles.retain(|p| p.name != name);
+                self.profile_name.clear();
+            }
+        });
+    }
+
+    fn render_results_only(&mut self, ui: &mut egui::Ui, _ctx: &egui::Context) {
+        let result = match self.scan_result.clone() {
+            Some(r) => r,
+            None => {
+                if self.is_scanning {
+                    self.render_live_groups(ui);
+                } else {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(50.0);
+                        ui.label("Add folders and click 'Scan' to find duplicates.");
+                    });
+                }
+                return;
+            }
+        };
+
+        ui.separator();
+
+        if let Some(staleness) = self.restored_scan {
+            self.render_restored_banner(ui, staleness);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(result_summary(&result));
+            if ui
+                .add_enabled(
+                    !self.is_scanning && self.has_scan_inputs(),
+                    egui::Button::new("⟳ Rescan"),
+                )
+                .on_hover_text("Scan the same folders again with the current settings")
+                .clicked()
+            {
+                self.start_scan();
+            }
+        });
+
+        if result.time_limit_reached {
+            ui.label(
+                egui::RichText::new(
+                    "⚠ Partial result: the time limit was reached before all candidates were hashed",
+                )
+                .color(ui.visuals().warn_fg_color),
+            );
+        }
+
+        for root in &result.slow_roots {
+            let note = if root.skipped {
+                format!(
+                    "⚠ Skipped {}: it's on a {}",
+                    root.path.display(),
+                    root.kind.label()
+                )
+            } else {
+                format!(
+                    "{} is on a {}, so fewer files were read at once",
+                    root.path.display(),
+                    root.kind.label()
+                )
+            };
+            ui.label(
+                egui::RichText::new(note)
+                    .small()
+                    .color(ui.visuals().warn_fg_color),
+            );
+        }
+
+        if result.total_files > 0 {
+            ui.label(
+                egui::RichText::new(format!(
+                    "Pre-filter skipped hashing for {} of {} files",
+                    result.total_files.saturating_sub(result.hash_candidates),
+                    result.total_files
+                ))
+                .small()
+                .color(egui::Color32::GRAY),
+            );
+        }
+
+        Self::render_folder_breakdown(ui, &self.folder_breakdown);
+        self.render_duplicate_folders(ui, &result);
+
+        Self::render_path_list(
+            ui,
+            "unreadable_files",
+            &format!(
+                "{} unreadable file(s) skipped",
+                result.unreadable_files.len()
+            ),
+            &result.unreadable_files,
+        );
+        Self::render_path_list(
+            ui,
+            "sparse_files",
+            &format!(
+                "{} sparse file(s) hashed without size pre-filter",
+                result.sparse_files.len()
+            ),
+            &result.sparse_files,
+        );
+        Self::render_path_list(
+            ui,
+            "locked_files",
+            &format!(
+                "{} file(s) in use by another program: close it, then Retry them below",
+                result.locked_files.len()
+            ),
+            &result.locked_files,
+        );
+        self.render_unhashed_suspects(ui, &result);
+        self.render_empty_files(ui, &result);
+
+        if !result.all_files.is_empty() {
+            self.render_all_files_search(ui, &result);
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let count = self.selected_files.len();
+            if ui
+                .add_enabled(count > 0, egui::Button::new(format!("Delete ({})", count)))
+                .on_hover_text("Delete key")
+                .clicked()
+            {
+                self.open_delete_dialog();
+            }
+            if ui
+                .add_enabled(count > 0, egui::Button::new(format!("Move ({})", count)))
+                .clicked()
+            {
+                if let Some(dest) = FileDialog::new().pick_folder() {
+                    let moves = self.move_targets(&result, &dest);
+                    let paths: Vec<PathBuf> = moves.iter().map(|(p, _)| p.clone()).collect();
+                    self.space_check = SpaceCheck::for_move(&paths, &dest);
+                    self.show_confirmation_dialog =
+                        Some(ConfirmationDialog::MoveFiles(moves, dest));
+                }
+            }
+            if ui
+                .add_enabled(
+                    count > 0,
+                    egui::Button::new(format!("Link to keeper ({})", count)),
+                )
+                .on_hover_text("Replace duplicates with hard links to the keeper, keeping every path")
+                .clicked()
+            {
+                let links = self.link_targets(&result);
+                if !links.is_empty() {
+                    self.show_confirmation_dialog = Some(ConfirmationDialog::LinkToKeeper(links));
+                }
+            }
+            if ui
+                .add_enabled(count > 0, egui::Button::new(format!("Rename ({})", count)))
+                .on_hover_text("Rename the selected files with a pattern such as {name}_{index}")
+                .clicked()
+            {
+                self.open_rename_dialog(&result);
+            }
+            egui::ComboBox::from_id_salt("move_layout")
+                .selected_text(self.move_layout.label())
+                .show_ui(ui, |ui| {
+                    for layout in MoveLayout::ALL {
+                        ui.selectable_value(&mut self.move_layout, layout, layout.label());
+                    }
+                });
+            if ui.button("Select All").on_hover_text("Ctrl+A").clicked() {
+                self.select_all_duplicates();
+            }
+            let invert_hint = if self.invert_includes_keepers {
+                "Flip the selection of every file. Right-click for options"
+            } else {
+                "Flip the selection of every file except keepers. Right-click for options"
+            };
+            let invert = ui.button("Invert").on_hover_text(invert_hint);
+            if invert.clicked() {
+                self.invert_selection();
+            }
+            invert.context_menu(|ui| {
+                ui.checkbox(&mut self.invert_includes_keepers, "Include keepers")
+                    .on_hover_text("Inverting can then select every copy of a group");
+            });
+            if ui.button("Clear").clicked() {
+                self.selected_files.clear();
+            }
+            ui.label("Expand top");
+            if ui
+                .add(egui::DragValue::new(&mut self.auto_expand_groups).range(0..=50))
+                .on_hover_text("Groups wasting the most space start expanded")
+                .changed()
+            {
+                self.expanded_groups = Self::top_groups_by_waste(&result, self.auto_expand_groups);
+            }
+            if !self.scan_config.protected_folders.is_empty() {
+                ui.checkbox(&mut self.hide_protected_groups, "Hide fully-protected")
+                    .on_hover_text("Hide groups where every copy is in a protected folder");
+            }
+            ui.checkbox(&mut self.show_flagged_only, "Flagged only")
+                .on_hover_text(format!("{} file(s) flagged", self.file_flags.len()));
+            if ui
+                .checkbox(&mut self.watch_folders, "Watch for new")
+                .on_hover_text(
+                    "Keep watching the scanned folders and flag new copies of files found by this scan",
+                )
+                .changed()
+            {
+                self.restart_watcher();
+            }
+            if ui
+                .add_enabled(!self.is_scanning, egui::Button::new("Open in new window"))
+                .on_hover_text("Keep this result open to compare with another scan")
+                .clicked()
+            {
+                self.open_result_window();
+            }
+
+            let (can_undo, undo_hint) = match self.file_ops.next_undo() {
+                Some(batch) if batch.reversible => (true, format!("Undo: {}", batch.description)),
+                Some(batch) => (
+                    false,
+                    format!("Cannot undo past '{}'", batch.description),
+                ),
+                None => (false, "Nothing to undo".to_string()),
+            };
+            if ui
+                .add_enabled(can_undo && !self.is_scanning, egui::Button::new("Undo"))
+                .on_hover_text(&undo_hint)
+                .on_disabled_hover_text(&undo_hint)
+                .clicked()
+            {
+                self.undo_last_batch();
+            }
+            self.render_operation_log_toggle(ui);
+        });
+        self.render_keeper_pattern(ui);
+        self.render_baseline(ui, &result);
+        self.render_group_search(ui, &result);
+
+        ui.separator();
+
+        let available = ui.available_size();
+        egui::ScrollArea::vertical()
+            .id_salt("main_list")
+            .auto_shrink([false, false])
+            .max_height(available.y)
+            .show(ui, |ui| {
+                let query = GroupQuery::parse(&self.group_query);
+                // Indices stay those of the full list, so selections survive filtering
+                for (group_idx, group) in result.duplicate_groups.iter().enumerate() {
+                    if !self.group_visible(group, &query) {
+                        continue;
+                    }
+                    let mut view = GroupView {
+                        selected_files: &self.selected_files,
+                        flags: &mut self.file_flags,
+                        flagged_only: self.show_flagged_only,
+                        protected: &self.scan_config.protected_folders,
+                        expanded: &mut self.expanded_groups,
+                        reverifying: Some(&self.reverify_jobs),
+                        new_files: Some(&self.new_duplicates),
+                        missing: Some(&self.missing_files),
+                        set_keeper: true,
+                        thumbnails: &mut self.group_thumbnails,
+                        focused: self.focused_row,
+                        scroll_to_focus: std::mem::take(&mut self.scroll_to_focus),
+                        anchor: self.focused_row,
+                    };
+                    let action = Self::render_group(ui, group_idx, group, &mut view);
+                    // Keep the request alive until the focused group is drawn
+                    self.scroll_to_focus |= view.scroll_to_focus;
+                    if let Some(action) = action {
+                        if let GroupAction::Select(g, f, _) = action {
+                            self.focused_row = Some((g, f));
+                        }
+                        let mut selected = std::mem::take(&mut self.selected_files);
+                        self.apply_group_action(action, &mut selected);
+                        self.selected_files = selected;
+                    }
+                }
+            });
+    }
+
+    /// Whether the main list shows `group` under the current filters
+    fn group_visible(&self, group: &DuplicateGroup, query: &GroupQuery) -> bool {
+        if self.show_flagged_only && !self.group_has_flags(group) {
+            return false;
+        }
+        if !self.show_seen_groups
+            && self
+                .baseline
+                .as_ref()
+                .is_some_and(|b| b.contains(&group.hash))
+        {
+            return false;
+        }
+        if self.hide_protected_groups && !group.is_actionable(&self.scan_config.protected_folders) {
+            return false;
+        }
+        self.group_type_shown(group) && query.matches(group)
+    }
+
+    /// Whether the type chips let `group` through, judged by its keeper
+    fn group_type_shown(&self, group: &DuplicateGroup) -> bool {
+        self.hidden_file_types.is_empty()
+            || group.files.first().is_none_or(|keeper| {
+                let file_type = Self::get_file_type(&normalized_extension(&keeper.path));
+                !self.hidden_file_types.contains(&file_type.category())
+            })
+    }
+
+    fn group_has_flags(&self, group: &DuplicateGroup) -> bool {
+        group
+            .files
+            .iter()
+            .any(|f| self.file_flags.contains_key(&f.path))
+    }
+
+    /// Extra viewports holding snapshots of earlier scans
+    fn render_result_windows(&mut self, ctx: &egui::Context) {
+        // Taken out so each window's selection can be mutated alongside `self`
+        let mut windows = std::mem::take(&mut self.result_windows);
+        for window in &mut windows {
+            let builder = egui::ViewportBuilder::default()
+                .with_title(&window.title)
+                .with_inner_size([700.0, 500.0]);
+            ctx.show_viewport_immediate(window.id, builder, |ctx, _class| {
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    window.open = false;
+                }
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    self.render_result_window(ui, window);
+                });
+            });
+        }
+        windows.retain(|w| w.open);
+        self.result_windows = windows;
+    }
+
+    fn render_result_window(&mut self, ui: &mut egui::Ui, window: &mut ResultWindow) {
+        let result = &window.result;
+        ui.label(result_summary(result));
+
+        ui.horizontal(|ui| {
+            let count = window.selected_files.len();
+            let busy = self.show_confirmation_dialog.is_some();
+            if ui
+                .add_enabled(
+                    count > 0 && !busy,
+                    egui::Button::new(format!("Delete ({})", count)),
+                )
+                .clicked()
+            {
+                // Confirmation lives in the main window
+                self.show_confirmation_dialog = Some(ConfirmationDialog::DeleteFiles(
+                    self.selected_paths(result, &window.selected_files),
+                ));
+                ui.ctx()
+                    .send_viewport_cmd_to(egui::ViewportId::ROOT, egui::ViewportCommand::Focus);
+            }
+            if ui.button("Clear").clicked() {
+                window.selected_files.clear();
+            }
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .id_salt("window_list")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (group_idx, group) in result.duplicate_groups.iter().enumerate() {
+                    let mut view = GroupView {
+                        selected_files: &window.selected_files,
+                        flags: &mut self.file_flags,
+                        flagged_only: false,
+                        protected: &self.scan_config.protected_folders,
+                        expanded: &mut window.expanded_groups,
+                        reverifying: None,
+                        new_files: None,
+                        missing: None,
+                        set_keeper: false,
+                        thumbnails: &mut self.group_thumbnails,
+                        focused: None,
+                        scroll_to_focus: false,
+                        anchor: window.anchor,
+                    };
+                    if let Some(action) = Self::render_group(ui, group_idx, group, &mut view) {
+                        if let GroupAction::Select(g, f, _) = action {
+                            window.anchor = Some((g, f));
+                        }
+                        if matches!(action, GroupAction::DeleteDuplicates(_)) {
+                            ui.ctx().send_viewport_cmd_to(
+                                egui::ViewportId::ROOT,
+                                egui::ViewportCommand::Focus,
+                            );
+                        }
+                        self.apply_group_action(action, &mut window.selected_files);
+                    }
+                }
+            });
+    }
+
+    /// Snapshot the current result into its own window
+    fn open_result_window(&mut self) {
+        let Some(result) = self.scan_result.clone() else {
+            return;
+        };
+        self.next_window_id += 1;
+        let folders: Vec<String> = self
+            .selected_folders
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        self.result_windows.push(ResultWindow {
+            id: egui::ViewportId::from_hash_of(("result_window", self.next_window_id)),
+            title: format!(
+                "Scan {} - {} ({})",
+                self.next_window_id,
+                folders.join(", "),
+                chrono::Local::now().format("%H:%M")
+            ),
+            expanded_groups: Self::top_groups_by_waste(&result, self.auto_expand_groups),
+            result,
+            selected_files: Vec::new(),
+            anchor: None,
+            open: true,
+        });
+    }
+
+    fn render_restored_banner(&mut self, ui: &mut egui::Ui, staleness: Staleness) {
+        let date = chrono::DateTime::from_timestamp(self.scan_finished_at, 0)
+            .map(|d| {
+                d.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "an earlier session".to_string());
+        egui::Frame::group(ui.style())
+            .fill(ui.visuals().faint_bg_color)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Showing results from your scan on {}; files may have changed since.",
+                            date
+                        ))
+                        .color(ui.visuals().warn_fg_color),
+                    );
+                    if staleness.missing > 0 {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} of {} sampled files are gone.",
+                                staleness.missing, staleness.checked
+                            ))
+                            .color(egui::Color32::RED),
+                        );
+                    }
+                    if ui
+                        .add_enabled(!self.is_scanning, egui::Button::new("Rescan"))
+                        .clicked()
+                    {
+                        self.start_scan();
+                    }
+                    if ui.small_button("Dismiss").clicked() {
+                        self.restored_scan = None;
+                    }
+                });
+            });
+    }
+
+    /// Export the result, or compare it with an earlier export to see only new groups
+    fn render_baseline(&mut self, ui: &mut egui::Ui, result: &ScanResult) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Export CSV...")
+                .on_hover_text("One row per file, for spreadsheets")
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("filexsorter_export.csv")
+                    .save_file()
+                {
+                    self.status_message = Some(match export::export_csv(result, &path) {
+                        Ok(()) => (
+                            format!("Exported to {}", path.display()),
+                            MessageType::Success,
+                        ),
+                        Err(e) => (e, MessageType::Error),
+                    });
+                }
+            }
+            if ui
+                .button("Save Session...")
+                .on_hover_text("Save these results to reopen later or compare against")
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name("filexsorter_session.json")
+                    .save_file()
+                {
+                    self.status_message = Some(match export::save_results_json(result, &path) {
+                        Ok(()) => (
+                            format!("Saved session to {}", path.display()),
+                            MessageType::Success,
+                        ),
+                        Err(e) => (e, MessageType::Error),
+                    });
+                }
+            }
+            if ui
+                .button("Compare to export...")
+                .on_hover_text("Hide groups that were already in a previous export")
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Export", &["json", "csv"])
+                    .pick_file()
+                {
+                    match export::load_baseline(&path) {
+                        Ok(hashes) => {
+                            self.baseline = Some(hashes);
+                            self.show_seen_groups = false;
+                        }
+                        Err(e) => self.status_message = Some((e, MessageType::Error)),
+                    }
+                }
+            }
+
+            let Some(baseline) = &self.baseline else {
+                return;
+            };
+            let diff = export::diff_against_baseline(result, baseline);
+            ui.label(format!("{} new, {} previously seen", diff.new, diff.seen));
+            ui.checkbox(&mut self.show_seen_groups, "Show seen");
+            if ui.button("Clear baseline").clicked() {
+                self.baseline = None;
+            }
+        });
+    }
+
+    /// Search box narrowing the group list by path or size
+    fn render_group_search(&mut self, ui: &mut egui::Ui, result: &ScanResult) {
+        ui.horizontal(|ui| {
+            ui.label("Filter groups:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.group_query)
+                    .hint_text("name or path, size>10MB")
+                    .desired_width(220.0),
+            )
+            .on_hover_text("All terms must match; size supports >, >=, <, <= with KB/MB/GB");
+            for file_type in FileType::FILTERS {
+                let mut shown = !self.hidden_file_types.contains(&file_type);
+                if ui
+                    .toggle_value(&mut shown, file_type.filter_label())
+                    .changed()
+                {
+                    if shown {
+                        self.hidden_file_types.remove(&file_type);
+                    } else {
+                        self.hidden_file_types.insert(file_type);
+                    }
+                }
+            }
+            let query = GroupQuery::parse(&self.group_query);
+            if !query.is_empty() || !self.hidden_file_types.is_empty() {
+                let shown = result
+                    .duplicate_groups
+                    .iter()
+                    .filter(|g| self.group_type_shown(g) && query.matches(g))
+                    .count();
+                ui.label(format!(
+                    "{} of {} groups",
+                    shown,
+                    result.duplicate_groups.len()
+                ));
+                if ui.small_button("✖").on_hover_text("Clear filter").clicked() {
+                    self.group_query.clear();
+                    self.hidden_file_types.clear();
+                }
+            }
+            let before = self.group_sort;
+            egui::ComboBox::from_id_salt("group_sort")
+                .selected_text(format!("Sort: {}", self.group_sort.label()))
+                .show_ui(ui, |ui| {
+                    for sort in GroupSort::ALL {
+                        ui.selectable_value(&mut self.group_sort, sort, sort.label());
+                    }
+                });
+            if self.group_sort != before {
+                self.apply_group_sort();
+                self.session_dirty = true;
+            }
+        });
+    }
+
+    /// Re-sort the groups, carrying selections and the focused row to their new positions
+    fn apply_group_sort(&mut self) {
+        let Some(result) = self.scan_result.as_mut() else {
+            return;
+        };
+        let order = filter::sort_groups(&mut result.duplicate_groups, self.group_sort);
+        let mut new_index = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old] = new;
+        }
+        let remap = |(g, f): (usize, usize)| (new_index.get(g).copied().unwrap_or(g), f);
+        for sel in &mut self.selected_files {
+            *sel = remap(*sel);
+        }
+        self.focused_row = self.focused_row.map(remap);
+    }
+
+    /// Bulk keeper override: keep the copy matching a wildcard pattern in every group
+    fn render_keeper_pattern(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Keep copies matching:");
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.keeper_pattern)
+                    .hint_text("*\\Archive\\*")
+                    .desired_width(200.0),
+            );
+            let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let can_apply = !self.keeper_pattern.trim().is_empty() && !self.is_scanning;
+            let clicked = ui
+                .add_enabled(can_apply, egui::Button::new("Apply"))
+                .on_hover_text("* matches anything, ? one character; \\ and / are the same")
+                .clicked();
+            if !(clicked || submitted && can_apply) {
+                return;
+            }
+            let Some(result) = self.scan_result.as_mut() else {
+                return;
+            };
+
+            let changed = result.apply_keeper_pattern(&self.keeper_pattern);
+            if changed > 0 {
+                // File indices within the changed groups have shifted
+                self.selected_files.clear();
+                self.folder_breakdown = result.wasted_by_folder();
+                self.session_dirty = true;
+            }
+            self.status_message = Some((
+                format!(
+                    "Keeper changed in {} of {} group(s)",
+                    changed,
+                    result.duplicate_groups.len()
+                ),
+                MessageType::Success,
+            ));
+        });
+    }
+
+    /// Low-confidence list of size matches that couldn't be hashed, with per-file retry
+    fn render_unhashed_suspects(&mut self, ui: &mut egui::Ui, result: &ScanResult) {
+        if result.unhashed_suspects.is_empty() {
+            return;
+        }
+
+        let mut retry = None;
+        let title = format!(
+            "{} file(s) matched a duplicate's size but couldn't be hashed",
+            result.unhashed_suspects.len()
+        );
+        egui::CollapsingHeader::new(egui::RichText::new(title).color(ui.visuals().warn_fg_color))
+            .id_salt("unhashed_suspects")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("unhashed_suspects_list")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for (idx, suspect) in result.unhashed_suspects.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(
+                                        !self.is_scanning,
+                                        egui::Button::new("Retry").small(),
+                                    )
+                                    .clicked()
+                                {
+                                    retry = Some(idx);
+                                }
+                                ui.label(
+                                    egui::RichText::new(suspect.file.path.display().to_string())
+                                        .small(),
+                                )
+                                .on_hover_text(format!(
+                                    "{} | {} same-size file(s)",
+                                    suspect.error,
+                                    suspect.peers.len()
+                                ));
+                            });
+                        }
+                    });
+            });
+
+        let (Some(idx), Some(result)) = (retry, self.scan_result.as_mut()) else {
+            return;
+        };
+        self.session_dirty = true;
+        self.status_message = Some(match result.retry_suspect(idx) {
+            Ok(true) => ("Hashed: it's a duplicate".to_string(), MessageType::Success),
+            Ok(false) => (
+                "Hashed: no match, file is unique".to_string(),
+                MessageType::Info,
+            ),
+            Err(e) => (e, MessageType::Error),
+        });
+    }
+
+    /// Collapsible list of paths that need the user's attention
+    fn render_path_list(ui: &mut egui::Ui, id: &str, title: &str, paths: &[PathBuf]) {
+        if paths.is_empty() {
+            return;
+        }
+
+        egui::CollapsingHeader::new(egui::RichText::new(title).color(ui.visuals().warn_fg_color))
+            .id_salt(id)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt((id, "list"))
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for path in paths {
+                            ui.label(egui::RichText::new(path.display().to_string()).small());
+                        }
+                    });
+            });
+    }
+
+    /// Folders whose whole contents are duplicated, deletable a tree at a time
+    fn render_duplicate_folders(&mut self, ui: &mut egui::Ui, result: &ScanResult) {
+        if result.duplicate_folders.is_empty() {
+            return;
+        }
+        // Deleting a folder would also take any protected folder inside it
+        let protected = &self.scan_config.protected_folders;
+        let deletable = |folder: &PathBuf| {
+            !is_protected(folder, protected) && !protected.iter().any(|p| p.starts_with(folder))
+        };
+        let mut to_delete = None;
+        egui::CollapsingHeader::new(format!(
+            "Duplicate folders ({})",
+            result.duplicate_folders.len()
+        ))
+        .id_salt("duplicate_folders")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("duplicate_folders_list")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (idx, group) in result.duplicate_folders.iter().enumerate() {
+                        ui.push_id(idx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "{} copies | {} files | {} each | {} wasted",
+                                        group.folders.len(),
+                                        group.file_count,
+                                        format_size(group.folder_size),
+                                        format_size(group.wasted_size())
+                                    ))
+                                    .strong(),
+                                );
+                                let others: Vec<PathBuf> = group.folders[1..]
+                                    .iter()
+                                    .filter(|f| deletable(f))
+                                    .cloned()
+                                    .collect();
+                                if ui
+                                    .add_enabled(
+                                        !others.is_empty(),
+                                        egui::Button::new("🗑 Delete other copies").small(),
+                                    )
+                                    .on_hover_text("Delete every copy of this folder but the first")
+                                    .clicked()
+                                {
+                                    to_delete = Some(others);
+                                }
+                            });
+                            for (folder_idx, folder) in group.folders.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0);
+                                    if folder_idx == 0 {
+                                        ui.label(
+                                            egui::RichText::new("[KEEP]")
+                                                .color(success_color(ui.visuals()))
+                                                .strong(),
+                                        );
+                                    } else if ui
+                                        .add_enabled(
+                                            deletable(folder),
+                                            egui::Button::new("🗑").small(),
+                                        )
+                                        .on_hover_text("Delete this folder")
+                                        .on_disabled_hover_text("Holds a protected folder")
+                                        .clicked()
+                                    {
+                                        to_delete = Some(vec![folder.clone()]);
+                                    }
+                                    if ui.small_button("📂").on_hover_text("Open folder").clicked()
+                                    {
+                                        if let Err(e) = Self::open_folder_and_select_file(folder) {
+                                            self.status_message = Some((
+                                                format!("Failed to open folder: {}", e),
+                                                MessageType::Error,
+                                            ));
+                                        }
+                                    }
+                                    ui.label(egui::RichText::new(folder.display().to_string()));
+                                });
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+        });
+        if let (Some(folders), None) = (to_delete, &self.show_confirmation_dialog) {
+            let folders = folders
+                .into_iter()
+                .map(|folder| {
+                    let matched = folders::matched_files(&result.duplicate_groups, &folder);
+                    (folder, matched)
+                })
+                .collect();
+            self.show_confirmation_dialog = Some(ConfirmationDialog::DeleteFolders(folders));
+        }
+    }
+
+    /// Zero-byte files found while scanning, which can be deleted in one go
+    fn render_empty_files(&mut self, ui: &mut egui::Ui, result: &ScanResult) {
+        if result.zero_byte_paths.is_empty() {
+            return;
+        }
+        let protected = &self.scan_config.protected_folders;
+        egui::CollapsingHeader::new(format!("{} empty file(s) found", result.empty_files))
+            .id_salt("empty_files")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .small_button("Select all")
+                        .on_hover_text("Every empty file except markers such as .gitkeep")
+                        .clicked()
+                    {
+                        self.selected_empty_files = result
+                            .zero_byte_paths
+                            .iter()
+                            .filter(|p| !is_marker_file(p) && !is_protected(p, protected))
+                            .cloned()
+                            .collect();
+                    }
+                    if ui.small_button("Select none").clicked() {
+                        self.selected_empty_files.clear();
+                    }
+                    let paths: Vec<PathBuf> = result
+                        .zero_byte_paths
+                        .iter()
+                        .filter(|p| self.selected_empty_files.contains(*p))
+                        .cloned()
+                        .collect();
+                    if ui
+                        .add_enabled(
+                            !paths.is_empty() && self.show_confirmation_dialog.is_none(),
+                            egui::Button::new(format!("Delete selected ({})", paths.len())).small(),
+                        )
+                        .clicked()
+                    {
+                        self.show_confirmation_dialog =
+                            Some(ConfirmationDialog::DeleteFiles(paths));
+                    }
+                    if self.scan_config.min_size > 0 {
+                        ui.label(
+                            egui::RichText::new(
+                                "Not matched as duplicates while the minimum size is above 0",
+                            )
+                            .small()
+                            .color(egui::Color32::GRAY),
+                        );
+                    }
+                });
+                let row_height = ui.spacing().interact_size.y;
+                egui::ScrollArea::vertical()
+                    .id_salt("empty_files_list")
+                    .max_height(150.0)
+                    .show_rows(ui, row_height, result.zero_byte_paths.len(), |ui, rows| {
+                        for path in &result.zero_byte_paths[rows] {
+                            let mut selected = self.selected_empty_files.contains(path);
+                            let mut text = egui::RichText::new(path.display().to_string()).small();
+                            if is_marker_file(path) {
+                                text = text.color(egui::Color32::GRAY);
+                            }
+                            let checkbox = ui.add_enabled(
+                                !is_protected(path, protected),
+                                egui::Checkbox::new(&mut selected, text),
+                            );
+                            if checkbox.changed() {
+                                if selected {
+                                    self.selected_empty_files.insert(path.clone());
+                                } else {
+                                    self.selected_empty_files.remove(path);
+                                }
+                            }
+                        }
+                    });
+            });
+    }
+
+    /// Folders holding the most wasted space, so the worst offenders can be cleaned first
+    fn render_folder_breakdown(ui: &mut egui::Ui, folders: &[FolderWaste]) {
+        const MAX_FOLDERS_SHOWN: usize = 100;
+
+        if folders.is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new(format!("By folder ({})", folders.len()))
+            .id_salt("folder_breakdown")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("folder_breakdown_list")
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("folder_breakdown_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for folder in folders.iter().take(MAX_FOLDERS_SHOWN) {
+                                    ui.label(format_size(folder.wasted_size));
+                                    ui.label(format!("{} duplicate(s)", folder.duplicates));
+                                    ui.label(
+                                        egui::RichText::new(folder.folder.display().to_string())
+                                            .small(),
+                                    );
+                                    ui.end_row();
+                                }
+                            });
+                        if folders.len() > MAX_FOLDERS_SHOWN {
+                            ui.label(format!(
+                                "... and {} more folders",
+                                folders.len() - MAX_FOLDERS_SHOWN
+                            ));
+                        }
+                    });
+            });
+    }
+
+    fn render_all_files_search(&mut self, ui: &mut egui::Ui, result: &ScanResult) {
+        const MAX_SEARCH_RESULTS: usize = 200;
+
+        egui::CollapsingHeader::new(format!(
+            "Search all {} scanned files",
+            result.all_files.len()
+        ))
+        .id_salt("all_files_search")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Find:");
+                ui.text_edit_singleline(&mut self.all_files_query);
+            });
+
+            let query = self.all_files_query.trim().to_lowercase();
+            if query.is_empty() {
+                return;
+            }
+
+            let group_of: HashMap<&Path, usize> = result
+                .duplicate_groups
+                .iter()
+                .enumerate()
+                .flat_map(|(g, group)| group.files.iter().map(move |f| (f.path.as_path(), g)))
+                .collect();
+
+            let matches: Vec<&FileEntry> = result
+                .all_files
+                .iter()
+                .filter(|f| f.path.to_string_lossy().to_lowercase().contains(&query))
+                .collect();
+
+            ui.label(egui::RichText::new(format!("{} match(es)", matches.len())).small());
+            egui::ScrollArea::vertical()
+                .id_salt("all_files_results")
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    for file in matches.iter().take(MAX_SEARCH_RESULTS) {
+                        ui.horizontal(|ui| {
+                            match group_of.get(file.path.as_path()) {
+                                Some(g) => ui.label(
+                                    egui::RichText::new(format!("[Group {}]", g + 1))
+                                        .color(ui.visuals().warn_fg_color),
+                                ),
+                                None => ui.label(
+                                    egui::RichText::new("[Unique]").color(egui::Color32::GRAY),
+                                ),
+                            };
+                            ui.label(format_size(file.size));
+                            ui.label(egui::RichText::new(file.path.display().to_string()).small());
+                        });
+                    }
+                    if matches.len() > MAX_SEARCH_RESULTS {
+                        ui.label(format!(
+                            "...and {} more",
+                            matches.len() - MAX_SEARCH_RESULTS
+                        ));
+                    }
+                });
+        });
+    }
+
+    fn render_preview_panel(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let available_height = ui.available_height();
+        let width = ui.available_width();
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Preview").strong());
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let response = ui
+                        .add(
+                            egui::DragValue::new(&mut self.preview_max_size)
+                                .range(128..=4096)
+                                .suffix(" px"),
+                        )
+                        .on_hover_text("Maximum decoded preview size");
+                    if response.changed() {
+                        // Cached textures were decoded at the old size
+                        self.loaded_images.clear();
+                        self.image_access_order.clear();
+                        self.failed_images.clear();
+                        self.gif_animations.clear();
+                        self.media_previews.clear();
+                    }
+                });
+            });
+            ui.separator();
+
+            let preview = match self.preview_file.clone() {
+                Some(p) => p,
+                None => {
+                    ui.label(
+                        egui::RichText::new("Click 👁 to preview a file")
+                            .small()
+                            .italics(),
+                    );
+                    return;
+                }
+            };
+
+            ui.label(egui::RichText::new(&preview.name).strong().size(11.0));
+            ui.label(format!(
+                "{} | {}",
+                format_size(preview.size),
+                preview.extension.to_uppercase()
+            ));
+
+            if let Some((w, h)) = preview.dimensions {
+                ui.label(egui::RichText::new(format!("{}x{}", w, h)).small());
+            }
+
+            ui.add_space(8.0);
+
+            let header_used = 90.0;
+            let button_height = 35.0;
+            let content_height = (available_height - header_used - button_height).max(80.0);
+
+            match preview.file_type {
+                FileType::Image | FileType::Gif => {
+                    let max_size = self.preview_max_size as f32;
+                    let frame = match preview.file_type {
+                        FileType::Gif => self.gif_frame(ctx, &preview.path),
+                        _ => None,
+                    };
+                    match frame.or_else(|| self.load_image_texture(ctx, &preview.path, max_size)) {
+                        Some(texture) => {
+                            Self::show_scaled_texture(ui, &texture, width - 10.0, content_height)
+                        }
+                        None => {
+                            ui.label(egui::RichText::new("Preview unavailable").small().italics());
+                        }
+                    }
+                }
+                FileType::Video | FileType::Audio => {
+                    let is_video = preview.file_type == FileType::Video;
+                    self.poll_media_preview(ctx, &preview.path, is_video);
+                    let (icon, kind) = if is_video {
+                        ("🎬", "Video File")
+                    } else {
+                        ("🎵", "Audio File")
+                    };
+                    match self.media_previews.get(&preview.path) {
+                        Some(MediaPreview::Ready(info, texture)) => {
+                            match texture {
+                                Some(texture) => Self::show_scaled_texture(
+                                    ui,
+                                    texture,
+                                    width - 10.0,
+                                    content_height - 40.0,
+                                ),
+                                None => {
+                                    ui.vertical_centered(|ui| {
+                                        ui.label(egui::RichText::new(icon).size(48.0));
+                                    });
+                                }
+                            }
+                            Self::render_media_details(ui, info);
+                        }
+                        Some(MediaPreview::Loading(_)) => {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Reading media info...");
+                            });
+                        }
+                        _ => {
+                            ui.vertical_centered(|ui| {
+                                ui.label(egui::RichText::new(icon).size(64.0));
+                                ui.label(kind);
+                            });
+                        }
+                    }
+                    if !is_video {
+                        self.render_audio_controls(ui, &preview.path);
+                    }
+                }
+                FileType::Text => {
+                    if let Some(ref text) = preview.preview_text {
+                        let highlighted =
+                            self.highlighted_text(text, &preview.extension, ui.visuals().dark_mode);
+                        egui::ScrollArea::vertical()
+                            .max_height(content_height)
+                            .show(ui, |ui| match highlighted {
+                                Some(job) => ui.label(job),
+                                None => ui.label(egui::RichText::new(text).monospace().size(10.0)),
+                            });
+                    }
+                }
+                FileType::Other => {
+                    ui.vertical_centered(|ui| {
+                        ui.label(egui::RichText::new("📄").size(64.0));
+                        ui.label("File");
+                    });
+                }
+            }
+
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Open")
+                    .on_hover_text("Open with default app")
+                    .clicked()
+                {
+                    Self::open_file_with_default(&preview.path);
+                }
+                if ui
+                    .button("Folder")
+                    .on_hover_text("Show in Explorer")
+                    .clicked()
+                {
+                    self.open_folder_and_select_file_safe(&preview.path);
+                }
+                ui.menu_button("Copy", |ui| Self::copy_path_buttons(ui, &preview.path));
+            });
+        });
+    }
+
+    fn confidence_color(confidence: MatchConfidence, visuals: &egui::Visuals) -> egui::Color32 {
+        match (confidence, visuals.dark_mode) {
+            (MatchConfidence::Exact, _) => success_color(visuals),
+            (MatchConfidence::Verified, true) => egui::Color32::LIGHT_GREEN,
+            (MatchConfidence::Verified, false) => egui::Color32::from_rgb(60, 140, 60),
+            (MatchConfidence::Probable, _) => visuals.warn_fg_color,
+            (MatchConfidence::Approximate, true) => egui::Color32::from_rgb(255, 140, 0),
+            (MatchConfidence::Approximate, false) => egui::Color32::from_rgb(200, 90, 0),
+        }
+    }
+
+    /// How many of `paths` come from groups that aren't exact matches
+    fn count_uncertain(&self, paths: &[PathBuf]) -> usize {
+        let paths: HashSet<&PathBuf> = paths.iter().collect();
+        self.scan_result
+            .iter()
+            .chain(self.result_windows.iter().map(|w| &w.result))
+            .flat_map(|r| &r.duplicate_groups)
+            .filter(|g| g.confidence != MatchConfidence::Exact)
+            .flat_map(|g| &g.files)
+            .map(|f| &f.path)
+            .collect::<HashSet<_>>()
+            .intersection(&paths)
+            .count()
+    }
+
+    /// Draw one group; clicks are returned so callers can apply them to their own selection
+    fn render_group(
+        ui: &mut egui::Ui,
+        group_idx: usize,
+        group: &DuplicateGroup,
+        view: &mut GroupView,
+    ) -> Option<GroupAction> {
+        let mut action = None;
+        let keeper_size = format_size(group.files.first().map(|f| f.size).unwrap_or(0));
+        let header = match group.kind {
+            GroupKind::Exact => format!(
+                "{} files | {} each | {} wasted",
+                group.files.len(),
+                keeper_size,
+                format_size(group.wasted_size)
+            ),
+            GroupKind::PartialCopy => format!(
+                "Partial copies | {} files | {} complete | {} wasted",
+                group.files.len(),
+                keeper_size,
+                format_size(group.wasted_size)
+            ),
+            GroupKind::SimilarImage => format!(
+                "Similar images | {} files | up to {} bits apart | {} wasted",
+                group.files.len(),
+                (1..group.files.len())
+                    .filter_map(|idx| group.similarity_distance(idx))
+                    .max()
+                    .unwrap_or(0),
+                format_size(group.wasted_size)
+            ),
+        };
+
+        let verifying = view
+            .reverifying
+            .is_some_and(|jobs| jobs.contains_key(&group.hash));
+        let header = if verifying {
+            format!("{} | re-verifying...", header)
+        } else {
+            header
+        };
+
+        let mut title = egui::text::LayoutJob::default();
+        egui::RichText::new(format!("{} ", group.confidence.label()))
+            .color(Self::confidence_color(group.confidence, ui.visuals()))
+            .strong()
+            .append_to(
+                &mut title,
+                ui.style(),
+                egui::FontSelection::Default,
+                egui::Align::Center,
+            );
+        egui::RichText::new(header).append_to(
+            &mut title,
+            ui.style(),
+            egui::FontSelection::Default,
+            egui::Align::Center,
+        );
+
+        // Every copy but the keeper that can still be acted on
+        let duplicates: Vec<usize> = (1..group.files.len())
+            .filter(|&idx| {
+                let path = &group.files[idx].path;
+                !is_protected(path, view.protected)
+                    && !view.missing.is_some_and(|m| m.contains(path))
+            })
+            .collect();
+        let picked = duplicates
+            .iter()
+            .filter(|&&idx| view.selected_files.contains(&(group_idx, idx)))
+            .count();
+
+        let open = view.expanded.contains(&group.hash);
+        let mut state = egui::collapsing_header::CollapsingState::load_with_default_open(
+            ui.ctx(),
+            ui.make_persistent_id(&group.hash),
+            open,
+        );
+        state.set_open(open);
+        let (toggle, header, _) = state
+            .show_header(ui, |ui| {
+                let mut all = !duplicates.is_empty() && picked == duplicates.len();
+                let some = picked > 0 && !all;
+                if ui
+                    .add_enabled(
+                        !duplicates.is_empty(),
+                        egui::Checkbox::without_text(&mut all).indeterminate(some),
+                    )
+                    .on_hover_text("Select or clear every copy except the keeper")
+                    .changed()
+                {
+                    action = Some(GroupAction::SelectRange(group_idx, duplicates.clone(), all));
+                }
+                ui.add(egui::Label::new(title).sense(egui::Sense::click()))
+            })
+            .body(|ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !duplicates.is_empty(),
+                            egui::Button::new("Select duplicates").small(),
+                        )
+                        .on_hover_text("Select every copy in this group except the keeper")
+                        .clicked()
+                    {
+                        action = Some(GroupAction::SelectDuplicates(group_idx, duplicates.clone()));
+                    }
+                    if ui
+                        .add_enabled(
+                            !duplicates.is_empty(),
+                            egui::Button::new("🗑 Delete duplicates").small(),
+                        )
+                        .on_hover_text("Delete every copy in this group except the keeper")
+                        .clicked()
+                    {
+                        let paths = duplicates
+                            .iter()
+                            .map(|&idx| group.files[idx].path.clone())
+                            .collect();
+                        action = Some(GroupAction::DeleteDuplicates(paths));
+                    }
+                    if view.reverifying.is_some() && group.kind == GroupKind::Exact {
+                        if verifying {
+                            ui.spinner();
+                            ui.label(egui::RichText::new("Re-hashing files...").small());
+                        } else if ui
+                            .small_button("⟳ Re-verify")
+                            .on_hover_text("Re-hash these files now and update the group")
+                            .clicked()
+                        {
+                            action = Some(GroupAction::Reverify(group.clone()));
+                        }
+                    }
+                });
+                let all_images = group.files.iter().all(|f| {
+                    matches!(
+                        Self::get_file_type(&normalized_extension(&f.path)),
+                        FileType::Image | FileType::Gif
+                    )
+                });
+                if all_images {
+                    let mut grid = view.thumbnails.shown.contains(&group.hash);
+                    if ui
+                        .toggle_value(&mut grid, "▦ Thumbnails")
+                        .on_hover_text("Compare the images side by side; click one to select it")
+                        .changed()
+                    {
+                        if grid {
+                            view.thumbnails.shown.insert(group.hash.clone());
+                        } else {
+                            view.thumbnails.shown.remove(&group.hash);
+                        }
+                    }
+                    if grid {
+                        if let Some(grid_action) =
+                            Self::render_thumbnail_grid(ui, group_idx, group, view)
+                        {
+                            action = Some(grid_action);
+                        }
+                    }
+                }
+                for (file_idx, file) in group.files.iter().enumerate() {
+                    if view.flagged_only && !view.flags.contains_key(&file.path) {
+                        continue;
+                    }
+                    let row = ui.horizontal(|ui| {
+                        let protected = is_protected(&file.path, view.protected);
+                        let missing = view.missing.is_some_and(|m| m.contains(&file.path));
+                        let mut selected = view.selected_files.contains(&(group_idx, file_idx));
+                        if ui
+                            .add_enabled(
+                                !protected && !missing,
+                                egui::Checkbox::without_text(&mut selected),
+                            )
+                            .changed()
+                        {
+                            action = Some(match view.anchor {
+                                Some((g, from))
+                                    if g == group_idx && ui.input(|i| i.modifiers.shift) =>
+                                {
+                                    let range = from.min(file_idx)..=from.max(file_idx);
+                                    let files = range
+                                        .filter(|&idx| {
+                                            let path = &group.files[idx].path;
+                                            !is_protected(path, view.protected)
+                                                && !view.missing.is_some_and(|m| m.contains(path))
+                                        })
+                                        .collect();
+                                    GroupAction::SelectRange(group_idx, files, selected)
+                                }
+                                _ => GroupAction::Select(group_idx, file_idx, selected),
+                            });
+                        }
+                        Self::render_flag_button(ui, &file.path, view.flags);
+                        if protected {
+                            ui.label("🔒").on_hover_text("In a protected folder");
+                        }
+
+                        if file_idx == 0 {
+                            ui.label(
+                                egui::RichText::new("[KEEP]")
+                                    .color(success_color(ui.visuals()))
+                                    .strong(),
+                            );
+                        } else if view.set_keeper
+                            && group.kind != GroupKind::PartialCopy
+                            && ui
+                                .small_button("⭐")
+                                .on_hover_text("Set as keeper")
+                                .clicked()
+                        {
+                            action = Some(GroupAction::SetKeeper(group_idx, file_idx));
+                        }
+                        if view.new_files.is_some_and(|new| new.contains(&file.path)) {
+                            ui.label(
+                                egui::RichText::new("[NEW]")
+                                    .color(highlight_color(ui.visuals()))
+                                    .strong(),
+                            )
+                            .on_hover_text("Appeared after the scan");
+                        }
+
+                        let icon = match Self::get_file_type(&normalized_extension(&file.path)) {
+                            FileType::Image => "🖼",
+                            FileType::Gif => "🎞",
+                            FileType::Video => "🎬",
+                            FileType::Audio => "🎵",
+                            FileType::Text => "📄",
+                            FileType::Other => "📁",
+                        };
+                        ui.label(icon);
+                        let name = if missing {
+                            egui::RichText::new(&file.name)
+                                .strikethrough()
+                                .color(egui::Color32::GRAY)
+                        } else {
+                            egui::RichText::new(&file.name)
+                        };
+                        let name = ui.add(egui::Label::new(name).sense(egui::Sense::click()));
+                        name.context_menu(|ui| Self::copy_path_buttons(ui, &file.path));
+                        if missing {
+                            name.on_hover_text("No longer exists on disk");
+                            ui.label(egui::RichText::new("[MISSING]").color(egui::Color32::GRAY));
+                        } else {
+                            name.on_hover_text("Right-click to copy the path");
+                        }
+                        ui.label(format_size(file.size));
+                        if let Some(distance) =
+                            group.similarity_distance(file_idx).filter(|_| file_idx > 0)
+                        {
+                            ui.label(
+                                egui::RichText::new(format!("Δ{}", distance))
+                                    .color(Self::confidence_color(group.confidence, ui.visuals())),
+                            )
+                            .on_hover_text(format!(
+                                "Differs from the keeper in {} of 64 perceptual hash bits",
+                                distance
+                            ));
+                        }
+
+                        if ui.small_button("👁").on_hover_text("Preview").clicked() {
+                            action = Some(GroupAction::Preview(file.clone()));
+                        }
+                        if ui
+                            .small_button("📂")
+                            .on_hover_text("Open folder & select file")
+                            .clicked()
+                        {
+                            action = Some(GroupAction::Reveal(file.path.clone()));
+                        }
+                    });
+                    if view.focused == Some((group_idx, file_idx)) {
+                        let rect = row.response.rect.expand(1.0);
+                        ui.painter()
+                            .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+                        if view.scroll_to_focus {
+                            row.response.scroll_to_me(None);
+                            view.scroll_to_focus = false;
+                        }
+                    }
+                }
+            });
+        // Open state is driven explicitly, so header clicks have to be applied by hand
+        if toggle.clicked() || header.inner.clicked() {
+            if open {
+                view.expanded.remove(&group.hash);
+            } else {
+                view.expanded.insert(group.hash.clone());
+            }
+        }
+        action
+    }
+
+    /// Menu entries that put a file's full path or name on the clipboard
+    fn copy_path_buttons(ui: &mut egui::Ui, path: &Path) {
+        if ui.button("Copy full path").clicked() {
+            ui.ctx().copy_text(path.display().to_string());
+            ui.close_menu();
+        }
+        if ui.button("Copy file name").clicked() {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+            ui.ctx().copy_text(name.to_string_lossy().into_owned());
+            ui.close_menu();
+        }
+    }
+
+    /// Thumbnails of an image group side by side; clicking one toggles its selection
+    fn render_thumbnail_grid(
+        ui: &mut egui::Ui,
+        group_idx: usize,
+        group: &DuplicateGroup,
+        view: &mut GroupView,
+    ) -> Option<GroupAction> {
+        let mut action = None;
+        let size = egui::vec2(THUMBNAIL_SIZE as f32, THUMBNAIL_SIZE as f32);
+        ui.horizontal_wrapped(|ui| {
+            for (file_idx, file) in group.files.iter().enumerate() {
+                let selectable = !is_protected(&file.path, view.protected)
+                    && !view.missing.is_some_and(|m| m.contains(&file.path));
+                let selected = view.selected_files.contains(&(group_idx, file_idx));
+                ui.vertical(|ui| {
+                    ui.set_width(size.x + 8.0);
+                    let response = match view.thumbnails.get(ui.ctx(), &file.path) {
+                        Some(texture) => ui.add_enabled(
+                            selectable,
+                            egui::ImageButton::new(
+                                egui::Image::new(&texture)
+                                    .max_size(size)
+                                    .maintain_aspect_ratio(true),
+                            )
+                            .selected(selected),
+                        ),
+                        None => ui.add_enabled(
+                            selectable,
+                            egui::Button::new("🖼").min_size(size).selected(selected),
+                        ),
+                    };
+                    let response = response.on_hover_text(file.path.display().to_string());
+                    response.context_menu(|ui| Self::copy_path_buttons(ui, &file.path));
+                    if response.clicked() {
+                        action = Some(GroupAction::Select(group_idx, file_idx, !selected));
+                    }
+                    let caption = if file_idx == 0 {
+                        egui::RichText::new("[KEEP]").color(success_color(ui.visuals()))
+                    } else {
+                        egui::RichText::new(format_size(file.size))
+                    };
+                    ui.label(caption.small());
+                });
+            }
+        });
+        action
+    }
+
+    /// Hashes of the `n` groups wasting the most space
+    fn top_groups_by_waste(result: &ScanResult, n: usize) -> HashSet<String> {
+        let mut groups: Vec<&DuplicateGroup> = result.duplicate_groups.iter().collect();
+        groups.sort_by_key(|g| std::cmp::Reverse(g.wasted_size));
+        groups.into_iter().take(n).map(|g| g.hash.clone()).collect()
+    }
+
+    /// Colored dot that opens a menu for flagging a file during review
+    fn render_flag_button(ui: &mut egui::Ui, path: &Path, flags: &mut HashMap<PathBuf, FileFlag>) {
+        let (dot, hover) = match flags.get(path) {
+            Some(flag) if flag.note.is_empty() => (
+                egui::RichText::new("●").color(flag.color.color32()),
+                flag.color.label().to_string(),
+            ),
+            Some(flag) => (
+                egui::RichText::new("●").color(flag.color.color32()),
+                format!("{}: {}", flag.color.label(), flag.note),
+            ),
+            None => (
+                egui::RichText::new("○").color(egui::Color32::DARK_GRAY),
+                "Flag for later".to_string(),
+            ),
+        };
+        ui.menu_button(dot, |ui| {
+            let mut clear = false;
+            ui.horizontal(|ui| {
+                for color in FlagColor::ALL {
+                    let current = flags.get(path).map(|f| f.color);
+                    let text = egui::RichText::new("●").color(color.color32());
+                    if ui
+                        .selectable_label(current == Some(color), text)
+                        .on_hover_text(color.label())
+                        .clicked()
+                    {
+                        flags.entry(path.to_path_buf()).or_default().color = color;
+                    }
+                }
+            });
+            if let Some(flag) = flags.get_mut(path) {
+                ui.add(egui::TextEdit::singleline(&mut flag.note).hint_text("Note"));
+                clear = ui.button("Clear flag").clicked();
+            }
+            if clear {
+                flags.remove(path);
+                ui.close_menu();
+            }
+        })
+        .response
+        .on_hover_text(hover);
+    }
+
+    /// Select every non-keeper file outside the protected folders
+    fn select_all_duplicates(&mut self) {
+        self.selected_files.clear();
+        let Some(result) = &self.scan_result else {
+            return;
+        };
+        let protected = &self.scan_config.protected_folders;
+        for (g, group) in result.duplicate_groups.iter().enumerate() {
+            for (f, file) in group.files.iter().enumerate().skip(1) {
+                if !is_protected(&file.path, protected) {
+                    self.selected_files.push((g, f));
+                }
+            }
+        }
+    }
+
+    /// Select every unselected file and unselect the rest, leaving keepers
+    /// alone unless `invert_includes_keepers` is set
+    fn invert_selection(&mut self) {
+        let Some(result) = &self.scan_result else {
+            return;
+        };
+        let protected = &self.scan_config.protected_folders;
+        let previous: HashSet<(usize, usize)> = self.selected_files.drain(..).collect();
+        for (g, group) in result.duplicate_groups.iter().enumerate() {
+            for (f, file) in group.files.iter().enumerate() {
+                let was_selected = previous.contains(&(g, f));
+                let flippable = (f > 0 || self.invert_includes_keepers)
+                    && !is_protected(&file.path, protected)
+                    && !self.missing_files.contains(&file.path);
+                if was_selected != flippable {
+                    self.selected_files.push((g, f));
+                }
+            }
+        }
+    }
+
+    fn open_delete_dialog(&mut self) {
+        if !self.selected_files.is_empty() {
+            self.show_confirmation_dialog =
+                Some(ConfirmationDialog::DeleteFiles(self.get_selected_paths()));
+        }
+    }
+
+    /// Keyboard shortcuts for the main list; ignored while typing or while a dialog is open
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if self.is_scanning || ctx.wants_keyboard_input() || self.show_confirmation_dialog.is_some()
+        {
+            return;
+        }
+        let pressed = |modifiers, key| ctx.input_mut(|i| i.consume_key(modifiers, key));
+        use egui::{Key, Modifiers};
+
+        if pressed(Modifiers::COMMAND, Key::Z) {
+            self.undo_last_batch();
+        }
+        if self.scan_result.is_none() {
+            return;
+        }
+        if pressed(Modifiers::COMMAND, Key::A) {
+            self.select_all_duplicates();
+        }
+        if pressed(Modifiers::NONE, Key::Delete) {
+            self.open_delete_dialog();
+        }
+        if pressed(Modifiers::NONE, Key::ArrowDown) {
+            self.move_focus(true);
+        }
+        if pressed(Modifiers::NONE, Key::ArrowUp) {
+            self.move_focus(false);
+        }
+        if pressed(Modifiers::NONE, Key::Space) {
+            self.toggle_focused_row();
+        }
+    }
+
+    /// Step the focused row through the files of expanded groups
+    fn move_focus(&mut self, forward: bool) {
+        let Some(result) = &self.scan_result else {
+            return;
+        };
+        let query = GroupQuery::parse(&self.group_query);
+        let rows: Vec<(usize, usize)> = result
+            .duplicate_groups
+            .iter()
+            .enumerate()
+            .filter(|(_, group)| {
+                self.expanded_groups.contains(&group.hash) && self.group_visible(group, &query)
+            })
+            .flat_map(|(g, group)| (0..group.files.len()).map(move |f| (g, f)))
+            .collect();
+        let current = self
+            .focused_row
+            .and_then(|row| rows.iter().position(|&r| r == row));
+        let next = match (current, forward) {
+            (Some(idx), true) => (idx + 1).min(rows.len().saturating_sub(1)),
+            (Some(idx), false) => idx.saturating_sub(1),
+            (None, true) => 0,
+            (None, false) => rows.len().saturating_sub(1),
+        };
+        if let Some(&row) = rows.get(next) {
+            self.focused_row = Some(row);
+            self.scroll_to_focus = true;
+        }
+    }
+
+    fn toggle_focused_row(&mut self) {
+        let Some((g, f)) = self.focused_row else {
+            return;
+        };
+        let Some(file) = self
+            .scan_result
+            .as_ref()
+            .and_then(|r| r.duplicate_groups.get(g))
+            .and_then(|group| group.files.get(f))
+        else {
+            return;
+        };
+        if is_protected(&file.path, &self.scan_config.protected_folders)
+            || self.missing_files.contains(&file.path)
+        {
+            return;
+        }
+        if self.selected_files.contains(&(g, f)) {
+            self.selected_files.retain(|&sel| sel != (g, f));
+        } else {
+            self.selected_files.push((g, f));
+        }
+    }
+
+    /// Apply a group row click against the given selection
+    fn apply_group_action(
+        &mut self,
+        action: GroupAction,
+        selected_files: &mut Vec<(usize, usize)>,
+    ) {
+        match action {
+            GroupAction::Select(group_idx, file_idx, true) => {
+                selected_files.push((group_idx, file_idx))
+            }
+            GroupAction::Select(group_idx, file_idx, false) => {
+                selected_files.retain(|&(g, f)| g != group_idx || f != file_idx)
+            }
+            GroupAction::Preview(file) => self.load_file_preview(&file),
+            GroupAction::Reveal(path) => self.open_folder_and_select_file_safe(&path),
+            GroupAction::Reverify(group) => self.start_reverify(group),
+            GroupAction::SetKeeper(group_idx, file_idx) => {
+                let Some(result) = self.scan_result.as_mut() else {
+                    return;
+                };
+                let Some(group) = result.duplicate_groups.get_mut(group_idx) else {
+                    return;
+                };
+                if !group.set_keeper(file_idx) {
+                    return;
+                }
+                // Waste is counted against the folders of the non-keepers
+                self.folder_breakdown = result.wasted_by_folder();
+                // The new keeper can't stay selected; the old one moved to its slot
+                selected_files.retain(|&sel| sel != (group_idx, file_idx));
+                for sel in selected_files.iter_mut() {
+                    if *sel == (group_idx, 0) {
+                        sel.1 = file_idx;
+                    }
+                }
+                self.session_dirty = true;
+            }
+            GroupAction::SelectRange(group_idx, files, true) => {
+                for f in files {
+                    if !selected_files.contains(&(group_idx, f)) {
+                        selected_files.push((group_idx, f));
+                    }
+                }
+            }
+            GroupAction::SelectRange(group_idx, files, false) => {
+                selected_files.retain(|&(g, f)| g != group_idx || !files.contains(&f))
+            }
+            GroupAction::SelectDuplicates(group_idx, files) => {
+                selected_files.retain(|&(g, _)| g != group_idx);
+                selected_files.extend(files.into_iter().map(|f| (group_idx, f)));
+            }
+            GroupAction::DeleteDuplicates(paths) => {
+                if self.show_confirmation_dialog.is_none() {
+                    self.show_confirmation_dialog = Some(ConfirmationDialog::DeleteFiles(paths));
+                }
+            }
+        }
+    }
+
+    fn start_reverify(&mut self, group: DuplicateGroup) {
+        if self.reverify_jobs.contains_key(&group.hash) {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        // New hashes have to be comparable with the rest of the result
+        let mut config = self.scan_config.clone();
+        if let Some(result) = &self.scan_result {
+            config.hash_algorithm = result.hash_algorithm;
+            config.verify_strong = true;
+        }
+        let scanner = Scanner::new(config);
+        let hash = group.hash.clone();
+        let spawned = thread::Builder::new()
+            .name("fxs-reverify".into())
+            .spawn(move || {
+                let _ = tx.send(scanner.reverify_group(&group));
+            });
+        match spawned {
+            Ok(_) => {
+                self.reverify_jobs.insert(hash, rx);
+            }
+            Err(e) => {
+                self.status_message = Some((
+                    format!("Failed to start re-verify: {}", e),
+                    MessageType::Error,
+                ));
+            }
+        }
+    }
+
+    /// Apply finished group re-verifications to the current result
+    fn poll_reverify_jobs(&mut self, ctx: &egui::Context) {
+        if self.reverify_jobs.is_empty() {
+            return;
+        }
+        let mut finished = Vec::new();
+        self.reverify_jobs.retain(|hash, rx| match rx.try_recv() {
+            Ok(groups) => {
+                finished.push((hash.clone(), groups));
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+        ctx.request_repaint();
+
+        let Some(result) = self.scan_result.as_mut() else {
+            return;
+        };
+        let replaced = !finished.is_empty();
+        if replaced {
+            // Group indices shift when a group is replaced
+            self.selected_files.clear();
+            self.session_dirty = true;
+        }
+        for (hash, groups) in finished {
+            let message = match groups.len() {
+                0 => "Re-verified: files no longer match".to_string(),
+                1 => format!("Re-verified: {} files still match", groups[0].files.len()),
+                n => format!("Re-verified: group split into {}", n),
+            };
+            if self.expanded_groups.remove(&hash) {
+                self.expanded_groups
+                    .extend(groups.iter().map(|g| g.hash.clone()));
+            }
+            result.replace_group(&hash, groups);
+            self.status_message = Some((message, MessageType::Success));
+        }
+        if replaced {
+            // Split-off groups may belong elsewhere under the chosen sort
+            self.apply_group_sort();
+        }
+    }
+
+    /// (Re)start watching the current result's folders, or stop if watching is off
+    fn restart_watcher(&mut self) {
+        self.watcher = None;
+        if !self.watch_folders {
+            return;
+        }
+        let Some(result) = self.scan_result.as_ref().filter(|r| !r.folders.is_empty()) else {
+            return;
+        };
+        match DuplicateWatcher::start(&result.folders, result, &self.scan_config) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(e) => {
+                self.watch_folders = false;
+                self.status_message = Some((e, MessageType::Error));
+            }
+        }
+    }
+
+    /// Fold duplicates found by the watcher into the current result
+    fn poll_watcher(&mut self, ctx: &egui::Context) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        // Watcher events don't wake the UI on their own
+        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        let Some(result) = self.scan_result.as_mut() else {
+            return;
+        };
+
+        let mut added = 0;
+        for duplicate in watcher.poll() {
+            let path = duplicate.file.path.clone();
+            if result.insert_hashed(duplicate.file, [duplicate.peer]) {
+                self.new_duplicates.insert(path);
+                added += 1;
+            }
+        }
+        if added > 0 {
+            self.session_dirty = true;
+            self.status_message = Some((
+                format!("{} new duplicate(s) detected", added),
+                MessageType::Info,
+            ));
+        }
+    }
+
+    fn render_confirmation_dialog(&mut self, ctx: &egui::Context) {
+        let dialog = match self.show_confirmation_dialog.clone() {
+            Some(d) => d,
+            None => return,
+        };
+
+        let mut confirmed = false;
+        let mut cancelled =
+            ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape));
+        let max_height = ctx.screen_rect().height() * 0.7;
+        let all_copies = match &dialog {
+            ConfirmationDialog::DeleteFiles(paths) => self.groups_losing_every_copy(paths),
+            _ => Vec::new(),
+        };
+        let blocked = match &dialog {
+            ConfirmationDialog::RenameFiles(renames) => {
+                renames.is_empty() || self.rename_error.is_some()
+            }
+            _ => !all_copies.is_empty() && !self.confirm_all_copies,
+        };
+        let mut relayout = false;
+        let mut repattern = false;
+
+        egui::Window::new("Confirm")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .max_height(max_height)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                // Buttons go in first so they stay pinned below the scrolling body
+                egui::TopBottomPanel::bottom("confirm_actions")
+                    .show_separator_line(true)
+                    .show_inside(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let action = match &dialog {
+                                ConfirmationDialog::DeleteFiles(_)
+                                | ConfirmationDialog::DeleteFolders(_) => "Delete",
+                                ConfirmationDialog::MoveFiles(_, _) => "Move",
+                                ConfirmationDialog::LinkToKeeper(_) => "Link",
+                                ConfirmationDialog::RenameFiles(_) => "Rename",
+                            };
+                            let blocked_hint = match &dialog {
+                                ConfirmationDialog::RenameFiles(_) => "Fix the pattern first",
+                                _ => "Confirm deleting every copy first",
+                            };
+                            if ui
+                                .add_enabled(!blocked, egui::Button::new(action))
+                                .on_disabled_hover_text(blocked_hint)
+                                .clicked()
+                            {
+                                confirmed = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                cancelled = true;
+                            }
+                            ui.toggle_value(&mut self.preview_operations, "Preview")
+                                .on_hover_text("List exactly what will happen to each file");
+                        });
+                    });
+
+                egui::ScrollArea::vertical()
+                    .id_salt("confirm_body")
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        match &dialog {
+                            ConfirmationDialog::DeleteFiles(paths) => {
+                                ui.label(format!("Delete {} file(s)?", paths.len()));
+                                if !all_copies.is_empty() {
+                                    Self::render_all_copies_warning(
+                                        ui,
+                                        &all_copies,
+                                        &mut self.confirm_all_copies,
+                                    );
+                                }
+                                if ui
+                                    .checkbox(
+                                        &mut self.file_ops.use_recycle_bin,
+                                        "Move to Recycle Bin",
+                                    )
+                                    .changed()
+                                {
+                                    self.operation_preview = None;
+                                }
+                                if self.file_ops.use_recycle_bin {
+                                    ui.label(
+                                        egui::RichText::new(
+                                            "Files can be restored from the Recycle Bin, \
+                                         but keep using disk space until it's emptied. \
+                                         Files it refuses are left in place.",
+                                        )
+                                        .small(),
+                                    );
+                                } else {
+                                    ui.label(
+                                        egui::RichText::new("Files will be erased permanently!")
+                                            .color(egui::Color32::RED)
+                                            .small(),
+                                    );
+                                }
+                                let check = self
+                                    .space_check
+                                    .get_or_insert_with(|| SpaceCheck::for_delete(paths));
+                                Self::render_space_check(ui, check, self.file_ops.use_recycle_bin);
+                                let uncertain = self.count_uncertain(paths);
+                                if uncertain > 0 {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                        "⚠ {} file(s) are from groups that aren't exact matches; \
+                                         check them before deleting",
+                                        uncertain
+                                    ))
+                                        .color(ui.visuals().warn_fg_color),
+                                    );
+                                }
+                                ui.separator();
+                                self.render_file_preview(ui, paths);
+                            }
+                            ConfirmationDialog::MoveFiles(moves, dest) => {
+                                ui.label(format!("Move {} file(s)?", moves.len()));
+                                ui.label(egui::RichText::new(dest.display().to_string()).small());
+                                egui::ComboBox::from_id_salt("confirm_move_layout")
+                                    .selected_text(self.move_layout.label())
+                                    .show_ui(ui, |ui| {
+                                        for layout in MoveLayout::ALL {
+                                            relayout |= ui
+                                                .selectable_value(
+                                                    &mut self.move_layout,
+                                                    layout,
+                                                    layout.label(),
+                                                )
+                                                .changed();
+                                        }
+                                    });
+                                let folders: HashSet<&PathBuf> =
+                                    moves.iter().map(|(_, dir)| dir).collect();
+                                if !folders.iter().all(|dir| *dir == dest) {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "Into {} subfolder(s)",
+                                            folders.len()
+                                        ))
+                                        .small(),
+                                    );
+                                } else if self.move_layout == MoveLayout::PreserveStructure
+                                    && file_ops::common_base(moves.iter().map(|(p, _)| p.as_path()))
+                                        .is_none()
+                                {
+                                    ui.label(
+                                        egui::RichText::new(
+                                            "The files share no folder, so they'll be moved flat",
+                                        )
+                                        .small(),
+                                    );
+                                }
+                                if let Some(check) = &self.space_check {
+                                    Self::render_space_check(ui, check, false);
+                                }
+                                ui.separator();
+                                let paths: Vec<PathBuf> =
+                                    moves.iter().map(|(p, _)| p.clone()).collect();
+                                self.render_file_preview(ui, &paths);
+                            }
+                            ConfirmationDialog::LinkToKeeper(links) => {
+                                ui.label(format!(
+                                    "Replace {} file(s) with hard links to their keeper?",
+                                    links.len()
+                                ));
+                                ui.label(
+                                    egui::RichText::new(
+                                        "Every path stays, but linked paths share one copy: \
+                                     editing any of them changes all. This can't be undone.",
+                                    )
+                                    .small(),
+                                );
+                                let paths: Vec<PathBuf> =
+                                    links.iter().map(|(p, _)| p.clone()).collect();
+                                let uncertain = self.count_uncertain(&paths);
+                                if uncertain > 0 {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                        "⚠ {} file(s) are from groups that aren't exact matches; \
+                                         check them before linking",
+                                        uncertain
+                                    ))
+                                        .color(ui.visuals().warn_fg_color),
+                                    );
+                                }
+                                ui.separator();
+                                self.render_file_preview(ui, &paths);
+                            }
+                            ConfirmationDialog::DeleteFolders(folders) => {
+                                ui.label(format!(
+                                    "Delete {} folder(s) and everything in them?",
+                                    folders.len()
+                                ));
+                                if ui
+                                    .checkbox(
+                                        &mut self.file_ops.use_recycle_bin,
+                                        "Move to Recycle Bin",
+                                    )
+                                    .changed()
+                                {
+                                    self.operation_preview = None;
+                                }
+                                if !self.file_ops.use_recycle_bin {
+                                    ui.label(
+                                        egui::RichText::new("Folders will be erased permanently!")
+                                            .color(egui::Color32::RED)
+                                            .small(),
+                                    );
+                                }
+                                let check = self.space_check.get_or_insert_with(|| {
+                                    SpaceCheck::for_delete(
+                                        folders.iter().flat_map(|(_, matched)| matched),
+                                    )
+                                });
+                                Self::render_space_check(ui, check, self.file_ops.use_recycle_bin);
+                                ui.label(
+                                    egui::RichText::new(
+                                        "A folder is kept if it holds anything the scan \
+                                         didn't match, such as hidden or excluded files.",
+                                    )
+                                    .small(),
+                                );
+                                ui.separator();
+                                for (folder, _) in folders {
+                                    ui.label(
+                                        egui::RichText::new(folder.display().to_string()).small(),
+                                    );
+                                }
+                            }
+                            ConfirmationDialog::RenameFiles(renames) => {
+                                ui.label(format!("Rename {} file(s)?", renames.len()));
+                                repattern = ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut self.rename_pattern)
+                                            .hint_text(DEFAULT_RENAME_PATTERN),
+                                    )
+                                    .changed();
+                                ui.label(
+                                    egui::RichText::new(
+                                        "{name} current name, {index} 1, 2, 3... across the \
+                                         selection, {n} position in its group, {hash8} start \
+                                         of the group hash. The extension is kept.",
+                                    )
+                                    .small(),
+                                );
+                                if let Some(error) = &self.rename_error {
+                                    ui.label(
+                                        egui::RichText::new(format!("⚠ {}", error))
+                                            .color(ui.visuals().warn_fg_color),
+                                    );
+                                }
+                                ui.separator();
+                                for (from, to) in renames {
+                                    let name = |p: &PathBuf| {
+                                        p.file_name()
+                                            .map(|n| n.to_string_lossy().into_owned())
+                                            .unwrap_or_default()
+                                    };
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "{} → {}",
+                                            name(from),
+                                            name(to)
+                                        ))
+                                        .small(),
+                                    )
+                                    .on_hover_text(from.display().to_string());
+                                }
+                            }
+                        }
+                        self.render_operation_preview(ui, &dialog);
+                    });
+            });
+
+        if cancelled {
+            self.show_confirmation_dialog = None;
+            self.space_check = None;
+        } else if confirmed {
+            self.execute_confirmed(&dialog);
+        } else if let (true, ConfirmationDialog::MoveFiles(_, dest), Some(result)) =
+            (relayout, &dialog, &self.scan_result)
+        {
+            let moves = self.move_targets(result, dest);
+            self.show_confirmation_dialog =
+                Some(ConfirmationDialog::MoveFiles(moves, dest.clone()));
+            self.operation_preview = None;
+        } else if repattern {
+            if let Some(result) = self.scan_result.clone() {
+                self.open_rename_dialog(&result);
+            }
+        }
+        if self.show_confirmation_dialog.is_none() {
+            self.thumbnails.clear();
+            self.operation_preview = None;
+            self.confirm_all_copies = false;
+        }
+    }
+
+    /// Keeper names of groups in which every file is among `paths`
+    fn groups_losing_every_copy(&self, paths: &[PathBuf]) -> Vec<String> {
+        let paths: HashSet<&PathBuf> = paths.iter().collect();
+        self.scan_result
+            .iter()
+            .chain(self.result_windows.iter().map(|w| &w.result))
+            .flat_map(|r| &r.duplicate_groups)
+            .filter(|g| !g.files.is_empty() && g.files.iter().all(|f| paths.contains(&f.path)))
+            .map(|g| g.files[0].name.clone())
+            .collect()
+    }
+
+    /// Warning that a delete leaves no copy behind, with the checkbox that unblocks it
+    fn render_all_copies_warning(ui: &mut egui::Ui, names: &[String], confirmed: &mut bool) {
+        const MAX_NAMED: usize = 10;
+
+        ui.label(
+            egui::RichText::new(format!(
+                "⚠ Every copy in {} group(s) is selected; no copy of these files will remain:",
+                names.len()
+            ))
+            .color(egui::Color32::RED)
+            .strong(),
+        );
+        for name in names.iter().take(MAX_NAMED) {
+            ui.label(egui::RichText::new(format!("  • {}", name)).color(egui::Color32::RED));
+        }
+        if names.len() > MAX_NAMED {
+            ui.label(format!("and {} more", names.len() - MAX_NAMED));
+        }
+        ui.checkbox(confirmed, "I understand I'm deleting all copies");
+    }
+
+    /// "Keep operation log" checkbox plus a button revealing the log file
+    fn render_operation_log_toggle(&mut self, ui: &mut egui::Ui) {
+        let mut keep_log = self.file_ops.log_file.is_some();
+        if ui
+            .checkbox(&mut keep_log, "Keep operation log")
+            .on_hover_text("Record every delete, move and link in a file that survives restarts")
+            .changed()
+        {
+            self.file_ops.log_file = if keep_log {
+                FileOperations::default_log_path()
+            } else {
+                None
+            };
+        }
+        let Some(log_file) = self.file_ops.log_file.clone() else {
+            return;
         };
+        if ui
+            .add_enabled(log_file.exists(), egui::Button::new("Open log location"))
+            .on_hover_text(log_file.display().to_string())
+            .on_disabled_hover_text("Nothing has been logged yet")
+            .clicked()
+        {
+            self.open_folder_and_select_file_safe(&log_file);
+        }
+    }
 
-        ui.separator();
+    /// Groups the running scan has confirmed so far, read-only until it finishes
+    fn render_live_groups(&self, ui: &mut egui::Ui) {
+        let Ok(groups) = self.scan_state.progress.groups.lock() else {
+            return;
+        };
+        if groups.is_empty() {
+            return;
+        }
 
+        ui.separator();
+        let wasted: u64 = groups.iter().map(|g| g.wasted_size).sum();
         ui.label(format!(
-            "Scanned {} files ({}) | {} groups | {} duplicates | {} wasted",
-            result.total_files,
-            format_size(result.total_size),
-            result.duplicate_groups.len(),
-            result.total_duplicates,
-            format_size(result.wasted_space)
+            "Found so far: {} groups | {} wasted",
+            groups.len(),
+            format_size(wasted)
         ));
-
-        ui.separator();
-
-        ui.horizontal(|ui| {
-            let count = self.selected_files.len();
-            if ui
-                .add_enabled(count > 0, egui::Button::new(format!("Delete ({})", count)))
-                .clicked()
-            {
-                self.show_confirmation_dialog =
-                    Some(ConfirmationDialog::DeleteFiles(self.get_selected_paths()));
-            }
-            if ui
-                .add_enabled(count > 0, egui::Button::new(format!("Move ({})", count)))
-                .clicked()
-            {
-                if let Some(dest) = FileDialog::new().pick_folder() {
-                    self.show_confirmation_dialog = Some(ConfirmationDialog::MoveFiles(
-                        self.get_selected_paths(),
-                        dest,
-                    ));
-                }
-            }
-            if ui.button("Select All").clicked() {
-                self.selected_files.clear();
-                for (g, group) in result.duplicate_groups.iter().enumerate() {
-                    for f in 1..group.files.len() {
-                        self.selected_files.push((g, f));
-                    }
+        ui.label(
+            egui::RichText::new("Files can be selected once the scan finishes")
+                .small()
+                .color(egui::Color32::GRAY),
+        );
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .id_salt("live_groups")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, groups.len(), |ui, rows| {
+                for group in &groups[rows] {
+                    let Some(keeper) = group.files.first() else {
+                        continue;
+                    };
+                    let paths: Vec<String> = group
+                        .files
+                        .iter()
+                        .map(|f| f.path.display().to_string())
+                        .collect();
+                    ui.label(format!(
+                        "{} × {} ({}) | {} wasted",
+                        group.files.len(),
+                        keeper.name,
+                        format_size(keeper.size),
+                        format_size(group.wasted_size)
+                    ))
+                    .on_hover_text(paths.join("\n"));
                 }
-            }
-            if ui.button("Clear").clicked() {
-                self.selected_files.clear();
-            }
+            });
+    }
+
+    /// Dry-run list of what confirming the dialog would do to each file
+    fn render_operation_preview(&mut self, ui: &mut egui::Ui, dialog: &ConfirmationDialog) {
+        if !self.preview_operations {
+            self.operation_preview = None;
+            return;
+        }
+        let preview = self.operation_preview.get_or_insert_with(|| {
+            let mut ops = self.file_ops.dry_run_copy();
+            run_confirmed(&mut ops, dialog).2
         });
 
         ui.separator();
-
-        let available = ui.available_size();
+        ui.label("Planned operations:");
         egui::ScrollArea::vertical()
-            .id_salt("main_list")
-            .auto_shrink([false, false])
-            .max_height(available.y)
+            .id_salt("operation_preview")
+            .max_height(200.0)
             .show(ui, |ui| {
-                for (group_idx, group) in result.duplicate_groups.iter().enumerate() {
-                    self.render_group(ui, group_idx, group);
+                for result in preview.iter() {
+                    match result {
+                        OperationResult::Success(msg) => ui.label(egui::RichText::new(msg).small()),
+                        OperationResult::Error(msg) => ui.label(
+                            egui::RichText::new(format!("⚠ {}", msg))
+                                .small()
+                                .color(ui.visuals().warn_fg_color),
+                        ),
+                    };
                 }
             });
     }
 
-    fn render_preview_panel(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        let available_height = ui.available_height();
-        let width = ui.available_width();
-
-        ui.vertical(|ui| {
-            ui.label(egui::RichText::new("Preview").strong());
-            ui.separator();
-
-            let preview = match self.preview_file.clone() {
-                Some(p) => p,
-                None => {
+    /// Projected free space; `recycle` says whether deleted files go to the bin
+    fn render_space_check(ui: &mut egui::Ui, check: &SpaceCheck, recycle: bool) {
+        let (space, incoming, same_volume) = match check {
+            SpaceCheck::Move {
+                space,
+                incoming,
+                same_volume,
+            } => (space, *incoming, *same_volume),
+            SpaceCheck::Delete(volumes) => {
+                for freeing in volumes {
+                    let after = if recycle {
+                        freeing.space.available
+                    } else {
+                        freeing.space.available_after_freeing(freeing.freed)
+                    };
                     ui.label(
-                        egui::RichText::new("Click 👁 to preview a file")
-                            .small()
-                            .italics(),
+                        egui::RichText::new(format!(
+                            "{} free after delete: {} of {}",
+                            freeing.mount_point.display(),
+                            format_size(after),
+                            format_size(freeing.space.total)
+                        ))
+                        .small(),
                     );
-                    return;
+                    if recycle && freeing.space.near_full_after(0) {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "⚠ {} is nearly full, and recycling frees nothing \
+                                 until the Recycle Bin is emptied",
+                                freeing.mount_point.display()
+                            ))
+                            .color(ui.visuals().warn_fg_color),
+                        );
+                    }
                 }
-            };
-
-            ui.label(egui::RichText::new(&preview.name).strong().size(11.0));
-            ui.label(format!(
-                "{} | {}",
-                format_size(preview.size),
-                preview.extension.to_uppercase()
-            ));
-
-            if let Some((w, h)) = preview.dimensions {
-                ui.label(egui::RichText::new(format!("{}x{}", w, h)).small());
+                return;
             }
+        };
+        ui.label(
+            egui::RichText::new(format!(
+                "Destination free after move: {} of {}",
+                format_size(space.available_after(incoming)),
+                format_size(space.total)
+            ))
+            .small(),
+        );
+        if same_volume > 0 {
+            // Moving within a drive is a rename, so nothing is reclaimed
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} is already on this drive; moving it frees no space",
+                    format_size(same_volume)
+                ))
+                .small(),
+            );
+        }
+        if space.near_full_after(incoming) {
+            ui.label(
+                egui::RichText::new("⚠ Destination drive will be nearly full")
+                    .color(ui.visuals().warn_fg_color),
+            );
+        }
+    }
 
-            ui.add_space(8.0);
+    fn execute_confirmed(&mut self, dialog: &ConfirmationDialog) {
+        let (verb, paths, results) = run_confirmed(&mut self.file_ops, dialog);
 
-            let header_used = 90.0;
-            let button_height = 35.0;
-            let content_height = (available_height - header_used - button_height).max(80.0);
+        if let ConfirmationDialog::RenameFiles(renames) = dialog {
+            self.finish_rename(renames, &results);
+            return;
+        }
+        let done: HashSet<PathBuf> = paths
+            .iter()
+            .zip(&results)
+            .filter(|(_, r)| matches!(r, OperationResult::Success(_)))
+            .map(|(p, _)| p.clone())
+            .collect();
+        // Surface why files were left alone, e.g. links across drives
+        let first_error = results.iter().find_map(|r| match r {
+            OperationResult::Error(e) => Some(e),
+            OperationResult::Success(_) => None,
+        });
+        self.status_message = Some(match first_error {
+            Some(e) => (
+                format!("{} {}/{} | {}", verb, done.len(), paths.len(), e),
+                MessageType::Error,
+            ),
+            None => (
+                format!("{} {}/{}", verb, done.len(), paths.len()),
+                MessageType::Success,
+            ),
+        });
+        self.selected_files.clear();
+        self.preview_file = None;
+        self.show_confirmation_dialog = None;
+        self.space_check = None;
+        self.session_dirty = true;
+        for window in &mut self.result_windows {
+            window.result.remove_paths(&done);
+            window.selected_files.clear();
+        }
 
-            match preview.file_type {
-                FileType::Image | FileType::Gif => {
-                    if let Some(texture) = self.load_image_texture(ctx, &preview.path, width * 2.0)
-                    {
-                        let size = texture.size_vec2();
-                        let scale_w = (width - 10.0) / size.x;
-                        let scale_h = content_height / size.y;
-                        let scale = scale_w.min(scale_h).min(1.0);
-                        ui.image(egui::load::SizedTexture::new(texture.id(), size * scale));
-                    }
-                }
-                FileType::Video => {
-                    ui.vertical_centered(|ui| {
-                        ui.label(egui::RichText::new("🎬").size(64.0));
-                        ui.label("Video File");
-                    });
-                }
-                FileType::Audio => {
-                    ui.vertical_centered(|ui| {
-                        ui.label(egui::RichText::new("🎵").size(64.0));
-                        ui.label("Audio File");
-                    });
-                }
-                FileType::Text => {
-                    if let Some(ref text) = preview.preview_text {
-                        egui::ScrollArea::vertical()
-                            .max_height(content_height)
-                            .show(ui, |ui| {
-                                ui.label(egui::RichText::new(text).monospace().size(10.0));
-                            });
-                    }
-                }
-                FileType::Other => {
-                    ui.vertical_centered(|ui| {
-                        ui.label(egui::RichText::new("📄").size(64.0));
-                        ui.label("File");
-                    });
-                }
+        // A rescan is pointless once the patched result has nothing left to resolve
+        if let Some(ref mut result) = self.scan_result {
+            result.remove_paths(&done);
+            self.folder_breakdown = result.wasted_by_folder();
+            if result.duplicate_groups.is_empty() {
+                self.status_message = Some((
+                    format!(
+                        "{} {}/{} | No more duplicates",
+                        verb,
+                        done.len(),
+                        paths.len()
+                    ),
+                    MessageType::Success,
+                ));
+                return;
             }
+        }
+        // Linked copies still hold the same bytes, so a rescan would list them again
+        if self.has_scan_inputs() && !matches!(dialog, ConfirmationDialog::LinkToKeeper(_)) {
+            self.start_scan();
+        }
+    }
 
-            ui.add_space(8.0);
-
-            ui.horizontal(|ui| {
-                if ui
-                    .button("Open")
-                    .on_hover_text("Open with default app")
-                    .clicked()
-                {
-                    Self::open_file_with_default(&preview.path);
-                }
-                if ui
-                    .button("Folder")
-                    .on_hover_text("Show in Explorer")
-                    .clicked()
-                {
-                    Self::open_folder_and_select_file(&preview.path);
-                }
-            });
+    /// Renamed files are still duplicates, so they keep their place in the results
+    fn finish_rename(&mut self, renames: &[(PathBuf, PathBuf)], results: &[OperationResult]) {
+        let renamed: HashMap<PathBuf, PathBuf> = renames
+            .iter()
+            .zip(results)
+            .filter(|((from, to), r)| from != to && matches!(r, OperationResult::Success(_)))
+            .map(|(pair, _)| pair.clone())
+            .collect();
+        let done = results
+            .iter()
+            .filter(|r| matches!(r, OperationResult::Success(_)))
+            .count();
+        let failed = results.iter().find_map(|r| match r {
+            OperationResult::Error(e) => Some(e),
+            OperationResult::Success(_) => None,
+        });
+        self.status_message = Some(match failed {
+            Some(e) => (
+                format!("Renamed {}/{} | {}", done, renames.len(), e),
+                MessageType::Error,
+            ),
+            None => (
+                format!("Renamed {}/{}", done, renames.len()),
+                MessageType::Success,
+            ),
         });
+        self.show_confirmation_dialog = None;
+        self.preview_file = None;
+        self.session_dirty = true;
+        for (from, to) in &renamed {
+            if let Some(flag) = self.file_flags.remove(from) {
+                self.file_flags.insert(to.clone(), flag);
+            }
+        }
+        if let Some(ref mut result) = self.scan_result {
+            result.rename_paths(&renamed);
+            self.folder_breakdown = result.wasted_by_folder();
+        }
+        for window in &mut self.result_windows {
+            window.result.rename_paths(&renamed);
+        }
     }
 
-    fn render_group(&mut self, ui: &mut egui::Ui, group_idx: usize, group: &DuplicateGroup) {
-        let header = format!(
-            "{} files | {} each | {} wasted",
-            group.files.len(),
-            format_size(group.files.first().map(|f| f.size).unwrap_or(0)),
-            format_size(group.wasted_size)
-        );
-
-        egui::CollapsingHeader::new(header)
-            .default_open(group.files.len() <= 3)
-            .show(ui, |ui| {
-                for (file_idx, file) in group.files.iter().enumerate() {
-                    ui.horizontal(|ui| {
-                        let mut selected = self.selected_files.contains(&(group_idx, file_idx));
-                        if ui.checkbox(&mut selected, "").changed() {
-                            if selected {
-                                self.selected_files.push((group_idx, file_idx));
-                            } else {
-                                self.selected_files
-                                    .retain(|&(g, f)| g != group_idx || f != file_idx);
-                            }
-                        }
-
-                        if file_idx == 0 {
-                            ui.label(
-                                egui::RichText::new("[KEEP]")
-                                    .color(egui::Color32::GREEN)
-                                    .strong(),
-                            );
-                        }
-
-                        let ext = file
-                            .path
-                            .extension()
-                            .map(|e| e.to_string_lossy().to_lowercase())
-                            .unwrap_or_default();
-                        let icon = match Self::get_file_type(&ext) {
-                            FileType::Image => "🖼",
-                            FileType::Gif => "🎞",
-                            FileType::Video => "🎬",
-                            FileType::Audio => "🎵",
-                            FileType::Text => "📄",
-                            FileType::Other => "📁",
-                        };
-                        ui.label(icon);
-                        ui.label(&file.name);
-                        ui.label(format_size(file.size));
+    fn undo_last_batch(&mut self) {
+        let description = match self.file_ops.next_undo() {
+            Some(batch) => batch.description.clone(),
+            None => return,
+        };
 
-                        if ui.small_button("👁").on_hover_text("Preview").clicked() {
-                            self.load_file_preview(file);
-                        }
-                        if ui
-                            .small_button("📂")
-                            .on_hover_text("Open folder & select file")
-                            .clicked()
-                        {
-                            Self::open_folder_and_select_file(&file.path);
-                        }
-                    });
-                }
-            });
-    }
+        let results = self.file_ops.undo_last();
+        let success = results
+            .iter()
+            .filter(|r| matches!(r, OperationResult::Success(_)))
+            .count();
 
-    fn render_confirmation_dialog(&mut self, ctx: &egui::Context) {
-        let dialog = match self.show_confirmation_dialog.clone() {
-            Some(d) => d,
-            None => return,
+        self.status_message = match results.as_slice() {
+            [OperationResult::Error(e)] if success == 0 => Some((e.clone(), MessageType::Error)),
+            _ => Some((
+                format!(
+                    "Undid '{}': restored {}/{}",
+                    description,
+                    success,
+                    results.len()
+                ),
+                MessageType::Success,
+            )),
         };
 
-        egui::Window::new("Confirm")
-            .collapsible(false)
-            .resizable(false)
-            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-            .show(ctx, |ui| match &dialog {
-                ConfirmationDialog::DeleteFiles(paths) => {
-                    ui.label(format!("Delete {} file(s)?", paths.len()));
-                    ui.label(
-                        egui::RichText::new("Cannot be undone!")
-                            .color(egui::Color32::RED)
-                            .small(),
-                    );
-                    ui.horizontal(|ui| {
-                        if ui.button("Delete").clicked() {
-                            let results = self.file_ops.delete_files(paths);
-                            let success = results
-                                .iter()
-                                .filter(|r| matches!(r, OperationResult::Success(_)))
-                                .count();
-                            self.status_message = Some((
-                                format!("Deleted {}/{}", success, paths.len()),
-                                MessageType::Success,
-                            ));
-                            self.selected_files.clear();
-                            self.preview_file = None;
-                            self.show_confirmation_dialog = None;
-                            if !self.selected_folders.is_empty() {
-                                self.start_scan();
-                            }
-                        }
-                        if ui.button("Cancel").clicked() {
-                            self.show_confirmation_dialog = None;
-                        }
-                    });
-                }
-                ConfirmationDialog::MoveFiles(paths, dest) => {
-                    ui.label(format!("Move {} file(s)?", paths.len()));
-                    ui.label(egui::RichText::new(dest.display().to_string()).small());
-                    ui.horizontal(|ui| {
-                        if ui.button("Move").clicked() {
-                            let results = self.file_ops.move_files(paths, dest);
-                            let success = results
-                                .iter()
-                                .filter(|r| matches!(r, OperationResult::Success(_)))
-                                .count();
-                            self.status_message = Some((
-                                format!("Moved {}/{}", success, paths.len()),
-                                MessageType::Success,
-                            ));
-                            self.selected_files.clear();
-                            self.preview_file = None;
-                            self.show_confirmation_dialog = None;
-                            if !self.selected_folders.is_empty() {
-                                self.start_scan();
-                            }
-                        }
-                        if ui.button("Cancel").clicked() {
-                            self.show_confirmation_dialog = None;
-                        }
-                    });
-                }
-            });
+        if success > 0 && self.has_scan_inputs() {
+            self.start_scan();
+        }
     }
 
     fn render_status_bar(&mut self, ui: &mut egui::Ui) {
@@ -789,34 +5059,101 @@ impl FileXSorterApp {
             if let Some((msg, msg_type)) = &self.status_message {
                 let color = match msg_type {
                     MessageType::Info => egui::Color32::GRAY,
-                    MessageType::Success => egui::Color32::from_rgb(100, 255, 100),
-                    MessageType::Error => egui::Color32::RED,
+                    MessageType::Success => success_color(ui.visuals()),
+                    MessageType::Error => ui.visuals().error_fg_color,
                 };
                 ui.label(egui::RichText::new(msg).color(color));
             }
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                if !self.selected_files.is_empty() {
-                    let size: u64 = self
-                        .get_selected_paths()
-                        .iter()
-                        .filter_map(|p| fs::metadata(p).ok())
-                        .map(|m| m.len())
-                        .sum();
-                    ui.label(format!(
-                        "Selected: {} ({})",
-                        self.selected_files.len(),
-                        format_size(size)
-                    ));
+                let Some(result) = self
+                    .scan_result
+                    .as_ref()
+                    .filter(|_| !self.selected_files.is_empty())
+                else {
+                    self.selection_space = None;
+                    return;
+                };
+                let files: Vec<(PathBuf, u64)> = Self::selected_entries(
+                    result,
+                    &self.selected_files,
+                    &self.scan_config.protected_folders,
+                )
+                .map(|file| (file.path.clone(), file.size))
+                .collect();
+                if self.selection_space.as_ref().map(|s| &s.files) != Some(&files) {
+                    self.selection_space = Some(SelectionSpace::measure(files));
+                }
+                let Some(selection) = &self.selection_space else {
+                    return;
+                };
+                // Right to left, so the drives go in first to end up after the count
+                for freeing in selection.volumes.iter().rev() {
+                    let label = ui.label(
+                        egui::RichText::new(format!(
+                            "{} {} free → {}",
+                            freeing.mount_point.display(),
+                            format_size(freeing.space.available),
+                            format_size(freeing.space.available_after_freeing(freeing.freed))
+                        ))
+                        .small(),
+                    );
+                    let hint = if self.file_ops.use_recycle_bin {
+                        "Free space after deleting the selection and emptying the Recycle Bin"
+                    } else {
+                        "Free space after deleting the selection"
+                    };
+                    label.on_hover_text(hint);
                 }
+                ui.label(format!(
+                    "Selected: {} ({})",
+                    self.selected_files.len(),
+                    format_size(selection.size)
+                ));
             });
         });
     }
 }
 
 impl eframe::App for FileXSorterApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SCAN_PROFILES_KEY, &self.scan_profiles);
+        eframe::set_value(storage, AUTO_EXPAND_KEY, &self.auto_expand_groups);
+        eframe::set_value(storage, REMEMBER_SCAN_KEY, &self.remember_last_scan);
+        eframe::set_value(storage, HASH_CACHE_KEY, &self.use_hash_cache);
+        eframe::set_value(
+            storage,
+            OPERATION_LOG_KEY,
+            &self.file_ops.log_file.is_some(),
+        );
+        eframe::set_value(storage, RECYCLE_BIN_KEY, &self.file_ops.use_recycle_bin);
+        eframe::set_value(storage, SCAN_CONFIG_KEY, &self.scan_config);
+        eframe::set_value(storage, PREVIEW_PANEL_KEY, &self.show_preview_panel);
+        eframe::set_value(storage, PREVIEW_WIDTH_KEY, &self.preview_panel_width);
+        eframe::set_value(storage, THEME_KEY, &self.theme);
+        let folders: Vec<VolumePath> = self
+            .selected_folders
+            .iter()
+            .map(|f| VolumePath::from_path(f))
+            .collect();
+        eframe::set_value(storage, FOLDERS_KEY, &folders);
+        if self.remember_last_scan {
+            eframe::set_value(storage, FILE_FLAGS_KEY, &self.file_flags);
+        }
+        self.save_last_scan();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_shortcuts(ctx);
         self.check_scan_complete();
+        if let Some(e) = self.file_ops.take_log_error() {
+            self.status_message = Some((e, MessageType::Error));
+        }
+        self.revalidate_preview(ctx);
+        self.handle_dropped_files(ctx);
+        self.poll_reverify_jobs(ctx);
+        self.poll_watcher(ctx);
+        self.stop_audio_if_deselected();
         if self.is_scanning {
             ctx.request_repaint();
         }
@@ -828,14 +5165,15 @@ impl eframe::App for FileXSorterApp {
             });
 
         if self.show_preview_panel {
-            egui::SidePanel::right("preview_panel")
+            let panel = egui::SidePanel::right("preview_panel")
                 .resizable(true)
                 .default_width(self.preview_panel_width)
-                .width_range(150.0..=400.0)
+                .width_range(150.0..=(ctx.screen_rect().width() * 0.6).max(150.0))
                 .show(ctx, |ui| {
-                    self.preview_panel_width = ui.available_width();
                     self.render_preview_panel(ui, ctx);
                 });
+            // The whole panel, not its content area, so a restored width doesn't lose the margins
+            self.preview_panel_width = panel.response.rect.width();
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -844,6 +5182,108 @@ impl eframe::App for FileXSorterApp {
             self.render_results_only(ui, ctx);
         });
 
+        self.render_result_windows(ctx);
         self.render_confirmation_dialog(ctx);
+        self.render_drop_hint(ctx);
+    }
+}
+
+/// Carry out a confirmed dialog's operations, returning the past-tense verb,
+/// the affected paths and one result per path
+fn run_confirmed(
+    ops: &mut FileOperations,
+    dialog: &ConfirmationDialog,
+) -> (&'static str, Vec<PathBuf>, Vec<OperationResult>) {
+    match dialog {
+        ConfirmationDialog::DeleteFiles(paths) => {
+            ("Deleted", paths.clone(), ops.delete_files(paths))
+        }
+        ConfirmationDialog::DeleteFolders(folders) => (
+            "Deleted",
+            folders.iter().map(|(folder, _)| folder.clone()).collect(),
+            ops.delete_folders(folders),
+        ),
+        ConfirmationDialog::MoveFiles(moves, dest) => (
+            "Moved",
+            moves.iter().map(|(p, _)| p.clone()).collect(),
+            ops.move_files_into(moves, dest),
+        ),
+        ConfirmationDialog::RenameFiles(renames) => (
+            "Renamed",
+            renames.iter().map(|(p, _)| p.clone()).collect(),
+            ops.rename_files(renames),
+        ),
+        ConfirmationDialog::LinkToKeeper(links) => {
+            let mut by_keeper: Vec<(&PathBuf, Vec<PathBuf>)> = Vec::new();
+            for (duplicate, keeper) in links {
+                match by_keeper.iter_mut().find(|(k, _)| *k == keeper) {
+                    Some((_, duplicates)) => duplicates.push(duplicate.clone()),
+                    None => by_keeper.push((keeper, vec![duplicate.clone()])),
+                }
+            }
+            let mut paths = Vec::new();
+            let mut results = Vec::new();
+            for (keeper, duplicates) in by_keeper {
+                results.extend(ops.link_duplicates(keeper, &duplicates));
+                paths.extend(duplicates);
+            }
+            ("Linked", paths, results)
+        }
+    }
+}
+
+/// Whether `path` is an empty file that marks its folder, such as `.gitkeep`
+fn is_marker_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| MARKER_FILE_NAMES.contains(&name))
+}
+
+/// Green for keepers and successes, dark enough to read on a light background
+fn success_color(visuals: &egui::Visuals) -> egui::Color32 {
+    if visuals.dark_mode {
+        egui::Color32::from_rgb(100, 255, 100)
+    } else {
+        egui::Color32::from_rgb(0, 128, 0)
+    }
+}
+
+/// Blue marking files that appeared after the scan
+fn highlight_color(visuals: &egui::Visuals) -> egui::Color32 {
+    if visuals.dark_mode {
+        egui::Color32::LIGHT_BLUE
+    } else {
+        egui::Color32::from_rgb(0, 90, 200)
+    }
+}
+
+/// `path` cut down to its last `max_chars` characters, so the file name always shows
+fn shorten_path(path: &Path, max_chars: usize) -> String {
+    let text = path.display().to_string();
+    let len = text.chars().count();
+    if len <= max_chars {
+        return text;
+    }
+    let tail: String = text
+        .chars()
+        .skip(len - max_chars.saturating_sub(3))
+        .collect();
+    format!("...{}", tail)
+}
+
+/// One-line overview of a scan for the results header
+fn result_summary(result: &ScanResult) -> String {
+    let mut summary = format!(
+        "Scanned {} files ({}) | {} groups | {} duplicates | {} wasted",
+        result.total_files,
+        format_size(result.total_size),
+        result.duplicate_groups.len(),
+        result.total_duplicates,
+        format_size(result.wasted_space)
+    );
+    if let Some(timing) = result.timing_summary() {
+        summary.push_str(" | ");
+        summary.push_str(&timing);
     }
+    summary
 }
@@ -0,0 +1,134 @@
+//! Audio module - Playing audio duplicates from the preview panel
+//!
+//! Playback decodes in-process via `rodio`, which is behind the `audio`
+//! feature (on by default) since it needs the system audio libraries, ALSA
+//! on Linux. Without the feature nothing is playable.
+
+use std::path::Path;
+
+use crate::scanner::normalized_extension;
+
+/// Extensions of the audio formats `rodio` can decode
+const PLAYABLE_EXTENSIONS: [&str; 6] = ["mp3", "wav", "flac", "ogg", "m4a", "aac"];
+
+/// Whether `path` is an audio format that [`AudioPlayer`] can play
+pub fn can_play_audio(path: &Path) -> bool {
+    cfg!(feature = "audio") && PLAYABLE_EXTENSIONS.contains(&normalized_extension(path).as_str())
+}
+
+#[cfg(feature = "audio")]
+pub use rodio_player::AudioPlayer;
+#[cfg(not(feature = "audio"))]
+pub use silent_player::AudioPlayer;
+
+#[cfg(feature = "audio")]
+mod rodio_player {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+
+    /// Plays one audio file at a time on the default output device
+    #[derive(Default)]
+    pub struct AudioPlayer {
+        /// Opened on first use and kept, since the sink stops when it's dropped
+        output: Option<(OutputStream, OutputStreamHandle)>,
+        sink: Option<Sink>,
+        path: Option<PathBuf>,
+        duration: Option<Duration>,
+    }
+
+    impl AudioPlayer {
+        /// Stop whatever is playing and start `path` from the beginning
+        pub fn play(&mut self, path: &Path) -> Result<(), String> {
+            self.stop();
+            let file = File::open(path).map_err(|e| e.to_string())?;
+            let source = Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+            let duration = source.total_duration();
+
+            if self.output.is_none() {
+                self.output = Some(OutputStream::try_default().map_err(|e| e.to_string())?);
+            }
+            let (_, handle) = self.output.as_ref().expect("output stream was just opened");
+            let sink = Sink::try_new(handle).map_err(|e| e.to_string())?;
+            sink.append(source);
+
+            self.sink = Some(sink);
+            self.path = Some(path.to_path_buf());
+            self.duration = duration;
+            Ok(())
+        }
+
+        pub fn stop(&mut self) {
+            if let Some(sink) = self.sink.take() {
+                sink.stop();
+            }
+            self.path = None;
+            self.duration = None;
+        }
+
+        /// The file currently playing, or `None` once it has finished
+        pub fn playing(&self) -> Option<&Path> {
+            match &self.sink {
+                Some(sink) if !sink.empty() => self.path.as_deref(),
+                _ => None,
+            }
+        }
+
+        /// Elapsed playback time of the current file
+        pub fn position(&self) -> Duration {
+            self.sink.as_ref().map_or(Duration::ZERO, Sink::get_pos)
+        }
+
+        /// Length of the current file, when the decoder knows it up front
+        pub fn duration(&self) -> Option<Duration> {
+            self.duration
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+mod silent_player {
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Stand-in for builds without the `audio` feature; never plays anything
+    #[derive(Default)]
+    pub struct AudioPlayer {}
+
+    impl AudioPlayer {
+        pub fn play(&mut self, _path: &Path) -> Result<(), String> {
+            Err("this build has no audio playback".to_string())
+        }
+
+        pub fn stop(&mut self) {}
+
+        pub fn playing(&self) -> Option<&Path> {
+            None
+        }
+
+        pub fn position(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        pub fn duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_play_audio() {
+        assert_eq!(
+            can_play_audio(Path::new("music/Song.MP3")),
+            cfg!(feature = "audio")
+        );
+        assert!(!can_play_audio(Path::new("music/song.wma")));
+    }
+}
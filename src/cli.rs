@@ -0,0 +1,385 @@
+//! CLI module - Headless scanning for scripts and automation
+//!
+//! `FileXSorter --scan <folder>...` runs a scan without the GUI, prints a
+//! one-line summary to stdout and exits with one of the `EXIT_*` codes.
+//! The result can also be saved as JSON, and duplicates can be sent to the
+//! Recycle Bin, or only listed with `--dry-run`.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use filexsorter::export;
+use filexsorter::file_ops::{FileOperations, OperationResult};
+use filexsorter::scanner::{GroupKind, ScanResult, Scanner, ScannerConfig};
+
+/// No duplicates were found
+pub const EXIT_NO_DUPLICATES: u8 = 0;
+/// At least one duplicate group was found
+pub const EXIT_DUPLICATES_FOUND: u8 = 1;
+/// Bad arguments or a folder that couldn't be scanned
+pub const EXIT_SCAN_ERROR: u8 = 2;
+/// `--delete-duplicates` failed to remove at least one copy
+pub const EXIT_DELETE_FAILED: u8 = 3;
+/// No CLI mode was requested and the GUI failed to start
+pub const EXIT_GUI_FAILED: u8 = 4;
+
+const USAGE: &str = "Usage: FileXSorter --scan <folder>... [--recursive | --no-recursive] [--min-size <bytes>] [--max-size <bytes>] [--json <file>] [--delete-duplicates [--dry-run]]";
+
+/// Options for a headless scan
+#[derive(Debug)]
+pub struct CliArgs {
+    pub folders: Vec<PathBuf>,
+    pub config: ScannerConfig,
+    /// Save the full result here with `export::save_results_json`
+    pub json: Option<PathBuf>,
+    /// Recycle every copy but the keeper in each exact duplicate group
+    pub delete_duplicates: bool,
+    /// List what `delete_duplicates` would remove without touching anything
+    pub dry_run: bool,
+}
+
+/// What the command line asked for
+#[derive(Debug)]
+pub enum Mode {
+    /// No arguments, so start the GUI
+    Gui,
+    /// `-h` or `--help`
+    Help,
+    Scan(Box<CliArgs>),
+}
+
+/// Parse command-line arguments (without the program name).
+///
+/// Folder and file names needn't be valid Unicode.
+pub fn parse_args(args: &[OsString]) -> Result<Mode, String> {
+    if args.is_empty() {
+        return Ok(Mode::Gui);
+    }
+
+    let mut scan = false;
+    let mut folders = Vec::new();
+    let mut config = ScannerConfig::default();
+    let mut json = None;
+    let mut delete_duplicates = false;
+    let mut dry_run = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.to_str() {
+            Some("--scan") => scan = true,
+            Some("--recursive") => config.recursive = true,
+            Some("--no-recursive") => config.recursive = false,
+            Some("--json") => {
+                let value = iter.next().ok_or("--json needs a file")?;
+                json = Some(PathBuf::from(value));
+            }
+            Some("--delete-duplicates") => delete_duplicates = true,
+            Some("--dry-run") => dry_run = true,
+            Some("--min-size") => {
+                let value = iter.next().ok_or("--min-size needs a value")?;
+                config.min_size = parse_size(value)
+                    .ok_or_else(|| format!("Invalid --min-size: {}", value.to_string_lossy()))?;
+            }
+            Some("--max-size") => {
+                let value = iter.next().ok_or("--max-size needs a value")?;
+                config.max_size = parse_size(value)
+                    .ok_or_else(|| format!("Invalid --max-size: {}", value.to_string_lossy()))?;
+            }
+            Some("-h" | "--help") => return Ok(Mode::Help),
+            Some(flag) if flag.starts_with('-') => {
+                return Err(format!("Unknown option: {}\n{}", flag, USAGE))
+            }
+            _ => folders.push(PathBuf::from(arg)),
+        }
+    }
+
+    if !scan {
+        return Err(USAGE.to_string());
+    }
+    if folders.is_empty() {
+        return Err(format!("No folders given\n{}", USAGE));
+    }
+    if config.min_size > config.max_size {
+        return Err("--min-size must not exceed --max-size".to_string());
+    }
+    if dry_run && !delete_duplicates {
+        return Err("--dry-run only applies to --delete-duplicates".to_string());
+    }
+    Ok(Mode::Scan(Box::new(CliArgs {
+        folders,
+        config,
+        json,
+        delete_duplicates,
+        dry_run,
+    })))
+}
+
+fn parse_size(value: &OsString) -> Option<u64> {
+    value.to_str()?.parse().ok()
+}
+
+/// Run a headless scan and report the outcome
+pub fn run(args: &CliArgs) -> ExitCode {
+    attach_console();
+
+    if let Some(missing) = args.folders.iter().find(|f| !f.is_dir()) {
+        eprintln!("Not a folder: {}", missing.display());
+        return ExitCode::from(EXIT_SCAN_ERROR);
+    }
+
+    let scanner = Scanner::new(args.config.clone());
+    let result = scanner.scan_directories(&args.folders);
+    for error in &result.errors {
+        eprintln!("{}", error);
+    }
+    for path in &result.locked_files {
+        eprintln!("In use by another process: {}", path.display());
+    }
+    for root in &result.slow_roots {
+        let action = if root.skipped { "Skipped" } else { "Slow" };
+        eprintln!("{} {}: {}", action, root.kind.label(), root.path.display());
+    }
+    println!("{}", summary_line(&result));
+
+    if let Some(path) = &args.json {
+        if let Err(e) = export::save_results_json(&result, path) {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_SCAN_ERROR);
+        }
+    }
+    if args.delete_duplicates && delete_duplicates(&result, args.dry_run) > 0 {
+        return ExitCode::from(EXIT_DELETE_FAILED);
+    }
+    ExitCode::from(exit_code(&result))
+}
+
+/// Recycle the non-keeper copies, or just print them on a dry run. Copies the
+/// Recycle Bin refuses are left in place, never erased.
+///
+/// Returns how many copies failed.
+fn delete_duplicates(result: &ScanResult, dry_run: bool) -> usize {
+    let mut ops = FileOperations::new();
+    ops.use_recycle_bin = true;
+    ops.dry_run = dry_run;
+    let paths = deletion_targets(result);
+    let results = ops.delete_files(&paths);
+
+    let mut deleted = 0;
+    for (path, outcome) in paths.iter().zip(&results) {
+        match outcome {
+            OperationResult::Success(message) => {
+                deleted += 1;
+                if dry_run {
+                    println!("{}", message);
+                }
+            }
+            OperationResult::Error(e) => eprintln!("{}: {}", path.display(), e),
+        }
+    }
+    let failed = paths.len() - deleted;
+    let key = if dry_run { "would_delete" } else { "deleted" };
+    println!("{}={} failed={}", key, deleted, failed);
+    failed
+}
+
+/// Every copy but the keeper, from groups whose files are byte-identical
+pub fn deletion_targets(result: &ScanResult) -> Vec<PathBuf> {
+    result
+        .duplicate_groups
+        .iter()
+        .filter(|g| g.kind == GroupKind::Exact)
+        .flat_map(|g| g.files.iter().skip(1).map(|f| f.path.clone()))
+        .collect()
+}
+
+/// Print the usage on stdout, as asked for with `--help`
+pub fn print_help() -> ExitCode {
+    attach_console();
+    println!("{}", USAGE);
+    ExitCode::SUCCESS
+}
+
+/// Report a usage error on the console
+pub fn report_error(message: &str) -> ExitCode {
+    attach_console();
+    eprintln!("{}", message);
+    ExitCode::from(EXIT_SCAN_ERROR)
+}
+
+/// Stable `key=value` summary; sizes are in bytes so scripts can parse them
+pub fn summary_line(result: &ScanResult) -> String {
+    format!(
+        "groups={} duplicates={} wasted_bytes={} scanned={} errors={}",
+        result.duplicate_groups.len(),
+        result.total_duplicates,
+        result.wasted_space,
+        result.total_files,
+        result.errors.len()
+    )
+}
+
+pub fn exit_code(result: &ScanResult) -> u8 {
+    if result.duplicate_groups.is_empty() {
+        EXIT_NO_DUPLICATES
+    } else {
+        EXIT_DUPLICATES_FOUND
+    }
+}
+
+/// Release builds use the GUI subsystem, so borrow the launching terminal's console
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: plain Win32 call; failure just means there's no parent console
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filexsorter::scanner::{DuplicateGroup, FileEntry, MatchConfidence};
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    fn scan_args(list: &[OsString]) -> CliArgs {
+        match parse_args(list) {
+            Ok(Mode::Scan(parsed)) => *parsed,
+            other => panic!("expected a scan, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert!(matches!(parse_args(&[]), Ok(Mode::Gui)));
+        assert!(matches!(parse_args(&args(&["--help"])), Ok(Mode::Help)));
+        assert!(matches!(
+            parse_args(&args(&["--scan", "a", "-h"])),
+            Ok(Mode::Help)
+        ));
+
+        let parsed = scan_args(&args(&["--scan", "a", "b", "--min-size", "10"]));
+        assert_eq!(parsed.folders, [PathBuf::from("a"), PathBuf::from("b")]);
+        assert_eq!(parsed.config.min_size, 10);
+        assert!(parsed.json.is_none() && !parsed.delete_duplicates);
+
+        let parsed = scan_args(&args(&[
+            "--scan",
+            "a",
+            "--json",
+            "out.json",
+            "--delete-duplicates",
+            "--dry-run",
+        ]));
+        assert_eq!(parsed.json, Some(PathBuf::from("out.json")));
+        assert!(parsed.delete_duplicates && parsed.dry_run);
+        assert!(parse_args(&args(&["--scan", "a", "--dry-run"])).is_err());
+        assert!(parse_args(&args(&["--scan", "a", "--json"])).is_err());
+
+        assert!(parse_args(&args(&["--scan"])).is_err());
+        assert!(parse_args(&args(&["--bogus", "a"])).is_err());
+        assert!(parse_args(&args(&[
+            "--scan",
+            "a",
+            "--min-size",
+            "9",
+            "--max-size",
+            "5"
+        ]))
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_args_keeps_non_unicode_folders() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let folder = OsString::from_vec(b"caf\xe9".to_vec());
+        let parsed = scan_args(&[OsString::from("--scan"), folder.clone()]);
+        assert_eq!(parsed.folders, [PathBuf::from(folder)]);
+    }
+
+    #[test]
+    fn test_exit_code_and_summary() {
+        let mut result = ScanResult {
+            total_files: 4,
+            ..Default::default()
+        };
+        assert_eq!(exit_code(&result), EXIT_NO_DUPLICATES);
+        assert_eq!(
+            summary_line(&result),
+            "groups=0 duplicates=0 wasted_bytes=0 scanned=4 errors=0"
+        );
+
+        result.duplicate_groups.push(DuplicateGroup {
+            hash: "x".into(),
+            files: Vec::new(),
+            total_size: 1024,
+            wasted_size: 512,
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        });
+        result.total_duplicates = 1;
+        result.wasted_space = 512;
+        assert_eq!(exit_code(&result), EXIT_DUPLICATES_FOUND);
+        assert_eq!(
+            summary_line(&result),
+            "groups=1 duplicates=1 wasted_bytes=512 scanned=4 errors=0"
+        );
+    }
+
+    #[test]
+    fn test_deletion_targets_skip_keepers_and_partial_copies() {
+        let group = |kind, names: &[&str]| DuplicateGroup {
+            hash: names[0].into(),
+            files: names
+                .iter()
+                .map(|n| FileEntry::new(PathBuf::from(n), n.to_string(), 1))
+                .collect(),
+            total_size: names.len() as u64,
+            wasted_size: names.len() as u64 - 1,
+            kind,
+            confidence: MatchConfidence::Exact,
+        };
+        let result = ScanResult {
+            duplicate_groups: vec![
+                group(GroupKind::Exact, &["keep", "dup1", "dup2"]),
+                group(GroupKind::PartialCopy, &["full", "part"]),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            deletion_targets(&result),
+            [PathBuf::from("dup1"), PathBuf::from("dup2")]
+        );
+    }
+
+    #[test]
+    fn test_failed_deletes_are_counted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (keeper, copy) = (tmp.path().join("keep"), tmp.path().join("gone"));
+        std::fs::write(&keeper, b"same").unwrap();
+        let result = ScanResult {
+            duplicate_groups: vec![DuplicateGroup::new(
+                "h".into(),
+                vec![
+                    FileEntry::new(keeper.clone(), "keep".into(), 4),
+                    FileEntry::new(copy, "gone".into(), 4),
+                ],
+                GroupKind::Exact,
+                MatchConfidence::Exact,
+            )],
+            ..Default::default()
+        };
+        // The copy vanished after the scan, so it can't be removed
+        assert_eq!(delete_duplicates(&result, true), 1);
+        assert_eq!(delete_duplicates(&result, false), 1);
+        assert!(keeper.exists());
+    }
+}
@@ -0,0 +1,217 @@
+//! Export module - Writing scan results out and reading them back as a baseline
+//!
+//! A baseline is an earlier export: groups whose hash appears in it were
+//! already reviewed, so a fresh scan can single out the groups that are new.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::scanner::{normalized_extension, ScanResult};
+use crate::session::SavedScan;
+
+/// How a scan compares with a baseline export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BaselineDiff {
+    /// Groups not present in the baseline
+    pub new: usize,
+    /// Groups already in the baseline
+    pub seen: usize,
+}
+
+/// Write the full result as JSON so it can be loaded again without rescanning
+pub fn save_results_json(result: &ScanResult, path: &Path) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(result).map_err(|e| format!("Failed to encode: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Load a result written by `save_results_json`, or a remembered last scan
+pub fn load_results_json(path: &Path) -> Result<ScanResult, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    match serde_json::from_str::<SavedScan>(&text) {
+        Ok(saved) => Ok(saved.result),
+        Err(_) => serde_json::from_str::<ScanResult>(&text)
+            .map_err(|e| format!("Not a saved scan: {}", e)),
+    }
+}
+
+/// Write one row per grouped file for spreadsheets
+pub fn export_csv(result: &ScanResult, path: &Path) -> Result<(), String> {
+    let mut csv = String::from("group,hash,name,path,size,keeper\r\n");
+    for (group_idx, group) in result.duplicate_groups.iter().enumerate() {
+        for (file_idx, file) in group.files.iter().enumerate() {
+            let row = [
+                (group_idx + 1).to_string(),
+                csv_field(&group.hash),
+                csv_field(&file.name),
+                csv_field(&file.path.to_string_lossy()),
+                file.size.to_string(),
+                (file_idx == 0).to_string(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push_str("\r\n");
+        }
+    }
+    fs::write(path, csv).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Quote a field if it contains a delimiter, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Read the group hashes from a previous CSV or JSON export
+pub fn load_baseline(path: &Path) -> Result<HashSet<String>, String> {
+    if normalized_extension(path) == "csv" {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        return csv_hashes(&text);
+    }
+
+    Ok(load_results_json(path)?
+        .duplicate_groups
+        .into_iter()
+        .map(|g| g.hash)
+        .collect())
+}
+
+/// Count groups in `result` that are new relative to `baseline`
+pub fn diff_against_baseline(result: &ScanResult, baseline: &HashSet<String>) -> BaselineDiff {
+    let seen = result
+        .duplicate_groups
+        .iter()
+        .filter(|g| baseline.contains(&g.hash))
+        .count();
+    BaselineDiff {
+        new: result.duplicate_groups.len() - seen,
+        seen,
+    }
+}
+
+/// Collect the `hash` column of a CSV export
+fn csv_hashes(text: &str) -> Result<HashSet<String>, String> {
+    let mut lines = text.lines();
+    let header = lines.next().ok_or("Empty CSV file")?;
+    let column = split_csv_line(header)
+        .iter()
+        .position(|h| h.trim().eq_ignore_ascii_case("hash"))
+        .ok_or("CSV has no 'hash' column")?;
+
+    Ok(lines
+        .filter_map(|line| split_csv_line(line).into_iter().nth(column))
+        .filter(|hash| !hash.is_empty())
+        .collect())
+}
+
+/// Split one CSV record, honoring quoted fields and doubled quotes (RFC 4180)
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{
+        DuplicateGroup, FileEntry, GroupKind, MatchConfidence, Scanner, ScannerConfig,
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_baseline_round_trip_and_diff() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a1"), b"first").unwrap();
+        fs::write(dir.join("a2"), b"first").unwrap();
+        let scanner = Scanner::new(ScannerConfig::default());
+        let before = scanner.scan_directory(dir);
+
+        let export = dir.join("export.json");
+        save_results_json(&before, &export).unwrap();
+        let loaded = load_results_json(&export).unwrap();
+        assert_eq!(loaded.duplicate_groups.len(), 1);
+        assert_eq!(loaded.hash_algorithm, before.hash_algorithm);
+        fs::write(dir.join("b1"), b"second").unwrap();
+        fs::write(dir.join("b2"), b"second").unwrap();
+        let after = scanner.scan_directory(dir);
+
+        let baseline = load_baseline(&export).unwrap();
+        assert_eq!(
+            diff_against_baseline(&after, &baseline),
+            BaselineDiff { new: 1, seen: 1 }
+        );
+    }
+
+    #[test]
+    fn test_csv_export_escapes_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+
+        // Windows forbids quotes in file names, so these files exist only in memory
+        let files = ["a, \"quoted\".txt", "b.txt"]
+            .map(|name| FileEntry::new(PathBuf::from("data").join(name), name.into(), 4));
+        let result = ScanResult {
+            duplicate_groups: vec![DuplicateGroup::new(
+                "abc".into(),
+                files.to_vec(),
+                GroupKind::Exact,
+                MatchConfidence::Exact,
+            )],
+            ..Default::default()
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        let export = tmp.path().join("export.csv");
+        export_csv(&result, &export).unwrap();
+        let text = fs::read_to_string(&export).unwrap();
+        let rows: Vec<Vec<String>> = text.lines().map(split_csv_line).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], ["group", "hash", "name", "path", "size", "keeper"]);
+        assert_eq!(rows[1][2], "a, \"quoted\".txt");
+        assert_eq!(rows[1][3], files[0].path.to_string_lossy());
+        assert_eq!(rows[1][5], "true");
+        assert_eq!(rows[2][5], "false");
+        assert_eq!(
+            load_baseline(&export).unwrap(),
+            HashSet::from([result.duplicate_groups[0].hash.clone()])
+        );
+    }
+
+    #[test]
+    fn test_csv_baseline_handles_quoted_fields() {
+        let csv = "group,hash,path\n\
+                   1,abc,\"C:\\a, \"\"quoted\"\".txt\"\n\
+                   2,def,plain.txt\n";
+        assert_eq!(
+            split_csv_line("1,abc,\"C:\\a, \"\"quoted\"\".txt\""),
+            ["1", "abc", "C:\\a, \"quoted\".txt"]
+        );
+        assert_eq!(
+            csv_hashes(csv).unwrap(),
+            HashSet::from(["abc".to_string(), "def".to_string()])
+        );
+        assert!(csv_hashes("group,path\n1,x\n").is_err());
+    }
+}
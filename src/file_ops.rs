@@ -1,12 +1,21 @@
-//! File operations module - Delete and Move functionality
+//! File operations module - Delete, Move, Link and Rename functionality
 //!
 //! This module handles file deletion and moving operations
-//! with proper error handling and logging.
+//! with proper error handling and logging. Logs can also be appended
+//! to a JSON Lines file as a permanent record.
 
+use std::collections::HashSet;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+
+use serde::Serialize;
 use uuid::Uuid;
 
+use crate::paths::long_path;
+use crate::scanner::split_extension;
+use crate::volume::same_volume;
+
 /// Result of a file operation
 #[derive(Debug, Clone)]
 pub enum OperationResult {
@@ -15,7 +24,7 @@ pub enum OperationResult {
 }
 
 /// Log entry for file operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct OperationLog {
     pub operation: String,
     pub source: PathBuf,
@@ -24,9 +33,286 @@ pub struct OperationLog {
     pub message: String,
 }
 
+/// How moved duplicates are laid out under the destination folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveLayout {
+    /// Everything goes straight into the destination
+    #[default]
+    Flat,
+    /// One subfolder per duplicate group, for reviewing groups side by side
+    PerGroup,
+    /// Recreate each file's folders below the sources' common folder
+    PreserveStructure,
+}
+
+impl MoveLayout {
+    pub const ALL: [MoveLayout; 3] = [
+        MoveLayout::Flat,
+        MoveLayout::PerGroup,
+        MoveLayout::PreserveStructure,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MoveLayout::Flat => "Into one folder",
+            MoveLayout::PerGroup => "Folder per group",
+            MoveLayout::PreserveStructure => "Keep folder structure",
+        }
+    }
+
+    /// Folder that `source`, from group `group_idx`, should be moved into.
+    ///
+    /// `base` is the common folder of everything being moved, see
+    /// [`common_base`]; without one, `PreserveStructure` moves flat.
+    pub fn target_dir(
+        self,
+        dest: &Path,
+        source: &Path,
+        base: Option<&Path>,
+        group_idx: usize,
+        hash: &str,
+    ) -> PathBuf {
+        match self {
+            MoveLayout::Flat => dest.to_path_buf(),
+            MoveLayout::PreserveStructure => {
+                let relative = source
+                    .parent()
+                    .zip(base)
+                    .and_then(|(parent, base)| parent.strip_prefix(base).ok());
+                match relative {
+                    Some(relative) => dest.join(relative),
+                    None => dest.to_path_buf(),
+                }
+            }
+            MoveLayout::PerGroup => {
+                // Drop kind prefixes such as `partial:`
+                let digest = hash.rsplit(':').next().unwrap_or(hash);
+                let prefix: String = digest
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric())
+                    .take(8)
+                    .collect();
+                dest.join(format!("group_{:04}_{}", group_idx + 1, prefix))
+            }
+        }
+    }
+}
+
+/// Deepest folder holding every path, or `None` when they only share a
+/// drive or filesystem root, so there is no structure worth keeping
+pub fn common_base<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut base: Option<PathBuf> = None;
+    for path in paths {
+        let parent = path.parent()?;
+        base = Some(match base {
+            None => parent.to_path_buf(),
+            Some(base) => base
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    base.filter(|b| {
+        b.components()
+            .any(|c| matches!(c, std::path::Component::Normal(_)))
+    })
+}
+
+/// Characters Windows doesn't allow in file names
+const ILLEGAL_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows reserves, with or without an extension
+const RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RenamePart {
+    Text(String),
+    /// `{name}`, the current name without its extension
+    Name,
+    /// `{index}`, counting the renamed files from 1
+    Index,
+    /// `{n}`, the position within the duplicate group, 1 being the keeper
+    Position,
+    /// `{hash8}`, the first 8 characters of the group hash
+    Hash8,
+}
+
+/// A batch rename pattern such as `{name}_{index}` or `{hash8}_{n}`.
+/// The original extension is always kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePattern {
+    parts: Vec<RenamePart>,
+}
+
+/// A file to rename, with the group details a pattern can refer to
+#[derive(Debug, Clone)]
+pub struct RenameItem {
+    pub path: PathBuf,
+    pub hash: String,
+    /// Position within the group, 0 being the keeper
+    pub position: usize,
+}
+
+impl RenamePattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+        while !rest.is_empty() {
+            let (text, tail) = rest.split_at(rest.find('{').unwrap_or(rest.len()));
+            if let Some(c) = text.chars().find(|c| ILLEGAL_NAME_CHARS.contains(c)) {
+                return Err(format!("File names can't contain '{}'", c));
+            }
+            if !text.is_empty() {
+                parts.push(RenamePart::Text(text.to_string()));
+            }
+            if tail.is_empty() {
+                break;
+            }
+            let end = tail.find('}').ok_or("Unclosed '{' in pattern")?;
+            parts.push(match &tail[1..end] {
+                "name" => RenamePart::Name,
+                "index" => RenamePart::Index,
+                "n" => RenamePart::Position,
+                "hash8" => RenamePart::Hash8,
+                other => return Err(format!("Unknown placeholder {{{}}}", other)),
+            });
+            rest = &tail[end + 1..];
+        }
+        if parts.is_empty() {
+            return Err("Pattern is empty".to_string());
+        }
+        Ok(Self { parts })
+    }
+
+    /// New file name for `item`, the `index`th file of the batch counting from 0
+    pub fn file_name(&self, item: &RenameItem, index: usize) -> String {
+        let name = item
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (stem, extension) = split_extension(&name);
+        let mut new_name = String::new();
+        for part in &self.parts {
+            match part {
+                RenamePart::Text(text) => new_name.push_str(text),
+                RenamePart::Name => new_name.push_str(stem),
+                RenamePart::Index => new_name.push_str(&(index + 1).to_string()),
+                RenamePart::Position => new_name.push_str(&(item.position + 1).to_string()),
+                RenamePart::Hash8 => {
+                    let digest = item.hash.rsplit(':').next().unwrap_or(&item.hash);
+                    new_name.extend(digest.chars().take(8));
+                }
+            }
+        }
+        if !extension.is_empty() {
+            new_name.push('.');
+            new_name.push_str(extension);
+        }
+        new_name
+    }
+}
+
+/// Check that `name` is a legal file name on Windows
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("File name is empty".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| ILLEGAL_NAME_CHARS.contains(c) || c.is_control())
+    {
+        return Err(format!("{:?} contains '{}'", name, c.escape_default()));
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(format!("{:?} can't end with a dot or space", name));
+    }
+    let device = name.split('.').next().unwrap_or(name).trim_end();
+    let reserved = RESERVED_NAMES
+        .iter()
+        .any(|r| device.eq_ignore_ascii_case(r))
+        || ["COM", "LPT"].iter().any(|prefix| {
+            // `get` rather than slicing: the name may be multi-byte
+            device.len() == 4
+                && device
+                    .get(..3)
+                    .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+                && matches!(device.as_bytes()[3], b'1'..=b'9')
+        });
+    if reserved {
+        return Err(format!("{:?} is reserved by Windows", name));
+    }
+    if name.len() > 255 {
+        return Err(format!("{:?} is longer than 255 bytes", name));
+    }
+    Ok(())
+}
+
+/// `(current, new)` path pairs for renaming `items` with `pattern`. Names
+/// that clash with existing files or each other get a `_N` suffix.
+pub fn plan_renames(
+    pattern: &RenamePattern,
+    items: &[RenameItem],
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut taken = HashSet::new();
+    let mut renames = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        let name = pattern.file_name(item, index);
+        validate_file_name(&name)?;
+        let mut target = item.path.with_file_name(&name);
+        if target != item.path && (target.exists() || taken.contains(&target)) {
+            target = generate_unique_path(&target, &taken);
+        }
+        taken.insert(target.clone());
+        renames.push((item.path.clone(), target));
+    }
+    Ok(renames)
+}
+
+/// Maximum number of operation batches kept for undo
+const MAX_UNDO_BATCHES: usize = 10;
+
+/// A single reversible step within an operation batch
+#[derive(Debug, Clone)]
+pub enum UndoStep {
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Sent to the Recycle Bin from `path`
+    Recycled {
+        path: PathBuf,
+    },
+}
+
+/// Operations performed together that are undone as a unit
+#[derive(Debug, Clone)]
+pub struct UndoBatch {
+    pub description: String,
+    pub steps: Vec<UndoStep>,
+    /// False for permanent deletes, which block undoing anything older
+    pub reversible: bool,
+}
+
 /// File operations handler
 pub struct FileOperations {
+    /// Send deletes to the Recycle Bin instead of erasing them
+    pub use_recycle_bin: bool,
+    /// Describe each operation in the results and logs without touching the filesystem
+    pub dry_run: bool,
+    /// Append every log entry to this file, one JSON object per line
+    pub log_file: Option<PathBuf>,
+    /// `log_file` as last opened, kept open between entries
+    log_writer: Option<(PathBuf, fs::File)>,
+    /// Why the log file couldn't be written, until the app has shown it
+    log_error: Option<String>,
     logs: Vec<OperationLog>,
+    undo_stack: Vec<UndoBatch>,
+    pending_steps: Vec<UndoStep>,
+    /// Destinations claimed by earlier moves of a dry run, which don't exist yet
+    planned: HashSet<PathBuf>,
 }
 
 impl Default for FileOperations {
@@ -37,7 +323,36 @@ impl Default for FileOperations {
 
 impl FileOperations {
     pub fn new() -> Self {
-        Self { logs: Vec::new() }
+        Self {
+            use_recycle_bin: true,
+            dry_run: false,
+            log_file: None,
+            log_writer: None,
+            log_error: None,
+            logs: Vec::new(),
+            undo_stack: Vec::new(),
+            pending_steps: Vec::new(),
+            planned: HashSet::new(),
+        }
+    }
+
+    /// A handler that only plans, with the same delete settings as `self`
+    pub fn dry_run_copy(&self) -> Self {
+        Self {
+            use_recycle_bin: self.use_recycle_bin,
+            dry_run: true,
+            ..Self::new()
+        }
+    }
+
+    /// Default location of the operation log file
+    pub fn default_log_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileXSorter").join("operations.jsonl"))
+    }
+
+    /// The latest failure to write the log file, if it hasn't been taken yet
+    pub fn take_log_error(&mut self) -> Option<String> {
+        self.log_error.take()
     }
 
     /// Get operation logs
@@ -52,10 +367,13 @@ impl FileOperations {
 
     /// Delete a file
     pub fn delete_file(&mut self, path: &Path) -> OperationResult {
+        if self.dry_run {
+            return self.plan("DELETE", path, None, format!("Delete: {}", path.display()));
+        }
         match fs::remove_file(path) {
             Ok(()) => {
                 let msg = format!("Deleted: {}", path.display());
-                self.logs.push(OperationLog {
+                self.push_log(OperationLog {
                     operation: "DELETE".to_string(),
                     source: path.to_path_buf(),
                     destination: None,
@@ -66,7 +384,7 @@ impl FileOperations {
             }
             Err(e) => {
                 let msg = format!("Failed to delete {}: {}", path.display(), e);
-                self.logs.push(OperationLog {
+                self.push_log(OperationLog {
                     operation: "DELETE".to_string(),
                     source: path.to_path_buf(),
                     destination: None,
@@ -78,13 +396,167 @@ impl FileOperations {
         }
     }
 
-    /// Delete multiple files
+    /// Move a file to the Recycle Bin. If the bin can't take it, the file is
+    /// left in place and an error returned; it is never erased instead.
+    pub fn delete_to_recycle_bin(&mut self, path: &Path) -> OperationResult {
+        if self.dry_run {
+            let msg = format!("Move to Recycle Bin: {}", path.display());
+            return self.plan("RECYCLE", path, None, msg);
+        }
+        match trash::delete(long_path(path)) {
+            Ok(()) => {
+                let msg = format!("Moved to Recycle Bin: {}", path.display());
+                self.pending_steps.push(UndoStep::Recycled {
+                    path: path.to_path_buf(),
+                });
+                self.record("RECYCLE", path, None, true, msg)
+            }
+            Err(e) => {
+                let msg = format!(
+                    "Failed to move {} to the Recycle Bin, it was left in place: {}",
+                    path.display(),
+                    e
+                );
+                self.record("RECYCLE", path, None, false, msg)
+            }
+        }
+    }
+
+    /// Delete multiple files, through the Recycle Bin when enabled
     pub fn delete_files(&mut self, paths: &[PathBuf]) -> Vec<OperationResult> {
-        paths.iter().map(|p| self.delete_file(p)).collect()
+        self.pending_steps.clear();
+        let recycle = self.use_recycle_bin;
+        let results: Vec<OperationResult> = paths
+            .iter()
+            .map(|p| {
+                if recycle {
+                    self.delete_to_recycle_bin(p)
+                } else {
+                    self.delete_file(p)
+                }
+            })
+            .collect();
+        if !self.dry_run {
+            self.push_delete_batch(recycle, &results, "file(s)");
+        }
+        results
+    }
+
+    /// Delete duplicated folders, each paired with the files the scan
+    /// matched inside it, through the Recycle Bin when enabled.
+    ///
+    /// The scan doesn't see everything: excluded subfolders, hidden, empty
+    /// and filtered-out files are never collected. So each folder is listed
+    /// again first, and left alone if it holds anything else.
+    pub fn delete_folders(
+        &mut self,
+        folders: &[(PathBuf, HashSet<PathBuf>)],
+    ) -> Vec<OperationResult> {
+        self.pending_steps.clear();
+        let recycle = self.use_recycle_bin;
+        let results: Vec<OperationResult> = folders
+            .iter()
+            .map(|(folder, matched)| self.delete_folder(folder, matched, recycle))
+            .collect();
+        if !self.dry_run {
+            self.push_delete_batch(recycle, &results, "folder(s)");
+        }
+        results
+    }
+
+    fn delete_folder(
+        &mut self,
+        folder: &Path,
+        matched: &HashSet<PathBuf>,
+        recycle: bool,
+    ) -> OperationResult {
+        let operation = if recycle { "RECYCLE" } else { "DELETE" };
+        if let Some(reason) = unmatched_content(folder, matched) {
+            let msg = format!("Kept {}: {}", folder.display(), reason);
+            return self.record(operation, folder, None, false, msg);
+        }
+        if self.dry_run {
+            let msg = if recycle {
+                format!("Move folder to Recycle Bin: {}", folder.display())
+            } else {
+                format!("Delete folder: {}", folder.display())
+            };
+            return self.plan(operation, folder, None, msg);
+        }
+
+        let removed = if recycle {
+            trash::delete(long_path(folder)).map_err(|e| {
+                format!(
+                    "Failed to move {} to the Recycle Bin, it was left in place: {}",
+                    folder.display(),
+                    e
+                )
+            })
+        } else {
+            fs::remove_dir_all(folder)
+                .map_err(|e| format!("Failed to delete {}: {}", folder.display(), e))
+        };
+        match removed {
+            Ok(()) => {
+                if recycle {
+                    self.pending_steps.push(UndoStep::Recycled {
+                        path: folder.to_path_buf(),
+                    });
+                }
+                let verb = if recycle {
+                    "Moved folder to Recycle Bin"
+                } else {
+                    "Deleted folder"
+                };
+                let msg = format!("{}: {}", verb, folder.display());
+                self.record(operation, folder, None, true, msg)
+            }
+            Err(msg) => self.record(operation, folder, None, false, msg),
+        }
+    }
+
+    /// Record a finished delete for undo: recycled items can be restored,
+    /// permanent deletes block undoing anything older, unless all of them failed
+    fn push_delete_batch(&mut self, recycle: bool, results: &[OperationResult], noun: &str) {
+        let steps = std::mem::take(&mut self.pending_steps);
+        let deleted = results
+            .iter()
+            .filter(|r| matches!(r, OperationResult::Success(_)))
+            .count();
+        if !recycle {
+            if deleted == 0 {
+                return;
+            }
+            self.push_undo_batch(UndoBatch {
+                description: format!("Delete {} {}", deleted, noun),
+                steps,
+                reversible: false,
+            });
+        } else if recycle_bin::CAN_RESTORE {
+            self.push_undo_batch(UndoBatch {
+                description: format!("Recycle {} {}", steps.len(), noun),
+                steps,
+                reversible: true,
+            });
+        }
+        // Where the bin can't be restored from here, recycled files still
+        // aren't lost, so they don't block undoing older batches
     }
 
     /// Move a file to a destination directory
     pub fn move_file(&mut self, source: &Path, dest_dir: &Path) -> OperationResult {
+        // Its name is taken by the file itself, which mustn't count as a clash
+        if source.parent().is_some_and(|dir| same_dir(dir, dest_dir)) {
+            return OperationResult::Success(format!("Already in place: {}", source.display()));
+        }
+
+        if self.dry_run {
+            let dest_path = self.free_destination(source, dest_dir);
+            self.planned.insert(dest_path.clone());
+            let msg = format!("Move: {} -> {}", source.display(), dest_path.display());
+            return self.plan("MOVE", source, Some(dest_path), msg);
+        }
+
         // Ensure destination directory exists (handle race condition directly)
         match fs::create_dir_all(dest_dir) {
             Ok(_) => {}
@@ -95,19 +567,16 @@ impl FileOperations {
             }
         }
 
-        // Build destination path
-        let file_name = source.file_name().unwrap_or_default();
-        let mut dest_path = dest_dir.join(file_name);
-
-        // Handle filename conflicts - generate unique path if file exists
-        if dest_path.exists() {
-            dest_path = generate_unique_path(&dest_path);
-        }
+        let dest_path = self.free_destination(source, dest_dir);
 
         match fs::rename(source, &dest_path) {
             Ok(()) => {
                 let msg = format!("Moved: {} -> {}", source.display(), dest_path.display());
-                self.logs.push(OperationLog {
+                self.pending_steps.push(UndoStep::Moved {
+                    from: source.to_path_buf(),
+                    to: dest_path.clone(),
+                });
+                self.push_log(OperationLog {
                     operation: "MOVE".to_string(),
                     source: source.to_path_buf(),
                     destination: Some(dest_path),
@@ -118,12 +587,16 @@ impl FileOperations {
             }
             Err(e) => {
                 // Try copy + delete if rename fails (cross-drive moves)
-                match fs::copy(source, &dest_path) {
+                match copy_with_times(source, &dest_path) {
                     Ok(_) => match fs::remove_file(source) {
                         Ok(()) => {
                             let msg =
                                 format!("Moved: {} -> {}", source.display(), dest_path.display());
-                            self.logs.push(OperationLog {
+                            self.pending_steps.push(UndoStep::Moved {
+                                from: source.to_path_buf(),
+                                to: dest_path.clone(),
+                            });
+                            self.push_log(OperationLog {
                                 operation: "MOVE".to_string(),
                                 source: source.to_path_buf(),
                                 destination: Some(dest_path),
@@ -140,7 +613,7 @@ impl FileOperations {
                                 source.display(),
                                 del_err
                             );
-                            self.logs.push(OperationLog {
+                            self.push_log(OperationLog {
                                 operation: "MOVE".to_string(),
                                 source: source.to_path_buf(),
                                 destination: Some(dest_path),
@@ -152,7 +625,7 @@ impl FileOperations {
                     },
                     Err(_) => {
                         let msg = format!("Failed to move {}: {}", source.display(), e);
-                        self.logs.push(OperationLog {
+                        self.push_log(OperationLog {
                             operation: "MOVE".to_string(),
                             source: source.to_path_buf(),
                             destination: Some(dest_path),
@@ -166,19 +639,448 @@ impl FileOperations {
         }
     }
 
+    /// Replace each duplicate with a hard link to `keeper`, freeing its space
+    /// while keeping every path. This can't be undone.
+    pub fn link_duplicates(
+        &mut self,
+        keeper: &Path,
+        duplicates: &[PathBuf],
+    ) -> Vec<OperationResult> {
+        duplicates
+            .iter()
+            .map(|duplicate| self.link_file(keeper, duplicate))
+            .collect()
+    }
+
+    /// Swap one duplicate for a hard link to `keeper`
+    fn link_file(&mut self, keeper: &Path, duplicate: &Path) -> OperationResult {
+        if self.dry_run {
+            let keeper_path = Some(keeper.to_path_buf());
+            if duplicate.exists() && !same_volume(keeper, duplicate) {
+                let msg = format!(
+                    "Cannot link {}: hard links can't span drives",
+                    duplicate.display()
+                );
+                return self.record("LINK", duplicate, keeper_path, false, msg);
+            }
+            let msg = format!("Link: {} -> {}", duplicate.display(), keeper.display());
+            return self.plan("LINK", duplicate, keeper_path, msg);
+        }
+
+        // Link under a temporary name first so a failure leaves the duplicate untouched
+        let name = duplicate.file_name().unwrap_or_default().to_string_lossy();
+        let temp = duplicate.with_file_name(format!(".{}.{}.link", name, uuid_simple()));
+        let linked = fs::hard_link(keeper, &temp).and_then(|()| {
+            fs::rename(&temp, duplicate).inspect_err(|_| {
+                let _ = fs::remove_file(&temp);
+            })
+        });
+
+        let (success, msg) = match linked {
+            Ok(()) => (
+                true,
+                format!("Linked: {} -> {}", duplicate.display(), keeper.display()),
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => (
+                false,
+                format!(
+                    "Cannot link {}: hard links can't span drives",
+                    duplicate.display()
+                ),
+            ),
+            Err(e) => (
+                false,
+                format!("Failed to link {}: {}", duplicate.display(), e),
+            ),
+        };
+        self.record("LINK", duplicate, Some(keeper.to_path_buf()), success, msg)
+    }
+
     /// Move multiple files to a destination directory
     pub fn move_files(&mut self, sources: &[PathBuf], dest_dir: &Path) -> Vec<OperationResult> {
-        sources
+        let moves: Vec<(PathBuf, PathBuf)> = sources
+            .iter()
+            .map(|p| (p.clone(), dest_dir.to_path_buf()))
+            .collect();
+        self.move_files_into(&moves, dest_dir)
+    }
+
+    /// Move each `(source, target_dir)` pair as one undoable batch under `dest_root`
+    pub fn move_files_into(
+        &mut self,
+        moves: &[(PathBuf, PathBuf)],
+        dest_root: &Path,
+    ) -> Vec<OperationResult> {
+        self.pending_steps.clear();
+        self.planned.clear();
+        let results = moves
+            .iter()
+            .map(|(source, target_dir)| self.move_file(source, target_dir))
+            .collect();
+        if self.dry_run {
+            return results;
+        }
+        let steps = std::mem::take(&mut self.pending_steps);
+        if !steps.is_empty() {
+            self.push_undo_batch(UndoBatch {
+                description: format!("Move {} file(s) to {}", steps.len(), dest_root.display()),
+                steps,
+                reversible: true,
+            });
+        }
+        results
+    }
+
+    /// Rename each `(current, new)` pair, see [`plan_renames`], as one undoable batch
+    pub fn rename_files(&mut self, renames: &[(PathBuf, PathBuf)]) -> Vec<OperationResult> {
+        self.pending_steps.clear();
+        let results = renames
+            .iter()
+            .map(|(from, to)| self.rename_file(from, to))
+            .collect();
+        if self.dry_run {
+            return results;
+        }
+        let steps = std::mem::take(&mut self.pending_steps);
+        if !steps.is_empty() {
+            self.push_undo_batch(UndoBatch {
+                description: format!("Rename {} file(s)", steps.len()),
+                steps,
+                reversible: true,
+            });
+        }
+        results
+    }
+
+    fn rename_file(&mut self, from: &Path, to: &Path) -> OperationResult {
+        if from == to {
+            return OperationResult::Success(format!("Name unchanged: {}", from.display()));
+        }
+        let msg = format!("Rename: {} -> {}", from.display(), to.display());
+        if self.dry_run {
+            return self.plan("RENAME", from, Some(to.to_path_buf()), msg);
+        }
+        // Never overwrite a file that appeared after the renames were planned
+        if to.exists() {
+            let msg = format!(
+                "Cannot rename {}: {} already exists",
+                from.display(),
+                to.display()
+            );
+            return self.record("RENAME", from, Some(to.to_path_buf()), false, msg);
+        }
+        match fs::rename(from, to) {
+            Ok(()) => {
+                self.pending_steps.push(UndoStep::Moved {
+                    from: from.to_path_buf(),
+                    to: to.to_path_buf(),
+                });
+                self.record("RENAME", from, Some(to.to_path_buf()), true, msg)
+            }
+            Err(e) => {
+                let msg = format!("Failed to rename {}: {}", from.display(), e);
+                self.record("RENAME", from, Some(to.to_path_buf()), false, msg)
+            }
+        }
+    }
+
+    /// The batch that the next undo would reverse, if any
+    pub fn next_undo(&self) -> Option<&UndoBatch> {
+        self.undo_stack.last()
+    }
+
+    /// Reverse the most recent operation batch
+    pub fn undo_last(&mut self) -> Vec<OperationResult> {
+        let batch = match self.undo_stack.pop() {
+            Some(batch) => batch,
+            None => return vec![OperationResult::Error("Nothing to undo".to_string())],
+        };
+
+        if !batch.reversible {
+            let msg = format!("Cannot undo: {} was permanent", batch.description);
+            self.undo_stack.push(batch);
+            return vec![OperationResult::Error(msg)];
+        }
+
+        let mut results = Vec::with_capacity(batch.steps.len());
+        let mut recycled = Vec::new();
+        for step in batch.steps.iter().rev() {
+            match step {
+                UndoStep::Moved { from, to } => results.push(self.restore_moved(to, from)),
+                UndoStep::Recycled { path } => recycled.push(path.as_path()),
+            }
+        }
+        if !recycled.is_empty() {
+            results.extend(self.restore_recycled(&recycled));
+        }
+        results
+    }
+
+    /// Put files back from the Recycle Bin, taking the latest copy of each
+    /// path in case it was recycled more than once
+    fn restore_recycled(&mut self, paths: &[&Path]) -> Vec<OperationResult> {
+        let mut items = match recycle_bin::list() {
+            Ok(items) => items,
+            Err(e) => {
+                let msg = format!("Failed to read the Recycle Bin: {}", e);
+                return vec![OperationResult::Error(msg)];
+            }
+        };
+        items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+
+        paths
             .iter()
-            .map(|p| self.move_file(p, dest_dir))
+            .map(|&path| {
+                let Some(index) = items.iter().position(|i| i.original_path() == path) else {
+                    let msg = format!("Cannot restore {}: not in the Recycle Bin", path.display());
+                    return self.log_undo(path, path, false, msg);
+                };
+                if path.exists() {
+                    let msg = format!("Cannot restore {}: it already exists", path.display());
+                    return self.log_undo(path, path, false, msg);
+                }
+                match recycle_bin::restore_all([items.remove(index)]) {
+                    Ok(()) => {
+                        let msg = format!("Restored from Recycle Bin: {}", path.display());
+                        self.log_undo(path, path, true, msg)
+                    }
+                    Err(e) => {
+                        let msg = format!("Failed to restore {}: {}", path.display(), e);
+                        self.log_undo(path, path, false, msg)
+                    }
+                }
+            })
             .collect()
     }
+
+    /// Move a file back to where it was before a move
+    fn restore_moved(&mut self, current: &Path, original: &Path) -> OperationResult {
+        if original.exists() {
+            let msg = format!(
+                "Cannot restore {}: {} already exists",
+                current.display(),
+                original.display()
+            );
+            return self.log_undo(current, original, false, msg);
+        }
+
+        let moved = fs::rename(current, original).or_else(|_| {
+            copy_with_times(current, original)?;
+            fs::remove_file(current)
+        });
+        match moved {
+            Ok(()) => {
+                let msg = format!("Restored: {} -> {}", current.display(), original.display());
+                self.log_undo(current, original, true, msg)
+            }
+            Err(e) => {
+                let msg = format!("Failed to restore {}: {}", current.display(), e);
+                self.log_undo(current, original, false, msg)
+            }
+        }
+    }
+
+    fn log_undo(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        success: bool,
+        message: String,
+    ) -> OperationResult {
+        self.push_log(OperationLog {
+            operation: "UNDO".to_string(),
+            source: source.to_path_buf(),
+            destination: Some(destination.to_path_buf()),
+            success,
+            message: message.clone(),
+        });
+        if success {
+            OperationResult::Success(message)
+        } else {
+            OperationResult::Error(message)
+        }
+    }
+
+    /// Where moving `source` into `dest_dir` would land, renamed if the name is taken
+    fn free_destination(&self, source: &Path, dest_dir: &Path) -> PathBuf {
+        let file_name = source.file_name().unwrap_or_default();
+        let dest_path = dest_dir.join(file_name);
+        if dest_path.exists() || self.planned.contains(&dest_path) {
+            generate_unique_path(&dest_path, &self.planned)
+        } else {
+            dest_path
+        }
+    }
+
+    /// Log what a dry run would do; a missing source is reported as a failure
+    fn plan(
+        &mut self,
+        operation: &str,
+        source: &Path,
+        destination: Option<PathBuf>,
+        message: String,
+    ) -> OperationResult {
+        if source.exists() {
+            self.record(operation, source, destination, true, message)
+        } else {
+            let message = format!("{} (file not found)", message);
+            self.record(operation, source, destination, false, message)
+        }
+    }
+
+    fn record(
+        &mut self,
+        operation: &str,
+        source: &Path,
+        destination: Option<PathBuf>,
+        success: bool,
+        message: String,
+    ) -> OperationResult {
+        self.push_log(OperationLog {
+            operation: operation.to_string(),
+            source: source.to_path_buf(),
+            destination,
+            success,
+            message: message.clone(),
+        });
+        if success {
+            OperationResult::Success(message)
+        } else {
+            OperationResult::Error(message)
+        }
+    }
+
+    /// Keep a log entry, also writing it to `log_file` unless this is a dry run
+    fn push_log(&mut self, entry: OperationLog) {
+        if let (Some(path), false) = (self.log_file.clone(), self.dry_run) {
+            if let Err(e) = self.append_log_line(&path, &entry) {
+                let message = format!("Failed to write operation log {}: {}", path.display(), e);
+                log::warn!("{}", message);
+                self.log_error = Some(message);
+            }
+        }
+        self.logs.push(entry);
+    }
+
+    /// Append `entry` to the log at `path` as a timestamped JSON line
+    fn append_log_line(&mut self, path: &Path, entry: &OperationLog) -> Result<(), String> {
+        #[derive(Serialize)]
+        struct LogLine<'a> {
+            timestamp: String,
+            #[serde(flatten)]
+            entry: &'a OperationLog,
+        }
+
+        let line = serde_json::to_string(&LogLine {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            entry,
+        })
+        .map_err(|e| format!("Failed to encode: {}", e))?;
+        let file = match &mut self.log_writer {
+            Some((open, file)) if open == path => file,
+            writer => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create folder: {}", e))?;
+                }
+                let file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| format!("Failed to open: {}", e))?;
+                &mut writer.insert((path.to_path_buf(), file)).1
+            }
+        };
+        writeln!(file, "{}", line).map_err(|e| {
+            // Reopen on the next entry, in case the file was moved or its drive came back
+            self.log_writer = None;
+            format!("Failed to write: {}", e)
+        })
+    }
+
+    fn push_undo_batch(&mut self, batch: UndoBatch) {
+        if batch.reversible && batch.steps.is_empty() {
+            return;
+        }
+        self.undo_stack.push(batch);
+        if self.undo_stack.len() > MAX_UNDO_BATCHES {
+            self.undo_stack.remove(0);
+        }
+    }
+}
+
+/// Why `folder` holds more than the `matched` files, if it does: a file or
+/// link the scan didn't match, or something that couldn't be listed
+fn unmatched_content(folder: &Path, matched: &HashSet<PathBuf>) -> Option<String> {
+    if !folder.is_dir() {
+        return Some("not a folder".to_string());
+    }
+    for entry in walkdir::WalkDir::new(folder).follow_links(false) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => return Some(format!("couldn't list everything in it: {}", e)),
+        };
+        if !entry.file_type().is_dir() && !matched.contains(entry.path()) {
+            return Some(format!(
+                "{} wasn't matched as a duplicate",
+                entry.path().display()
+            ));
+        }
+    }
+    None
+}
+
+/// Listing and restoring Recycle Bin items, which the macOS Trash doesn't offer
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+mod recycle_bin {
+    pub const CAN_RESTORE: bool = true;
+    pub use trash::os_limited::{list, restore_all};
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+mod recycle_bin {
+    use trash::{Error, TrashItem};
+
+    pub const CAN_RESTORE: bool = false;
+
+    pub fn list() -> Result<Vec<TrashItem>, Error> {
+        Err(unsupported())
+    }
+
+    pub fn restore_all(_items: impl IntoIterator<Item = TrashItem>) -> Result<(), Error> {
+        Err(unsupported())
+    }
+
+    fn unsupported() -> Error {
+        Error::Unknown {
+            description: "the Trash can't be restored from here".to_string(),
+        }
+    }
 }
 
-/// Generate a unique path by appending a number
-fn generate_unique_path(path: &Path) -> PathBuf {
-    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+/// Generate a unique path by appending a number, also avoiding the `taken` paths
+fn generate_unique_path(path: &Path, taken: &HashSet<PathBuf>) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string());
+    let (stem, extension) = split_extension(&name);
     let parent = path.parent().unwrap_or(Path::new("."));
 
     let mut counter = 1;
@@ -190,7 +1092,7 @@ fn generate_unique_path(path: &Path) -> PathBuf {
         };
 
         let new_path = parent.join(new_name);
-        if !new_path.exists() {
+        if !new_path.exists() && !taken.contains(&new_path) {
             return new_path;
         }
         counter += 1;
@@ -202,6 +1104,44 @@ fn generate_unique_path(path: &Path) -> PathBuf {
     }
 }
 
+/// Copy a file for a cross-drive move, keeping its timestamps.
+///
+/// `fs::copy` doesn't reliably carry the modified time over, which matters
+/// for libraries sorted by date. Failing to restore the times is only logged.
+fn copy_with_times(from: &Path, to: &Path) -> std::io::Result<()> {
+    let metadata = fs::metadata(from)?;
+    fs::copy(from, to)?;
+
+    let mut times = fs::FileTimes::new();
+    if let Ok(modified) = metadata.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    #[cfg(windows)]
+    if let Ok(created) = metadata.created() {
+        use std::os::windows::fs::FileTimesExt;
+        times = times.set_created(created);
+    }
+    let restored = fs::OpenOptions::new()
+        .write(true)
+        .open(to)
+        .and_then(|file| file.set_times(times));
+    if let Err(e) = restored {
+        log::warn!("Failed to keep timestamps of {}: {}", to.display(), e);
+    }
+    Ok(())
+}
+
+/// Whether two paths name the same directory, resolving `..` and links where they exist
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 /// Generate a cryptographically secure unique ID
 fn uuid_simple() -> String {
     Uuid::new_v4().to_string()
@@ -214,7 +1154,444 @@ mod tests {
     #[test]
     fn test_generate_unique_path() {
         let path = Path::new("/tmp/test.txt");
-        let unique = generate_unique_path(path);
+        let unique = generate_unique_path(path, &HashSet::new());
         assert!(unique.to_string_lossy().contains("test_1.txt"));
+
+        let archive =
+            generate_unique_path(Path::new("/tmp/fxs_missing/Backup.TAR.GZ"), &HashSet::new());
+        assert!(archive.ends_with("Backup_1.TAR.GZ"));
+    }
+
+    #[test]
+    fn test_per_group_move_layout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (src_a, src_b) = (root.join("a"), root.join("b"));
+        fs::create_dir_all(&src_a).unwrap();
+        fs::create_dir_all(&src_b).unwrap();
+        fs::write(src_a.join("x.txt"), b"1").unwrap();
+        fs::write(src_b.join("x.txt"), b"1").unwrap();
+
+        let dest = root.join("review");
+        let source = src_a.join("x.txt");
+        let dir = MoveLayout::PerGroup.target_dir(&dest, &source, None, 0, "partial:ab12cd34ef");
+        assert_eq!(dir, dest.join("group_0001_ab12cd34"));
+        assert_eq!(
+            MoveLayout::Flat.target_dir(&dest, &source, None, 3, "ff"),
+            dest
+        );
+
+        let mut ops = FileOperations::new();
+        let moves = vec![
+            (src_a.join("x.txt"), dir.clone()),
+            (src_b.join("x.txt"), dir.clone()),
+        ];
+        ops.move_files_into(&moves, &dest);
+        // Same-named duplicates in one group get unique names
+        assert!(dir.join("x.txt").exists());
+        assert!(dir.join("x_1.txt").exists());
+
+        ops.undo_last();
+        assert!(src_a.join("x.txt").exists() && src_b.join("x.txt").exists());
+    }
+
+    #[test]
+    fn test_preserve_structure_layout() {
+        let paths = [
+            Path::new("/photos/2021/beach/a.jpg"),
+            Path::new("/photos/2021/b.jpg"),
+            Path::new("/photos/2022/trip/c.jpg"),
+        ];
+        let base = common_base(paths).unwrap();
+        assert_eq!(base, Path::new("/photos"));
+
+        let dest = Path::new("/review");
+        let dir =
+            |source| MoveLayout::PreserveStructure.target_dir(dest, source, Some(&base), 0, "ff");
+        assert_eq!(dir(paths[0]), Path::new("/review/2021/beach"));
+        assert_eq!(dir(paths[1]), Path::new("/review/2021"));
+        // Outside the base, or with no base at all, it's flat
+        assert_eq!(dir(Path::new("/music/d.mp3")), dest);
+        assert_eq!(
+            MoveLayout::PreserveStructure.target_dir(dest, paths[0], None, 0, "ff"),
+            dest
+        );
+
+        // Unrelated roots share nothing worth recreating
+        assert_eq!(
+            common_base([Path::new("/a/x.jpg"), Path::new("/b/y.jpg")]),
+            None
+        );
+        assert_eq!(
+            common_base([Path::new("/a/b/x.jpg")]),
+            Some(PathBuf::from("/a/b"))
+        );
+    }
+
+    #[test]
+    fn test_move_renames_clashes_and_skips_files_already_in_place() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let dest = root.join("dest");
+        fs::create_dir_all(root.join("one")).unwrap();
+        fs::create_dir_all(root.join("two")).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(root.join("one").join("a.jpg"), b"first").unwrap();
+        fs::write(root.join("two").join("a.jpg"), b"second").unwrap();
+        fs::write(dest.join("b.jpg"), b"resident").unwrap();
+
+        let mut ops = FileOperations::new();
+        let results = ops.move_files(
+            &[
+                root.join("one").join("a.jpg"),
+                root.join("two").join("a.jpg"),
+                // Named through `..`, still its own folder
+                dest.join("..").join("dest").join("b.jpg"),
+            ],
+            &dest,
+        );
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, OperationResult::Success(_))));
+        assert_eq!(fs::read(dest.join("a.jpg")).unwrap(), b"first");
+        assert_eq!(fs::read(dest.join("a_1.jpg")).unwrap(), b"second");
+        assert_eq!(fs::read(dest.join("b.jpg")).unwrap(), b"resident");
+        assert!(!dest.join("b_1.jpg").exists());
+        assert!(ops
+            .next_undo()
+            .unwrap()
+            .description
+            .starts_with("Move 2 file(s)"));
+
+        // Nothing moved, so nothing to undo
+        ops.undo_last();
+        ops.move_files(&[dest.join("b.jpg")], &dest);
+        assert!(ops.next_undo().is_none());
+    }
+
+    #[test]
+    fn test_cross_drive_copy_keeps_timestamps() {
+        use std::time::{Duration, SystemTime};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (source, copy) = (root.join("photo.jpg"), root.join("copy.jpg"));
+        fs::write(&source, b"pixels").unwrap();
+        let taken = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_times(fs::FileTimes::new().set_modified(taken).set_accessed(taken))
+            .unwrap();
+
+        // The fallback `move_file` uses when a rename can't cross drives
+        copy_with_times(&source, &copy).unwrap();
+        let metadata = fs::metadata(&copy).unwrap();
+        assert_eq!(metadata.modified().unwrap(), taken);
+        assert_eq!(metadata.accessed().unwrap(), taken);
+        assert_eq!(fs::read(&copy).unwrap(), b"pixels");
+    }
+
+    #[test]
+    fn test_rename_pattern_validates_and_resolves_clashes() {
+        assert!(RenamePattern::parse("{name}_{index}").is_ok());
+        assert!(RenamePattern::parse("").is_err());
+        assert!(RenamePattern::parse("{name").is_err());
+        assert!(RenamePattern::parse("{size}").is_err());
+        assert!(RenamePattern::parse("a:{n}").is_err());
+        assert!(validate_file_name("com1.txt").is_err());
+        assert!(validate_file_name("photo. ").is_err());
+        assert!(validate_file_name("console.txt").is_ok());
+        assert!(validate_file_name("x日.jpg").is_ok());
+        assert!(validate_file_name("日.txt").is_ok());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        for name in ["a.jpg", "b.jpg", "abcdef12_1.jpg"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+        let item = |name: &str, position| RenameItem {
+            path: root.join(name),
+            hash: "partial:abcdef1234".to_string(),
+            position,
+        };
+        let items = [item("a.jpg", 0), item("b.jpg", 0)];
+
+        let pattern = RenamePattern::parse("{hash8}_{n}").unwrap();
+        let renames = plan_renames(&pattern, &items).unwrap();
+        // The first name is already taken on disk, the second by the first rename
+        assert_eq!(renames[0].1, root.join("abcdef12_1_1.jpg"));
+        assert_eq!(renames[1].1, root.join("abcdef12_1_2.jpg"));
+        let unchanged = RenamePattern::parse("{name}").unwrap();
+        assert_eq!(
+            plan_renames(&unchanged, &items).unwrap()[0].1,
+            root.join("a.jpg")
+        );
+        let reserved = RenamePattern::parse("nul").unwrap();
+        assert!(plan_renames(&reserved, &items).is_err());
+
+        let mut ops = FileOperations::new();
+        let results = ops.rename_files(&renames);
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, OperationResult::Success(_))));
+        assert_eq!(fs::read(root.join("abcdef12_1_2.jpg")).unwrap(), b"b.jpg");
+        assert_eq!(ops.get_logs()[0].operation, "RENAME");
+        ops.undo_last();
+        assert_eq!(fs::read(root.join("a.jpg")).unwrap(), b"a.jpg");
+        assert!(!root.join("abcdef12_1_1.jpg").exists());
+    }
+
+    #[test]
+    fn test_delete_without_recycle_bin_is_permanent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let file = root.join("erased.txt");
+        fs::write(&file, b"data").unwrap();
+
+        let mut ops = FileOperations::new();
+        assert!(ops.use_recycle_bin);
+        ops.use_recycle_bin = false;
+        let results = ops.delete_files(std::slice::from_ref(&file));
+        assert!(matches!(results[0], OperationResult::Success(_)));
+        assert!(!file.exists());
+        assert_eq!(ops.get_logs()[0].operation, "DELETE");
+        assert!(ops.next_undo().unwrap().description.starts_with("Delete"));
+    }
+
+    #[test]
+    fn test_undo_move_batches_in_lifo_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (first_dest, second_dest) = (root.join("first"), root.join("second"));
+        let file = root.join("a.txt");
+        fs::write(&file, b"data").unwrap();
+
+        let mut ops = FileOperations::new();
+        ops.move_files(std::slice::from_ref(&file), &first_dest);
+        ops.move_files(&[first_dest.join("a.txt")], &second_dest);
+        assert!(second_dest.join("a.txt").exists());
+
+        ops.undo_last();
+        assert!(first_dest.join("a.txt").exists());
+        ops.undo_last();
+        assert!(file.exists());
+        assert!(ops.next_undo().is_none());
+    }
+
+    #[test]
+    fn test_link_duplicates_keeps_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let keeper = root.join("keep.txt");
+        let duplicate = root.join("sub").join("copy.txt");
+        fs::write(&keeper, b"data").unwrap();
+        fs::write(&duplicate, b"data").unwrap();
+
+        let mut ops = FileOperations::new();
+        let results = ops.link_duplicates(&keeper, std::slice::from_ref(&duplicate));
+        assert!(matches!(results[0], OperationResult::Success(_)));
+        // Writing through the keeper shows up at the linked path
+        fs::write(&keeper, b"changed").unwrap();
+        assert_eq!(fs::read(&duplicate).unwrap(), b"changed");
+        assert_eq!(fs::read_dir(root.join("sub")).unwrap().count(), 1);
+
+        let results = ops.link_duplicates(&root.join("missing.txt"), std::slice::from_ref(&keeper));
+        assert!(matches!(results[0], OperationResult::Error(_)));
+        assert!(keeper.exists());
+    }
+
+    #[test]
+    fn test_dry_run_plans_without_touching_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (src_a, src_b, dest) = (root.join("a"), root.join("b"), root.join("dest"));
+        fs::create_dir_all(&src_a).unwrap();
+        fs::create_dir_all(&src_b).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        for file in [src_a.join("x.txt"), src_b.join("x.txt"), dest.join("x.txt")] {
+            fs::write(file, b"1").unwrap();
+        }
+
+        let mut ops = FileOperations::new().dry_run_copy();
+        let results = ops.move_files(&[src_a.join("x.txt"), src_b.join("x.txt")], &dest);
+        // Renames account for both the existing file and the earlier planned move
+        let planned: Vec<&Path> = ops
+            .get_logs()
+            .iter()
+            .filter_map(|log| log.destination.as_deref())
+            .collect();
+        assert_eq!(planned, [dest.join("x_1.txt"), dest.join("x_2.txt")]);
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, OperationResult::Success(_))));
+
+        let results = ops.delete_files(&[src_a.join("x.txt"), root.join("gone.txt")]);
+        assert!(matches!(results[0], OperationResult::Success(_)));
+        assert!(matches!(results[1], OperationResult::Error(_)));
+        assert!(src_a.join("x.txt").exists() && !dest.join("x_1.txt").exists());
+        assert!(ops.next_undo().is_none());
+    }
+
+    #[test]
+    fn test_operation_log_file_appends_json_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let log_file = root.join("logs").join("operations.jsonl");
+        let (kept, erased) = (root.join("kept.txt"), root.join("erased.txt"));
+        fs::write(&kept, b"data").unwrap();
+        fs::write(&erased, b"data").unwrap();
+
+        let mut ops = FileOperations::new();
+        ops.use_recycle_bin = false;
+        ops.log_file = Some(log_file.clone());
+        ops.delete_files(std::slice::from_ref(&erased));
+        ops.move_files(&[root.join("missing.txt")], &root.join("dest"));
+        // Planned operations never reach the permanent record
+        let mut dry = ops.dry_run_copy();
+        dry.log_file = Some(log_file.clone());
+        dry.delete_files(&[kept]);
+
+        let text = fs::read_to_string(&log_file).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["operation"], "DELETE");
+        assert_eq!(lines[0]["success"], true);
+        assert_eq!(lines[0]["source"], erased.to_string_lossy().as_ref());
+        assert!(lines[0]["timestamp"]
+            .as_str()
+            .is_some_and(|t| !t.is_empty()));
+        assert_eq!(lines[1]["operation"], "MOVE");
+        assert_eq!(lines[1]["success"], false);
+        assert!(lines[1]["destination"].is_string());
+        assert_eq!(ops.take_log_error(), None);
+
+        // A log under a plain file can't be created, which is reported once
+        ops.log_file = Some(root.join("kept.txt").join("operations.jsonl"));
+        ops.move_files(&[root.join("missing.txt")], &root.join("dest"));
+        assert!(ops
+            .take_log_error()
+            .is_some_and(|e| e.contains("operation log")));
+        assert_eq!(ops.take_log_error(), None);
+    }
+
+    #[test]
+    fn test_permanent_delete_blocks_undo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let file = root.join("b.txt");
+        fs::write(&file, b"data").unwrap();
+
+        let mut ops = FileOperations::new();
+        ops.use_recycle_bin = false;
+        // Nothing was erased, so there's nothing to block undo with
+        ops.delete_files(&[root.join("missing.txt")]);
+        assert!(ops.next_undo().is_none());
+
+        ops.delete_files(&[file, root.join("missing.txt")]);
+        let batch = ops.next_undo().unwrap();
+        assert!(!batch.reversible);
+        assert_eq!(batch.description, "Delete 1 file(s)");
+        let results = ops.undo_last();
+        assert!(matches!(results[0], OperationResult::Error(_)));
+        assert!(!ops.next_undo().unwrap().reversible);
+    }
+
+    #[test]
+    fn test_folder_holding_unscanned_files_is_kept() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (copy, extra) = (tmp.path().join("copy"), tmp.path().join("extra"));
+        for folder in [&copy, &extra] {
+            fs::create_dir_all(folder.join("sub")).unwrap();
+            fs::write(folder.join("a.txt"), b"same").unwrap();
+            fs::write(folder.join("sub").join("b.txt"), b"other").unwrap();
+        }
+        // Never collected by the scan, e.g. hidden or excluded
+        fs::write(extra.join("sub").join(".hidden"), b"").unwrap();
+        let matched = |folder: &Path| -> HashSet<PathBuf> {
+            HashSet::from([folder.join("a.txt"), folder.join("sub").join("b.txt")])
+        };
+
+        let mut ops = FileOperations::new();
+        ops.use_recycle_bin = false;
+        // Folders are never deleted as plain files
+        assert!(matches!(ops.delete_file(&copy), OperationResult::Error(_)));
+        let results = ops.delete_folders(&[
+            (copy.clone(), matched(&copy)),
+            (extra.clone(), matched(&extra)),
+        ]);
+        assert!(matches!(results[0], OperationResult::Success(_)));
+        assert!(!copy.exists());
+        let OperationResult::Error(msg) = &results[1] else {
+            panic!("a folder with unmatched files must be kept");
+        };
+        assert!(msg.contains(".hidden"), "{}", msg);
+        assert!(extra.join("sub").join(".hidden").exists());
+    }
+
+    #[test]
+    fn test_recycle_failure_keeps_file_and_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = tmp.path().join("gone.txt");
+
+        let mut ops = FileOperations::new();
+        ops.use_recycle_bin = true;
+        let results = ops.delete_files(std::slice::from_ref(&missing));
+        let OperationResult::Error(msg) = &results[0] else {
+            panic!("a failed recycle must not report success");
+        };
+        assert!(msg.contains("Recycle Bin"));
+        assert_eq!(ops.get_logs()[0].operation, "RECYCLE");
+        // Nothing reached the bin, so there's nothing to undo
+        assert!(ops.next_undo().is_none());
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_recycle_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_recycles_and_restores(&tmp.path().join(format!("recycled_{}.txt", uuid_simple())));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_recycle_past_max_path() {
+        // The Recycle Bin is reached through the shell, which doesn't
+        // lengthen paths the way std does
+        let tmp = tempfile::tempdir().unwrap();
+        let deep = (0..6).fold(tmp.path().to_path_buf(), |dir, i| {
+            dir.join(format!("{}_{}", i, "nested".repeat(8)))
+        });
+        fs::create_dir_all(&deep).unwrap();
+        let file = deep.join(format!("recycled_{}.txt", uuid_simple()));
+        assert!(file.as_os_str().len() > 260);
+        assert_recycles_and_restores(&file);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn assert_recycles_and_restores(file: &Path) {
+        fs::write(file, b"data").unwrap();
+
+        let mut ops = FileOperations::new();
+        let results = ops.delete_files(&[file.to_path_buf()]);
+        assert!(
+            matches!(results[0], OperationResult::Success(_)),
+            "{:?}",
+            results
+        );
+        assert!(!file.exists());
+        assert!(ops.next_undo().unwrap().reversible);
+        let restored = ops.undo_last();
+        assert!(
+            matches!(restored[0], OperationResult::Success(_)),
+            "{:?}",
+            restored
+        );
+        assert_eq!(fs::read(file).unwrap(), b"data");
     }
 }
@@ -0,0 +1,204 @@
+//! Filter module - Narrowing and ordering the visible duplicate groups
+//!
+//! A query is a list of space-separated terms that must all match. Plain
+//! terms match any file name or path in the group, case-insensitively;
+//! `size>10MB`, `size<=1.5GB` and friends compare the size of each copy.
+
+use std::cmp::Reverse;
+
+use crate::scanner::DuplicateGroup;
+
+/// Order of the duplicate group list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GroupSort {
+    /// Most wasted space first, the order scans produce
+    #[default]
+    WastedSpace,
+    /// Most copies first
+    FileCount,
+    /// Largest files first
+    FileSize,
+    /// Keeper file name, A-Z
+    Name,
+}
+
+impl GroupSort {
+    pub const ALL: [GroupSort; 4] = [
+        GroupSort::WastedSpace,
+        GroupSort::FileCount,
+        GroupSort::FileSize,
+        GroupSort::Name,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupSort::WastedSpace => "Wasted space",
+            GroupSort::FileCount => "Number of copies",
+            GroupSort::FileSize => "File size",
+            GroupSort::Name => "Name",
+        }
+    }
+}
+
+/// Sort `groups` in place and return the old index of each group in its new position
+pub fn sort_groups(groups: &mut Vec<DuplicateGroup>, sort: GroupSort) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..groups.len()).collect();
+    let keeper_size = |g: &DuplicateGroup| g.files.first().map_or(0, |f| f.size);
+    match sort {
+        GroupSort::WastedSpace => order.sort_by_key(|&i| Reverse(groups[i].wasted_size)),
+        GroupSort::FileCount => order.sort_by_key(|&i| Reverse(groups[i].files.len())),
+        GroupSort::FileSize => order.sort_by_key(|&i| Reverse(keeper_size(&groups[i]))),
+        GroupSort::Name => order.sort_by_cached_key(|&i| {
+            groups[i]
+                .files
+                .first()
+                .map(|f| f.name.to_lowercase())
+                .unwrap_or_default()
+        }),
+    }
+
+    let mut slots: Vec<Option<DuplicateGroup>> = groups.drain(..).map(Some).collect();
+    groups.extend(order.iter().filter_map(|&i| slots[i].take()));
+    order
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeOp {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+/// A parsed group search query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupQuery {
+    /// Lowercase substrings, all of which must occur in some file's path
+    text: Vec<String>,
+    sizes: Vec<(SizeOp, u64)>,
+}
+
+impl GroupQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        for term in query.split_whitespace() {
+            match parse_size_term(term) {
+                Some(size) => parsed.sizes.push(size),
+                None => parsed.text.push(term.to_lowercase()),
+            }
+        }
+        parsed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.sizes.is_empty()
+    }
+
+    /// Whether `group` satisfies every term
+    pub fn matches(&self, group: &DuplicateGroup) -> bool {
+        // Copies share a size except in partial-copy groups, where the keeper is the full file
+        let size = group.files.first().map_or(0, |f| f.size);
+        let size_ok = self.sizes.iter().all(|&(op, limit)| match op {
+            SizeOp::Greater => size > limit,
+            SizeOp::GreaterOrEqual => size >= limit,
+            SizeOp::Less => size < limit,
+            SizeOp::LessOrEqual => size <= limit,
+        });
+        size_ok
+            && self.text.iter().all(|term| {
+                group
+                    .files
+                    .iter()
+                    .any(|f| f.path.to_string_lossy().to_lowercase().contains(term))
+            })
+    }
+}
+
+/// Parse `size>10MB` style terms; anything else is left as text
+fn parse_size_term(term: &str) -> Option<(SizeOp, u64)> {
+    let rest = term.to_ascii_lowercase();
+    let rest = rest.strip_prefix("size")?;
+    let (op, value) = if let Some(v) = rest.strip_prefix(">=") {
+        (SizeOp::GreaterOrEqual, v)
+    } else if let Some(v) = rest.strip_prefix("<=") {
+        (SizeOp::LessOrEqual, v)
+    } else if let Some(v) = rest.strip_prefix('>') {
+        (SizeOp::Greater, v)
+    } else if let Some(v) = rest.strip_prefix('<') {
+        (SizeOp::Less, v)
+    } else {
+        return None;
+    };
+    Some((op, parse_size(value)?))
+}
+
+/// Parse a size such as `512`, `10kb` or `1.5GB` (binary units) into bytes
+fn parse_size(value: &str) -> Option<u64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "" | "b" => 1u64,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        "t" | "tb" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{FileEntry, GroupKind, MatchConfidence};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sort_groups_reports_old_positions() {
+        let group = |name: &str, size: u64, copies: usize| DuplicateGroup {
+            hash: name.into(),
+            files: (0..copies)
+                .map(|_| FileEntry::new(PathBuf::from(name), name.into(), size))
+                .collect(),
+            total_size: size * copies as u64,
+            wasted_size: size * (copies as u64 - 1),
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+        let mut groups = vec![group("b", 10, 5), group("a", 30, 2), group("c", 20, 2)];
+
+        assert_eq!(sort_groups(&mut groups, GroupSort::Name), [1, 0, 2]);
+        assert_eq!(groups[0].hash, "a");
+        assert_eq!(sort_groups(&mut groups, GroupSort::FileSize), [0, 2, 1]);
+        let hashes: Vec<&str> = groups.iter().map(|g| g.hash.as_str()).collect();
+        assert_eq!(hashes, ["a", "c", "b"]);
+        assert_eq!(sort_groups(&mut groups, GroupSort::WastedSpace), [2, 0, 1]);
+        assert_eq!(groups[0].hash, "b");
+    }
+
+    #[test]
+    fn test_group_query_matches_text_and_size() {
+        let file = |path: &str| FileEntry::new(PathBuf::from(path), path.into(), 20 << 20);
+        let group = DuplicateGroup {
+            hash: "x".into(),
+            files: vec![file("C:/Photos/Beach.JPG"), file("D:/Backup/beach.jpg")],
+            total_size: 40 << 20,
+            wasted_size: 20 << 20,
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+
+        assert!(GroupQuery::parse("").is_empty());
+        assert!(GroupQuery::parse("beach").matches(&group));
+        assert!(GroupQuery::parse("BACKUP beach").matches(&group));
+        assert!(!GroupQuery::parse("beach mountain").matches(&group));
+        assert!(GroupQuery::parse("size>10MB").matches(&group));
+        assert!(GroupQuery::parse("size<=20mb photos").matches(&group));
+        assert!(!GroupQuery::parse("size>1.5GB").matches(&group));
+        // Not a valid size, so it's searched for as text
+        assert!(!GroupQuery::parse("size>lots").matches(&group));
+    }
+}
@@ -0,0 +1,190 @@
+//! Folders module - Whole directories whose contents are duplicated
+//!
+//! A folder's signature is the sorted multiset of the hashes of every file
+//! beneath it. Two folders can only share a signature if each of their files
+//! has a copy elsewhere, so they are found from the exact duplicate groups
+//! plus a count of the files collected in each directory. Files the scan
+//! filtered out are not part of a folder's signature, which is why deleting
+//! a folder checks it on disk again first.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::scanner::{DuplicateGroup, GroupKind};
+
+/// Folders with identical contents, subfolders included
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateFolderGroup {
+    pub folders: Vec<PathBuf>,
+    /// Files in each copy
+    pub file_count: usize,
+    /// Size of each copy
+    pub folder_size: u64,
+}
+
+impl DuplicateFolderGroup {
+    /// Space taken by every copy but the first
+    pub fn wasted_size(&self) -> u64 {
+        self.folder_size * self.folders.len().saturating_sub(1) as u64
+    }
+}
+
+/// Every exactly duplicated file beneath `folder`, which is all a duplicate
+/// folder should still hold when it's deleted
+pub fn matched_files(groups: &[DuplicateGroup], folder: &Path) -> HashSet<PathBuf> {
+    groups
+        .iter()
+        .filter(|g| g.kind == GroupKind::Exact)
+        .flat_map(|g| &g.files)
+        .filter(|f| f.path.starts_with(folder))
+        .map(|f| f.path.clone())
+        .collect()
+}
+
+/// Find folders under `roots` holding the same files as another folder.
+///
+/// `file_counts` is the number of files collected directly in each
+/// directory. Only the topmost folders of matching trees are reported.
+pub fn find_duplicate_folders(
+    groups: &[DuplicateGroup],
+    file_counts: &HashMap<PathBuf, usize>,
+    roots: &[PathBuf],
+) -> Vec<DuplicateFolderGroup> {
+    // Hash and size of each duplicated file, by the directory it's directly in
+    let mut direct: BTreeMap<&Path, Vec<(&str, u64)>> = BTreeMap::new();
+    for group in groups.iter().filter(|g| g.kind == GroupKind::Exact) {
+        for file in &group.files {
+            if let Some(dir) = file.path.parent() {
+                direct
+                    .entry(dir)
+                    .or_default()
+                    .push((group.hash.as_str(), file.size));
+            }
+        }
+    }
+
+    // A folder can match only if every file beneath it is duplicated
+    let mut complete: HashMap<&Path, bool> = HashMap::new();
+    for (dir, &count) in file_counts {
+        let Some(root) = roots.iter().find(|root| dir.starts_with(root)) else {
+            continue;
+        };
+        let duplicated = direct.get(dir.as_path()).map_or(0, Vec::len) == count;
+        for folder in dir
+            .ancestors()
+            .take_while(|folder| folder.starts_with(root))
+        {
+            *complete.entry(folder).or_insert(true) &= duplicated;
+        }
+    }
+
+    let mut by_signature: HashMap<Vec<&str>, (u64, Vec<&Path>)> = HashMap::new();
+    for (&folder, _) in complete.iter().filter(|(_, &complete)| complete) {
+        let mut hashes = Vec::new();
+        let mut size = 0;
+        // Paths order by component, so a folder's subfolders follow it directly
+        for (_, files) in direct
+            .range(folder..)
+            .take_while(|(dir, _)| dir.starts_with(folder))
+        {
+            hashes.extend(files.iter().map(|&(hash, _)| hash));
+            size += files.iter().map(|&(_, size)| size).sum::<u64>();
+        }
+        hashes.sort_unstable();
+        by_signature
+            .entry(hashes)
+            .or_insert_with(|| (size, Vec::new()))
+            .1
+            .push(folder);
+    }
+
+    let mut found = Vec::new();
+    for (hashes, (folder_size, mut folders)) in by_signature {
+        // A folder holding nothing but one subfolder matches that subfolder
+        folders.sort();
+        folders.dedup_by(|inner, outer| inner.starts_with(outer));
+        if folders.len() > 1 {
+            found.push(DuplicateFolderGroup {
+                folders: folders.into_iter().map(Path::to_path_buf).collect(),
+                file_count: hashes.len(),
+                folder_size,
+            });
+        }
+    }
+
+    // Matching subfolders of matching folders add nothing
+    let matched: HashSet<PathBuf> = found.iter().flat_map(|g| g.folders.clone()).collect();
+    found.retain(|group| {
+        !group.folders.iter().all(|folder| {
+            folder
+                .parent()
+                .is_some_and(|parent| matched.contains(parent))
+        })
+    });
+    found.sort_by(|a, b| {
+        b.wasted_size()
+            .cmp(&a.wasted_size())
+            .then_with(|| a.folders.cmp(&b.folders))
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{FileEntry, MatchConfidence};
+
+    #[test]
+    fn test_finds_topmost_matching_folders() {
+        let root = PathBuf::from("/scan");
+        let group = |hash: &str, paths: &[&str]| DuplicateGroup {
+            hash: hash.into(),
+            files: paths
+                .iter()
+                .map(|p| FileEntry::new(root.join(p), String::new(), 10))
+                .collect(),
+            total_size: 10 * paths.len() as u64,
+            wasted_size: 10 * (paths.len() as u64 - 1),
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+        let groups = [
+            group("a", &["one/a.txt", "two/a.txt", "other/a.txt"]),
+            group("b", &["one/sub/b.txt", "two/sub/b.txt"]),
+            group("c", &["wrapped/inner/c.txt", "loose/c.txt"]),
+        ];
+        let counts: HashMap<PathBuf, usize> = [
+            ("one", 1),
+            ("one/sub", 1),
+            ("two", 1),
+            ("two/sub", 1),
+            // Holds a file with no copy, so it can't match `one` or `two`
+            ("other", 2),
+            ("wrapped/inner", 1),
+            ("loose", 1),
+        ]
+        .into_iter()
+        .map(|(dir, count)| (root.join(dir), count))
+        .collect();
+
+        let found = find_duplicate_folders(&groups, &counts, std::slice::from_ref(&root));
+        assert_eq!(
+            found,
+            [
+                DuplicateFolderGroup {
+                    folders: vec![root.join("one"), root.join("two")],
+                    file_count: 2,
+                    folder_size: 20,
+                },
+                // `wrapped` only holds `inner`, so it's reported once
+                DuplicateFolderGroup {
+                    folders: vec![root.join("loose"), root.join("wrapped")],
+                    file_count: 1,
+                    folder_size: 10,
+                },
+            ]
+        );
+    }
+}
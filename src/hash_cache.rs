@@ -0,0 +1,290 @@
+//! Hash cache module - Remembering file hashes between scans
+//!
+//! Hashes are keyed by volume and the path within it, and only reused while
+//! the file's size and modification time are unchanged. The cache is capped; when it grows past
+//! the cap, the least recently used entries are dropped.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scanner::HashAlgorithm;
+use crate::volume::VolumePath;
+
+/// Entries kept by default, enough for a large photo library
+pub const DEFAULT_CAPACITY: usize = 500_000;
+
+/// Bumped whenever the on-disk layout changes; older files are ignored
+const FORMAT_VERSION: u32 = 2;
+
+/// Where a cached file lives. Files on a volume that could be identified are
+/// keyed by its GUID, so a drive mounted under another letter keeps its hashes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum CacheKey {
+    Volume { guid: String, relative: PathBuf },
+    Path(PathBuf),
+}
+
+impl From<VolumePath> for CacheKey {
+    fn from(path: VolumePath) -> Self {
+        match (path.volume_guid, path.relative) {
+            (Some(guid), Some(relative)) => Self::Volume { guid, relative },
+            _ => Self::Path(path.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified: SystemTime,
+    algorithm: HashAlgorithm,
+    hash: String,
+    /// Value of the cache clock when this entry was last read or written
+    last_used: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    clock: u64,
+    entries: HashMap<CacheKey, CacheEntry>,
+}
+
+/// Hashes of files seen in earlier scans
+#[derive(Debug)]
+pub struct HashCache {
+    /// Where the cache is saved, or `None` for a cache that only lives in memory
+    path: Option<PathBuf>,
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Volume of each folder looked up so far, so it's queried once per folder
+    folders: HashMap<PathBuf, VolumePath>,
+    dirty: bool,
+}
+
+impl HashCache {
+    /// Empty cache that is never written to disk
+    pub fn in_memory(capacity: usize) -> Self {
+        Self {
+            path: None,
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+            folders: HashMap::new(),
+            dirty: false,
+        }
+    }
+
+    /// Load the cache at `path`, starting empty if it's missing or unreadable
+    pub fn open(path: PathBuf, capacity: usize) -> Self {
+        let file = fs::read(&path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<CacheFile>(&bytes).ok())
+            .filter(|file| file.version == FORMAT_VERSION)
+            .unwrap_or_default();
+        let mut cache = Self {
+            path: Some(path),
+            capacity,
+            clock: file.clock,
+            entries: file.entries,
+            folders: HashMap::new(),
+            dirty: false,
+        };
+        cache.evict();
+        cache
+    }
+
+    /// Default location of the cache file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("FileXSorter").join("hash_cache.bin"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The cached hash of `path`, if the file is unchanged since it was hashed
+    pub fn get(
+        &mut self,
+        path: &Path,
+        size: u64,
+        modified: SystemTime,
+        algorithm: HashAlgorithm,
+    ) -> Option<String> {
+        let key = self.key(path);
+        let entry = self.entries.get_mut(&key)?;
+        if entry.size != size || entry.modified != modified {
+            // The file changed, so the hash can never be used again
+            self.entries.remove(&key);
+            self.dirty = true;
+            return None;
+        }
+        if entry.algorithm != algorithm {
+            return None;
+        }
+        self.clock += 1;
+        entry.last_used = self.clock;
+        self.dirty = true;
+        Some(entry.hash.clone())
+    }
+
+    /// Remember the hash of `path` as it is now
+    pub fn insert(
+        &mut self,
+        path: PathBuf,
+        size: u64,
+        modified: SystemTime,
+        algorithm: HashAlgorithm,
+        hash: String,
+    ) {
+        self.clock += 1;
+        let key = self.key(&path);
+        self.entries.insert(
+            key,
+            CacheEntry {
+                size,
+                modified,
+                algorithm,
+                hash,
+                last_used: self.clock,
+            },
+        );
+        self.dirty = true;
+        self.evict();
+    }
+
+    /// Key for `path`, built from its folder's volume
+    fn key(&mut self, path: &Path) -> CacheKey {
+        let (Some(folder), Some(name)) = (path.parent(), path.file_name()) else {
+            return CacheKey::Path(path.to_path_buf());
+        };
+        let folder = self
+            .folders
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| VolumePath::from_path(folder));
+        CacheKey::from(VolumePath {
+            path: path.to_path_buf(),
+            volume_guid: folder.volume_guid.clone(),
+            relative: folder.relative.as_ref().map(|relative| relative.join(name)),
+        })
+    }
+
+    /// Write the cache to its file if anything changed since it was loaded
+    pub fn save(&mut self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        let file = CacheFile {
+            version: FORMAT_VERSION,
+            clock: self.clock,
+            entries: std::mem::take(&mut self.entries),
+        };
+        let encoded = bincode::serialize(&file);
+        self.entries = file.entries;
+        let bytes = encoded.map_err(|e| format!("Failed to encode: {}", e))?;
+        // Write then rename so a crash mid-write doesn't leave a truncated file
+        let tmp = path.with_extension("bin.tmp");
+        fs::write(&tmp, bytes).map_err(|e| format!("Failed to write: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to save: {}", e))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Drop the least recently used entries once over capacity.
+    ///
+    /// Trims to 90% of the cap so a full cache isn't sorted on every insert.
+    fn evict(&mut self) {
+        if self.entries.len() <= self.capacity {
+            return;
+        }
+        let keep = self.capacity * 9 / 10;
+        let mut ages: Vec<u64> = self.entries.values().map(|e| e.last_used).collect();
+        let drop = ages.len() - keep;
+        let (_, &mut cutoff, _) = ages.select_nth_unstable(drop - 1);
+        self.entries.retain(|_, e| e.last_used > cutoff);
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_entries_invalidate_and_evict() {
+        let algorithm = HashAlgorithm::Blake3;
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut cache = HashCache::in_memory(10);
+        cache.insert("a".into(), 5, t0, algorithm, "ha".into());
+
+        assert_eq!(
+            cache.get(Path::new("a"), 5, t0, algorithm),
+            Some("ha".into())
+        );
+        assert_eq!(
+            cache.get(Path::new("a"), 5, t0, HashAlgorithm::Sha256),
+            None
+        );
+        // A changed size or timestamp drops the entry for good
+        assert_eq!(cache.get(Path::new("a"), 6, t0, algorithm), None);
+        assert_eq!(cache.get(Path::new("a"), 5, t0, algorithm), None);
+
+        for i in 0..11 {
+            cache.insert(format!("f{}", i).into(), 1, t0, algorithm, i.to_string());
+        }
+        assert_eq!(cache.len(), 9);
+        assert_eq!(cache.get(Path::new("f0"), 1, t0, algorithm), None);
+        assert_eq!(
+            cache.get(Path::new("f10"), 1, t0, algorithm),
+            Some("10".into())
+        );
+    }
+
+    #[test]
+    fn test_remounted_volume_keeps_its_key() {
+        let on = |path: &str| VolumePath {
+            path: path.into(),
+            volume_guid: Some(r"\\?\Volume{1234}\".into()),
+            relative: Some(r"photos\a.jpg".into()),
+        };
+        assert_eq!(
+            CacheKey::from(on(r"D:\photos\a.jpg")),
+            CacheKey::from(on(r"E:\photos\a.jpg"))
+        );
+    }
+
+    #[test]
+    fn test_save_and_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("hash_cache.bin");
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        let mut cache = HashCache::open(path.clone(), 10);
+        assert!(cache.is_empty());
+        cache.insert("a".into(), 5, t0, HashAlgorithm::Blake3, "ha".into());
+        cache.save().unwrap();
+
+        let mut reopened = HashCache::open(path, 10);
+        assert_eq!(
+            reopened.get(Path::new("a"), 5, t0, HashAlgorithm::Blake3),
+            Some("ha".into())
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Highlight module - Syntax colouring for source file previews
+//!
+//! Grammars and themes are the ones bundled with syntect. Extensions it has
+//! no grammar for, and plain text, are left for the caller to show as is.
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Theme used on a dark background
+const DARK_THEME: &str = "base16-ocean.dark";
+
+/// Theme used on a light background
+const LIGHT_THEME: &str = "InspiredGitHub";
+
+/// A run of text drawn in one style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub color: [u8; 3],
+    pub italic: bool,
+}
+
+/// Bundled grammars and themes; loading them takes a moment, so keep one around
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    themes: ThemeSet,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            themes: ThemeSet::load_defaults(),
+        }
+    }
+}
+
+impl Highlighter {
+    /// Colour `text` by the grammar for `extension`, or `None` if there's no
+    /// grammar for it or it's plain text
+    pub fn highlight(&self, text: &str, extension: &str, dark: bool) -> Option<Vec<Span>> {
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_extension(extension)
+            .filter(|syntax| syntax.name != "Plain Text")?;
+        let theme = &self.themes.themes[if dark { DARK_THEME } else { LIGHT_THEME }];
+
+        let mut lines = HighlightLines::new(syntax, theme);
+        let mut spans: Vec<Span> = Vec::new();
+        for line in LinesWithEndings::from(text) {
+            for (style, piece) in lines.highlight_line(line, &self.syntaxes).ok()? {
+                let color = [style.foreground.r, style.foreground.g, style.foreground.b];
+                let italic = style.font_style.contains(FontStyle::ITALIC);
+                // Neighbouring pieces often share a style, so merge them
+                match spans.last_mut() {
+                    Some(last) if last.color == color && last.italic == italic => {
+                        last.text.push_str(piece);
+                    }
+                    _ => spans.push(Span {
+                        text: piece.to_string(),
+                        color,
+                        italic,
+                    }),
+                }
+            }
+        }
+        Some(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_is_coloured_and_plain_text_is_not() {
+        let highlighter = Highlighter::default();
+        let source = "fn main() {\n    let x = \"hi\";\n}\n";
+
+        let spans = highlighter.highlight(source, "rs", true).unwrap();
+        let text: String = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(text, source);
+        let keyword = spans.iter().find(|s| s.text.contains("fn")).unwrap();
+        let string = spans.iter().find(|s| s.text.contains("hi")).unwrap();
+        assert_ne!(keyword.color, string.color);
+
+        assert!(highlighter.highlight(source, "txt", true).is_none());
+        assert!(highlighter.highlight(source, "unknown", false).is_none());
+    }
+}
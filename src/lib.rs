@@ -0,0 +1,20 @@
+//! File X Sorter core library
+//!
+//! Duplicate detection and file operations used by the GUI, exposed
+//! separately so they can be driven without the interface.
+
+pub mod audio;
+pub mod export;
+pub mod file_ops;
+pub mod filter;
+pub mod folders;
+pub mod hash_cache;
+pub mod highlight;
+pub mod matcher;
+pub mod media;
+pub mod paths;
+pub mod scanner;
+pub mod session;
+pub mod similarity;
+pub mod volume;
+pub mod watcher;
@@ -6,21 +6,42 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app;
-mod file_ops;
-mod scanner;
+mod cli;
+
+use std::ffi::OsString;
+use std::process::ExitCode;
 
 use app::FileXSorterApp;
 
-fn main() -> eframe::Result<()> {
+fn main() -> ExitCode {
     // Initialize logging in debug mode
     #[cfg(debug_assertions)]
     env_logger::init();
 
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    match cli::parse_args(&args) {
+        Ok(cli::Mode::Scan(cli_args)) => cli::run(&cli_args),
+        Ok(cli::Mode::Help) => cli::print_help(),
+        Ok(cli::Mode::Gui) => match run_gui() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                log::error!("Failed to start GUI: {}", e);
+                ExitCode::from(cli::EXIT_GUI_FAILED)
+            }
+        },
+        Err(message) => cli::report_error(&message),
+    }
+}
+
+fn run_gui() -> eframe::Result<()> {
     let options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
             .with_inner_size([900.0, 600.0])
             .with_min_inner_size([600.0, 400.0])
+            .with_drag_and_drop(true)
             .with_title("File X Sorter"),
+        // Reopen at the last window size and position instead of the default above
+        persist_window: true,
         ..Default::default()
     };
 
@@ -0,0 +1,730 @@
+//! Matcher module - Pluggable logic deciding which candidate files are duplicates
+//!
+//! The scanner collects files and narrows them down to candidates that share
+//! a size; a `DuplicateMatcher` then gives each candidate a match key, and
+//! files with the same key are grouped. `HashMatcher` is the default;
+//! `PerceptualMatcher` keys images by visual similarity instead.
+//!
+//! `HashMatcher` narrows candidates in stages so that the slowest hash reads
+//! as little as possible: a hash of the first 4 KB, then, ahead of SHA-256, a
+//! fast XXH3 hash of the whole file, and only then the configured hash.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::hash_cache::HashCache;
+use crate::scanner::{
+    compute_file_hash_with, compute_quick_hash, is_lock_error, CurrentFile, FileEntry,
+    HashAlgorithm, MatchConfidence, QUICK_HASH_SIZE,
+};
+use crate::similarity;
+use crate::volume::{drive_kind, DriveKind};
+
+const MAX_PARALLEL_THREADS: usize = 8;
+
+/// Threads reading files on a network or removable drive, which more
+/// readers only slow down or time out
+const SLOW_DRIVE_THREADS: usize = 2;
+
+/// Wait before trying a file another process has locked a second time
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Scan state a matcher should honor while it works
+pub struct MatchContext<'a> {
+    /// Incremented once per processed candidate
+    pub progress_current: &'a AtomicUsize,
+    /// Size of every file read in full, for throughput reporting
+    pub bytes_hashed: &'a AtomicU64,
+    /// File a worker most recently started on, for display
+    pub current_file: &'a CurrentFile,
+    pub cancel_flag: &'a AtomicBool,
+    /// Stop starting new work after this instant
+    pub deadline: Option<Instant>,
+    /// Set when work was skipped because of `deadline`
+    pub time_limit_reached: &'a AtomicBool,
+    /// Thread pools files are read on, shared by every batch of a scan
+    pub pools: &'a ReadPools,
+}
+
+impl MatchContext<'_> {
+    /// Whether the matcher should stop before processing another file
+    pub fn should_stop(&self) -> bool {
+        if self.cancel_flag.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.time_limit_reached.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+}
+
+/// Candidates after matching
+#[derive(Debug, Default)]
+pub struct MatchOutput {
+    /// Files with their match key in `hash`; equal keys form a group
+    pub matched: Vec<FileEntry>,
+    /// Files that couldn't be processed, with the reason
+    pub failed: Vec<(FileEntry, String)>,
+    /// Files another process kept open or locked
+    pub locked: Vec<FileEntry>,
+}
+
+impl MatchOutput {
+    /// Record a file that couldn't be read, as locked or failed
+    fn add_failure(&mut self, file: FileEntry, error: std::io::Error) {
+        if is_lock_error(&error) {
+            self.locked.push(file);
+        } else {
+            self.failed.push((file, error.to_string()));
+        }
+    }
+}
+
+/// Decides which candidate files are duplicates of each other
+pub trait DuplicateMatcher: Send + Sync {
+    /// Give each candidate a match key. Candidates skipped because of
+    /// cancellation or the deadline are simply left out.
+    fn assign_keys(
+        &self,
+        candidates: Vec<FileEntry>,
+        ctx: &MatchContext,
+    ) -> Result<MatchOutput, String>;
+
+    /// How certain this matcher's groups are
+    fn confidence(&self) -> MatchConfidence {
+        MatchConfidence::Exact
+    }
+}
+
+/// Groups byte-identical files by a hash of their contents
+#[derive(Debug, Default, Clone)]
+pub struct HashMatcher {
+    /// Hash that confirms matches; with `Xxh3` the fast stage is final
+    pub algorithm: HashAlgorithm,
+    /// Memory-map files at least this large, see `ScannerConfig::mmap_threshold`
+    pub mmap_threshold: Option<u64>,
+    /// Hashes from earlier scans, reused for unchanged files and updated with new ones
+    pub cache: Option<Arc<Mutex<HashCache>>>,
+}
+
+impl DuplicateMatcher for HashMatcher {
+    fn assign_keys(
+        &self,
+        candidates: Vec<FileEntry>,
+        ctx: &MatchContext,
+    ) -> Result<MatchOutput, String> {
+        let algorithm = self.algorithm;
+        let mmap_threshold = self.mmap_threshold;
+
+        let (cached, candidates) = self.take_cached(candidates);
+        // A cached peer still needs comparing, so its size bucket can't be quick-filtered
+        let cached_sizes: HashSet<u64> = cached.iter().map(|f| f.size).collect();
+        let (mut to_hash, unfiltered): (Vec<FileEntry>, Vec<FileEntry>) = candidates
+            .into_iter()
+            .partition(|f| cached_sizes.contains(&f.size));
+        ctx.progress_current
+            .fetch_add(cached.len(), Ordering::Relaxed);
+
+        let mut output = MatchOutput::default();
+        let filtered = self.quick_filter(unfiltered, ctx);
+        let (unread, read) = self.fast_filter(filtered, ctx, &mut output);
+        to_hash.extend(unread);
+        let strong_hash = |path: &Path| {
+            let hash = compute_file_hash_with(path, algorithm, mmap_threshold);
+            if hash.is_ok() {
+                ctx.progress_current.fetch_add(1, Ordering::Relaxed);
+            }
+            hash
+        };
+        // Files the fast stage read in full are already in `bytes_hashed`
+        let mut results = hash_files(to_hash, ctx, true, strong_hash);
+        results.extend(hash_files(read, ctx, false, strong_hash));
+        for result in results {
+            match result {
+                Ok(file) => output.matched.push(file),
+                Err((file, e)) => output.add_failure(file, e),
+            }
+        }
+        self.remember(&output.matched);
+        output.matched.extend(cached);
+        Ok(output)
+    }
+}
+
+impl HashMatcher {
+    /// Split off candidates whose hash is already cached, with `hash` filled in
+    fn take_cached(&self, candidates: Vec<FileEntry>) -> (Vec<FileEntry>, Vec<FileEntry>) {
+        let Some(Ok(mut cache)) = self.cache.as_ref().map(|c| c.lock()) else {
+            return (Vec::new(), candidates);
+        };
+        let mut cached = Vec::new();
+        let mut uncached = Vec::new();
+        for mut file in candidates {
+            let hash = file
+                .modified
+                .and_then(|modified| cache.get(&file.path, file.size, modified, self.algorithm));
+            match hash {
+                Some(hash) => {
+                    file.hash = Some(hash);
+                    cached.push(file);
+                }
+                None => uncached.push(file),
+            }
+        }
+        (cached, uncached)
+    }
+
+    /// Add freshly hashed files to the cache
+    fn remember(&self, files: &[FileEntry]) {
+        let Some(Ok(mut cache)) = self.cache.as_ref().map(|c| c.lock()) else {
+            return;
+        };
+        for file in files {
+            if let (Some(modified), Some(hash)) = (file.modified, &file.hash) {
+                cache.insert(
+                    file.path.clone(),
+                    file.size,
+                    modified,
+                    self.algorithm,
+                    hash.clone(),
+                );
+            }
+        }
+    }
+
+    /// Drop candidates whose first bytes differ from every other candidate of the same size.
+    ///
+    /// Files no bigger than the quick hash would be read in full anyway, so they
+    /// go straight to full hashing, as do sparse files.
+    fn quick_filter(&self, candidates: Vec<FileEntry>, ctx: &MatchContext) -> Vec<FileEntry> {
+        let (mut keep, large): (Vec<FileEntry>, Vec<FileEntry>) = candidates
+            .into_iter()
+            .partition(|f| f.size <= QUICK_HASH_SIZE || f.is_sparse);
+
+        let quick: Vec<(FileEntry, Option<String>)> = ctx.pools.map(large, |file| {
+            if ctx.should_stop() {
+                return None;
+            }
+            let hash = compute_quick_hash(&file.path).ok();
+            Some((file, hash))
+        });
+
+        let mut by_size: HashMap<u64, Vec<(FileEntry, Option<String>)>> = HashMap::new();
+        for (file, hash) in quick {
+            by_size.entry(file.size).or_default().push((file, hash));
+        }
+        for files in by_size.into_values() {
+            // A file that couldn't be read could still match any of its size peers
+            if files.iter().any(|(_, hash)| hash.is_none()) {
+                keep.extend(files.into_iter().map(|(file, _)| file));
+                continue;
+            }
+            let mut by_quick: HashMap<String, Vec<FileEntry>> = HashMap::new();
+            for (file, hash) in files {
+                by_quick
+                    .entry(hash.unwrap_or_default())
+                    .or_default()
+                    .push(file);
+            }
+            for group in by_quick.into_values() {
+                if group.len() > 1 {
+                    keep.extend(group);
+                } else {
+                    // Ruled out without a full hash, but still counts as done
+                    ctx.progress_current.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        keep
+    }
+
+    /// Drop candidates whose XXH3 hash matches no other candidate of the same size.
+    ///
+    /// Only worth it ahead of SHA-256, and only for files larger than the
+    /// quick hash, which smaller ones have already been compared by in full.
+    /// Files that do match are read twice, which Blake3 is too fast to make
+    /// up for. Returns the candidates left unread, then those kept after a
+    /// full read.
+    fn fast_filter(
+        &self,
+        candidates: Vec<FileEntry>,
+        ctx: &MatchContext,
+        output: &mut MatchOutput,
+    ) -> (Vec<FileEntry>, Vec<FileEntry>) {
+        if self.algorithm != HashAlgorithm::Sha256 {
+            return (candidates, Vec::new());
+        }
+        let (unread, large): (Vec<FileEntry>, Vec<FileEntry>) = candidates
+            .into_iter()
+            .partition(|f| f.size <= QUICK_HASH_SIZE);
+
+        let mmap_threshold = self.mmap_threshold;
+        let mut keep = Vec::new();
+        let mut by_hash: HashMap<(u64, String), Vec<FileEntry>> = HashMap::new();
+        for result in hash_files(large, ctx, true, |path| {
+            compute_file_hash_with(path, HashAlgorithm::Xxh3, mmap_threshold)
+        }) {
+            match result {
+                Ok(mut file) => {
+                    let hash = file.hash.take().unwrap_or_default();
+                    by_hash.entry((file.size, hash)).or_default().push(file);
+                }
+                Err((file, e)) => output.add_failure(file, e),
+            }
+        }
+        for group in by_hash.into_values() {
+            if group.len() > 1 {
+                keep.extend(group);
+            } else {
+                ctx.progress_current.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        (unread, keep)
+    }
+}
+
+/// Hash `files` in parallel, giving a locked file one more try, and add
+/// their sizes to `bytes_hashed` when `count_bytes` is set.
+///
+/// Files skipped because of cancellation or the deadline are left out.
+fn hash_files<F>(
+    files: Vec<FileEntry>,
+    ctx: &MatchContext,
+    count_bytes: bool,
+    hash: F,
+) -> Vec<Result<FileEntry, (FileEntry, std::io::Error)>>
+where
+    F: Fn(&Path) -> std::io::Result<String> + Sync,
+{
+    ctx.pools.map(files, |mut file| {
+        if ctx.should_stop() {
+            return None;
+        }
+
+        ctx.current_file.set(&file.path);
+        // Locks are often brief, e.g. an antivirus scan or a sync client
+        let hashed = match hash(&file.path) {
+            Err(e) if is_lock_error(&e) => {
+                std::thread::sleep(LOCK_RETRY_DELAY);
+                hash(&file.path)
+            }
+            hashed => hashed,
+        };
+        Some(match hashed {
+            Ok(hash) => {
+                file.hash = Some(hash);
+                if count_bytes {
+                    ctx.bytes_hashed.fetch_add(file.size, Ordering::Relaxed);
+                }
+                Ok(file)
+            }
+            Err(e) => Err((file, e)),
+        })
+    })
+}
+
+/// Groups images whose perceptual hashes are within `threshold` bits of each other.
+///
+/// Every image lands in a set keyed by the hash of its first member, so keys
+/// are only shared by similar images. `perceptual_hash` is filled in on each.
+#[derive(Debug, Clone)]
+pub struct PerceptualMatcher {
+    pub threshold: u32,
+}
+
+impl DuplicateMatcher for PerceptualMatcher {
+    fn assign_keys(
+        &self,
+        candidates: Vec<FileEntry>,
+        ctx: &MatchContext,
+    ) -> Result<MatchOutput, String> {
+        let results: Vec<Result<FileEntry, (FileEntry, String)>> =
+            ctx.pools.map(candidates, |mut file| {
+                if ctx.should_stop() {
+                    return None;
+                }
+                ctx.current_file.set(&file.path);
+                let hash = similarity::difference_hash(&file.path);
+                ctx.progress_current.fetch_add(1, Ordering::Relaxed);
+                Some(match hash {
+                    Ok(hash) => {
+                        file.perceptual_hash = Some(hash);
+                        ctx.bytes_hashed.fetch_add(file.size, Ordering::Relaxed);
+                        Ok(file)
+                    }
+                    Err(e) => Err((file, e)),
+                })
+            });
+
+        let mut output = MatchOutput::default();
+        for result in results {
+            match result {
+                Ok(file) => output.matched.push(file),
+                Err(failed) => output.failed.push(failed),
+            }
+        }
+        let hashes: Vec<u64> = output
+            .matched
+            .iter()
+            .map(|f| f.perceptual_hash.unwrap_or_default())
+            .collect();
+        for set in similarity::cluster(&hashes, self.threshold) {
+            let key = format!("similar:{:016x}", hashes[set[0]]);
+            for idx in set {
+                output.matched[idx].hash = Some(key.clone());
+            }
+        }
+        Ok(output)
+    }
+
+    fn confidence(&self) -> MatchConfidence {
+        MatchConfidence::Approximate
+    }
+}
+
+/// Bounded pools for reading files, so large scans don't swamp the disk or
+/// memory. Files on network and removable drives get a smaller pool of their
+/// own, which local files don't have to wait on.
+pub struct ReadPools {
+    local: rayon::ThreadPool,
+    slow: rayon::ThreadPool,
+    /// Whether each folder seen so far is on a network or removable drive
+    slow_folders: Mutex<HashMap<PathBuf, bool>>,
+}
+
+impl ReadPools {
+    /// Start the pools, with threads below normal priority if `low_priority`
+    /// is set (Windows only)
+    pub fn new(low_priority: bool) -> Result<Self, String> {
+        Ok(Self {
+            local: read_pool("fxs-hash", MAX_PARALLEL_THREADS, low_priority)?,
+            slow: read_pool("fxs-slow", SLOW_DRIVE_THREADS, low_priority)?,
+            slow_folders: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Run `f` over `files` in parallel, each on the pool for its drive,
+    /// keeping the results of those it returns `Some` for in input order
+    fn map<T, F>(&self, files: Vec<FileEntry>, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(FileEntry) -> Option<T> + Sync,
+    {
+        let (slow, local): (Vec<_>, Vec<_>) = {
+            let mut slow_folders = self
+                .slow_folders
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            // Checked per folder, since a local folder can hold a network mount
+            files.into_iter().enumerate().partition(|(_, file)| {
+                let folder = file.path.parent().unwrap_or(&file.path);
+                *slow_folders
+                    .entry(folder.to_path_buf())
+                    .or_insert_with(|| drive_kind(folder) != DriveKind::Local)
+            })
+        };
+        let run = |files: Vec<(usize, FileEntry)>| -> Vec<(usize, T)> {
+            files
+                .into_par_iter()
+                .filter_map(|(i, file)| Some((i, f(file)?)))
+                .collect()
+        };
+        let (mut results, slow_results) = self
+            .local
+            .install(|| rayon::join(|| run(local), || self.slow.install(|| run(slow))));
+        if !slow_results.is_empty() {
+            results.extend(slow_results);
+            results.sort_unstable_by_key(|(i, _)| *i);
+        }
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+fn read_pool(
+    name: &'static str,
+    threads: usize,
+    low_priority: bool,
+) -> Result<rayon::ThreadPool, String> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |i| format!("{}-{}", name, i))
+        .start_handler(move |_| {
+            if low_priority {
+                lower_current_thread_priority();
+            }
+        })
+        .build()
+        .map_err(|e| format!("Failed to create hashing thread pool: {}", e))
+}
+
+/// Drop the calling thread below normal priority so foreground apps stay responsive
+#[cfg(windows)]
+fn lower_current_thread_priority() {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL,
+    };
+
+    // SAFETY: GetCurrentThread returns a pseudo-handle that's always valid for this thread
+    unsafe {
+        SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL);
+    }
+}
+
+#[cfg(not(windows))]
+fn lower_current_thread_priority() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{Scanner, ScannerConfig};
+    use std::fs;
+
+    /// Treats same-sized files with the same name as duplicates
+    struct NameMatcher;
+
+    impl DuplicateMatcher for NameMatcher {
+        fn assign_keys(
+            &self,
+            candidates: Vec<FileEntry>,
+            _ctx: &MatchContext,
+        ) -> Result<MatchOutput, String> {
+            let matched = candidates
+                .into_iter()
+                .map(|mut file| {
+                    file.hash = Some(file.name.to_lowercase());
+                    file
+                })
+                .collect();
+            Ok(MatchOutput {
+                matched,
+                ..Default::default()
+            })
+        }
+
+        fn confidence(&self) -> MatchConfidence {
+            MatchConfidence::Approximate
+        }
+    }
+
+    #[test]
+    fn test_quick_hash_rules_out_early_differences() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let big = QUICK_HASH_SIZE as usize * 2;
+        let write = |name: &str, first: u8, last: u8| {
+            let mut data = vec![first; big];
+            data[big - 1] = last;
+            fs::write(dir.join(name), &data).unwrap();
+            FileEntry::new(dir.join(name), name.into(), big as u64)
+        };
+        let candidates = vec![
+            write("same_a", 1, 0),
+            write("same_b", 1, 0),
+            // Same first bytes as the pair, so it needs a full hash to rule out
+            write("late_diff", 1, 9),
+            write("early_diff", 2, 0),
+            FileEntry::new(dir.join("small"), "small".into(), 10),
+        ];
+
+        let (progress, cancel, timed_out) = (
+            AtomicUsize::new(0),
+            AtomicBool::new(false),
+            AtomicBool::new(false),
+        );
+        let ctx = MatchContext {
+            progress_current: &progress,
+            bytes_hashed: &AtomicU64::new(0),
+            current_file: &CurrentFile::default(),
+            cancel_flag: &cancel,
+            deadline: None,
+            time_limit_reached: &timed_out,
+            pools: &ReadPools::new(false).unwrap(),
+        };
+        let matcher = HashMatcher::default();
+        let mut kept: Vec<String> = matcher
+            .quick_filter(candidates, &ctx)
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        kept.sort();
+        assert_eq!(kept, ["late_diff", "same_a", "same_b", "small"]);
+        assert_eq!(progress.load(Ordering::Relaxed), 1);
+
+        let result = Scanner::new(ScannerConfig::default()).scan_directory(dir);
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.duplicate_groups[0].files.len(), 2);
+    }
+
+    #[test]
+    fn test_fast_hash_rules_out_late_differences() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let big = QUICK_HASH_SIZE as usize * 2;
+        let write = |name: &str, last: u8| {
+            let mut data = vec![1; big];
+            data[big - 1] = last;
+            fs::write(dir.join(name), &data).unwrap();
+            FileEntry::new(dir.join(name), name.into(), big as u64)
+        };
+        let candidates = vec![
+            write("same_a", 0),
+            write("same_b", 0),
+            write("late_diff", 9),
+        ];
+
+        let (progress, cancel, timed_out) = (
+            AtomicUsize::new(0),
+            AtomicBool::new(false),
+            AtomicBool::new(false),
+        );
+        let ctx = MatchContext {
+            progress_current: &progress,
+            bytes_hashed: &AtomicU64::new(0),
+            current_file: &CurrentFile::default(),
+            cancel_flag: &cancel,
+            deadline: None,
+            time_limit_reached: &timed_out,
+            pools: &ReadPools::new(false).unwrap(),
+        };
+        let mut output = MatchOutput::default();
+        let matcher = HashMatcher {
+            algorithm: HashAlgorithm::Sha256,
+            ..Default::default()
+        };
+        let (unread, kept) = matcher.fast_filter(candidates.clone(), &ctx, &mut output);
+        assert!(unread.is_empty());
+        let mut kept: Vec<String> = kept.into_iter().map(|f| f.name).collect();
+        kept.sort();
+        assert_eq!(kept, ["same_a", "same_b"]);
+        assert_eq!(progress.load(Ordering::Relaxed), 1);
+
+        // The matching pair is read by both hashes but only counted once
+        let bytes_hashed = AtomicU64::new(0);
+        let ctx = MatchContext {
+            bytes_hashed: &bytes_hashed,
+            ..ctx
+        };
+        matcher.assign_keys(candidates, &ctx).unwrap();
+        assert_eq!(bytes_hashed.load(Ordering::Relaxed), 3 * big as u64);
+
+        let fast_only = Scanner::new(ScannerConfig {
+            verify_strong: false,
+            ..Default::default()
+        })
+        .scan_directory(dir);
+        assert_eq!(fast_only.hash_algorithm, HashAlgorithm::Xxh3);
+        assert_eq!(fast_only.duplicate_groups.len(), 1);
+        assert_eq!(fast_only.duplicate_groups[0].hash.len(), 32);
+    }
+
+    #[test]
+    fn test_cached_hashes_skip_rehashing_unchanged_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a"), b"same").unwrap();
+        fs::write(dir.join("b"), b"same").unwrap();
+        let cache = Arc::new(Mutex::new(HashCache::in_memory(100)));
+        let scan = |cache: Option<&Arc<Mutex<HashCache>>>| {
+            let scanner = Scanner::new(ScannerConfig::default());
+            match cache {
+                Some(cache) => scanner.with_hash_cache(Arc::clone(cache)),
+                None => scanner,
+            }
+            .scan_directory(dir)
+        };
+        assert_eq!(scan(Some(&cache)).duplicate_groups.len(), 1);
+        assert_eq!(cache.lock().unwrap().len(), 2);
+
+        // Same size and timestamp, so the cache can't tell the content changed
+        let modified = fs::metadata(dir.join("b")).unwrap().modified().unwrap();
+        fs::write(dir.join("b"), b"diff").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(dir.join("b"))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let cached = scan(Some(&cache));
+        let fresh = scan(None);
+
+        assert_eq!(cached.duplicate_groups.len(), 1);
+        assert!(fresh.duplicate_groups.is_empty());
+    }
+
+    #[test]
+    fn test_custom_matcher_decides_groups() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("a").join("notes.txt"), b"one").unwrap();
+        fs::write(dir.join("b").join("NOTES.TXT"), b"two").unwrap();
+
+        let default = Scanner::new(ScannerConfig::default()).scan_directory(dir);
+        let custom = Scanner::new(ScannerConfig::default())
+            .with_matcher(Box::new(NameMatcher))
+            .scan_directory(dir);
+
+        assert!(default.duplicate_groups.is_empty());
+        assert_eq!(custom.duplicate_groups.len(), 1);
+        assert_eq!(
+            custom.duplicate_groups[0].confidence,
+            MatchConfidence::Approximate
+        );
+    }
+
+    #[test]
+    fn test_slow_drive_files_are_read_on_their_own_pool() {
+        let ctx = MatchContext {
+            progress_current: &AtomicUsize::new(0),
+            bytes_hashed: &AtomicU64::new(0),
+            current_file: &CurrentFile::default(),
+            cancel_flag: &AtomicBool::new(false),
+            deadline: None,
+            time_limit_reached: &AtomicBool::new(false),
+            pools: &ReadPools::new(false).unwrap(),
+        };
+        let pools = ctx.pools;
+        // Stand in for a network share mounted inside a local folder
+        pools
+            .slow_folders
+            .lock()
+            .unwrap()
+            .insert(PathBuf::from("/data/share"), true);
+        let files: Vec<FileEntry> = (0..40)
+            .map(|i| {
+                let folder = if i % 3 == 0 { "/data/share" } else { "/data" };
+                let path = PathBuf::from(format!("{}/{}", folder, i));
+                FileEntry::new(path, i.to_string(), 1)
+            })
+            .collect();
+
+        let read = pools.map(files, |file| {
+            let thread = std::thread::current()
+                .name()
+                .unwrap_or_default()
+                .to_string();
+            (file.name != "1").then_some((file, thread))
+        });
+        assert_eq!(read.len(), 39);
+        for (i, (file, thread)) in read.iter().enumerate() {
+            // Results keep their input order, less the one dropped
+            assert_eq!(file.name, (if i == 0 { 0 } else { i + 1 }).to_string());
+            if file.path.starts_with("/data/share") {
+                assert!(
+                    ["fxs-slow-0", "fxs-slow-1"].contains(&thread.as_str()),
+                    "{thread}"
+                );
+            } else {
+                assert!(thread.starts_with("fxs-hash-"), "{thread}");
+            }
+        }
+    }
+}
@@ -0,0 +1,515 @@
+//! Media module - Audio/video probing, GIF animation and image orientation
+//!
+//! Duration, codecs and tags are read in-process with symphonia. Video
+//! streams are beyond it, so their details and thumbnails come from
+//! `ffprobe`/`ffmpeg` when those are on the PATH; without them the video
+//! functions fall back to what symphonia found, or `None`. Playback lives
+//! in the `audio` module.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use image::codecs::gif::GifDecoder;
+use image::metadata::Orientation;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, ImageReader};
+use serde::Deserialize;
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+
+use crate::scanner::normalized_extension;
+
+/// Position of the representative frame, as a fraction of the duration
+const THUMBNAIL_POSITION: f64 = 0.1;
+
+/// Frames with a shorter delay are shown for 100 ms, as browsers do
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// Stream details and tags of an audio or video file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    pub duration_secs: Option<f64>,
+    /// Overall bitrate in bits per second
+    pub bit_rate: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// The parts of `ffprobe -of json` output we read
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    #[serde(default)]
+    disposition: HashMap<String, i64>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Read duration, bitrate, audio codec and tags of a media file in-process.
+///
+/// Video streams aren't recognised, see [`probe_video`].
+pub fn probe_media(path: &Path) -> Option<MediaInfo> {
+    let file = File::open(path).ok()?;
+    let file_size = file.metadata().ok()?.len();
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(&normalized_extension(path));
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    let mut info = MediaInfo::default();
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL);
+    if let Some(params) = track.map(|track| &track.codec_params) {
+        info.audio_codec = symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|codec| codec.short_name.to_string());
+        info.duration_secs = match (params.time_base, params.n_frames, params.sample_rate) {
+            (Some(base), Some(frames), _) => {
+                let time = base.calc_time(frames);
+                Some(time.seconds as f64 + time.frac)
+            }
+            (None, Some(frames), Some(rate)) => Some(frames as f64 / rate as f64),
+            _ => None,
+        };
+    }
+    // Containers don't reliably store it, so average over the whole file
+    info.bit_rate = info
+        .duration_secs
+        .filter(|&secs| secs > 0.0)
+        .map(|secs| (file_size as f64 * 8.0 / secs) as u64);
+
+    // Tags read while probing (e.g. ID3v2) come before the container's own
+    let mut revisions: Vec<MetadataRevision> = Vec::new();
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        revisions.push(revision.clone());
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        revisions.push(revision.clone());
+    }
+    let tag = |key: StandardTagKey| {
+        revisions.iter().flat_map(|r| r.tags()).find_map(|tag| {
+            // RIFF INFO strings keep their C terminator
+            let value = tag.value.to_string();
+            let value = value.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            (tag.std_key == Some(key) && !value.is_empty()).then(|| value.to_string())
+        })
+    };
+    info.title = tag(StandardTagKey::TrackTitle);
+    info.artist = tag(StandardTagKey::Artist);
+    info.album = tag(StandardTagKey::Album);
+    Some(info)
+}
+
+/// Like [`probe_media`], but with the video codec and frame size from
+/// `ffprobe`; without it, only what [`probe_media`] finds
+pub fn probe_video(path: &Path) -> Option<MediaInfo> {
+    ffprobe(path).or_else(|| probe_media(path))
+}
+
+/// Read duration, bitrate, codecs and tags of a media file via `ffprobe`
+fn ffprobe(path: &Path) -> Option<MediaInfo> {
+    let output = tool_command("ffprobe")
+        .args(["-v", "error", "-of", "json"])
+        .args([
+            "-show_entries",
+            "stream=codec_type,codec_name,width,height:stream_disposition=attached_pic:stream_tags:format=duration,bit_rate:format_tags",
+        ])
+        .arg(path)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    parse_probe_output(&output.stdout)
+}
+
+/// Turn `ffprobe` JSON into a [`MediaInfo`]
+fn parse_probe_output(json: &[u8]) -> Option<MediaInfo> {
+    let probe: ProbeOutput = serde_json::from_slice(json).ok()?;
+    let mut info = MediaInfo::default();
+
+    for stream in &probe.streams {
+        // Embedded cover art is reported as a one-frame video stream
+        if stream.disposition.get("attached_pic") == Some(&1) {
+            continue;
+        }
+        match stream.codec_type.as_deref() {
+            Some("video") if info.video_codec.is_none() => {
+                info.video_codec = stream.codec_name.clone();
+                info.width = stream.width;
+                info.height = stream.height;
+            }
+            Some("audio") if info.audio_codec.is_none() => {
+                info.audio_codec = stream.codec_name.clone();
+            }
+            _ => {}
+        }
+    }
+
+    // Tags are on the container for most formats but on the stream for Ogg
+    let tag_sources: Vec<&HashMap<String, String>> = probe
+        .format
+        .iter()
+        .map(|f| &f.tags)
+        .chain(probe.streams.iter().map(|s| &s.tags))
+        .collect();
+    let tag = |name: &str| {
+        tag_sources.iter().find_map(|tags| {
+            tags.iter()
+                .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                .map(|(_, value)| value.trim().to_string())
+        })
+    };
+    info.title = tag("title");
+    info.artist = tag("artist");
+    info.album = tag("album");
+
+    if let Some(format) = &probe.format {
+        info.duration_secs = format.duration.as_deref().and_then(|d| d.parse().ok());
+        info.bit_rate = format.bit_rate.as_deref().and_then(|b| b.parse().ok());
+    }
+    Some(info)
+}
+
+/// Extract a representative frame (10% into the video) via `ffmpeg`
+pub fn extract_video_frame(
+    path: &Path,
+    info: &MediaInfo,
+    max_width: u32,
+) -> Option<image::RgbaImage> {
+    let seek = info.duration_secs.unwrap_or(0.0) * THUMBNAIL_POSITION;
+
+    let output = tool_command("ffmpeg")
+        .args(["-v", "error", "-ss", &format!("{:.3}", seek), "-i"])
+        .arg(path)
+        .args([
+            "-frames:v",
+            "1",
+            "-vf",
+            &format!("scale='min({},iw)':-2", max_width),
+        ])
+        .args(["-f", "image2pipe", "-vcodec", "png", "-"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return None;
+    }
+
+    image::load_from_memory(&output.stdout)
+        .ok()
+        .map(|img| img.to_rgba8())
+}
+
+/// Format a duration in seconds as `h:mm:ss` or `m:ss`
+pub fn format_duration(secs: f64) -> String {
+    let total = secs.round() as u64;
+    let (h, m, s) = (total / 3600, (total % 3600) / 60, total % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Format a bitrate in bits per second as `kb/s` or `Mb/s`
+pub fn format_bit_rate(bits_per_sec: u64) -> String {
+    if bits_per_sec >= 10_000_000 {
+        format!("{:.1} Mb/s", bits_per_sec as f64 / 1_000_000.0)
+    } else {
+        format!("{} kb/s", (bits_per_sec as f64 / 1000.0).round())
+    }
+}
+
+/// Decode an image upright, applying its EXIF orientation (JPEG, TIFF and WebP)
+pub fn open_oriented(path: &Path) -> Result<DynamicImage, String> {
+    let (decoder, orientation) = oriented_decoder(path)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Width and height of an image as displayed, after its EXIF orientation
+pub fn oriented_dimensions(path: &Path) -> Result<(u32, u32), String> {
+    let (decoder, orientation) = oriented_decoder(path)?;
+    let (width, height) = decoder.dimensions();
+    Ok(match orientation {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => (height, width),
+        _ => (width, height),
+    })
+}
+
+fn oriented_decoder(path: &Path) -> Result<(impl ImageDecoder, Orientation), String> {
+    let mut decoder = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    // A missing or unreadable tag just means the pixels are already upright
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    Ok((decoder, orientation))
+}
+
+/// Decode the frames of an animated GIF, each downscaled to fit `max_size`.
+///
+/// Decoding stops once the frames would take more than `max_bytes`, so
+/// very long animations loop over their first part.
+pub fn decode_gif_frames(
+    path: &Path,
+    max_size: u32,
+    max_bytes: usize,
+) -> Result<Vec<(image::RgbaImage, Duration)>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let decoder = GifDecoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+
+    let mut frames = Vec::new();
+    let mut bytes = 0;
+    for frame in decoder.into_frames() {
+        let frame = frame.map_err(|e| e.to_string())?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = Duration::from_millis((numer / denom.max(1)) as u64);
+        let delay = if delay < MIN_FRAME_DELAY {
+            DEFAULT_FRAME_DELAY
+        } else {
+            delay
+        };
+
+        let buffer = frame.into_buffer();
+        let image = if buffer.width() > max_size || buffer.height() > max_size {
+            image::DynamicImage::ImageRgba8(buffer)
+                .thumbnail(max_size, max_size)
+                .to_rgba8()
+        } else {
+            buffer
+        };
+        bytes += image.as_raw().len();
+        if bytes > max_bytes && !frames.is_empty() {
+            break;
+        }
+        frames.push((image, delay));
+    }
+    Ok(frames)
+}
+
+/// Index of the frame showing `elapsed` into a looping animation, and how
+/// long until the next frame is due
+pub fn frame_at(delays: &[Duration], elapsed: Duration) -> (usize, Duration) {
+    let total: Duration = delays.iter().sum();
+    if total.is_zero() {
+        return (0, DEFAULT_FRAME_DELAY);
+    }
+    let mut offset = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+    for (i, &delay) in delays.iter().enumerate() {
+        if offset < delay {
+            return (i, delay - offset);
+        }
+        offset -= delay;
+    }
+    (0, delays[0])
+}
+
+/// Build a command for an external tool without flashing a console window
+fn tool_command(name: &str) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(name);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command.stdin(Stdio::null());
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59.4), "0:59");
+        assert_eq!(format_duration(125.0), "2:05");
+        assert_eq!(format_duration(3725.0), "1:02:05");
+        assert_eq!(format_bit_rate(320_000), "320 kb/s");
+        assert_eq!(format_bit_rate(24_500_000), "24.5 Mb/s");
+    }
+
+    #[test]
+    fn test_open_oriented_rotates_sideways_jpeg() {
+        use image::codecs::jpeg::JpegEncoder;
+        use image::{ImageEncoder, RgbImage};
+
+        // Little-endian TIFF with a single Orientation = 6 (rotate 90° clockwise) entry
+        let exif = [
+            b"II*\0\x08\0\0\0\x01\0".as_slice(),
+            b"\x12\x01\x03\0\x01\0\0\0\x06\0\0\0",
+            b"\0\0\0\0",
+        ]
+        .concat();
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("rotated.jpg");
+        let image = RgbImage::new(40, 20);
+        let mut encoder = JpegEncoder::new(File::create(&path).unwrap());
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder
+            .write_image(image.as_raw(), 40, 20, image::ExtendedColorType::Rgb8)
+            .unwrap();
+
+        let oriented = open_oriented(&path).unwrap();
+        let dimensions = oriented_dimensions(&path).unwrap();
+        assert_eq!((oriented.width(), oriented.height()), (20, 40));
+        assert_eq!(dimensions, (20, 40));
+    }
+
+    #[test]
+    fn test_gif_frames_and_timing() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("anim.gif");
+        let frame = |color: u8, ms: u32| {
+            let image = RgbaImage::from_pixel(40, 20, Rgba([color, 0, 0, 255]));
+            Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(ms, 1))
+        };
+        let mut encoder = GifEncoder::new(File::create(&path).unwrap());
+        encoder
+            .encode_frames([frame(0, 50), frame(255, 0), frame(128, 200)])
+            .unwrap();
+        drop(encoder);
+
+        let frames = decode_gif_frames(&path, 10, usize::MAX).unwrap();
+        let capped = decode_gif_frames(&path, 10, 1).unwrap();
+
+        let delays: Vec<Duration> = frames.iter().map(|(_, d)| *d).collect();
+        let ms = Duration::from_millis;
+        assert_eq!(delays, [ms(50), ms(100), ms(200)]);
+        assert_eq!(frames[0].0.dimensions(), (10, 5));
+        assert_eq!(capped.len(), 1);
+
+        assert_eq!(frame_at(&delays, ms(0)), (0, ms(50)));
+        assert_eq!(frame_at(&delays, ms(120)), (1, ms(30)));
+        assert_eq!(frame_at(&delays, ms(350 + 160)), (2, ms(190)));
+        assert_eq!(frame_at(&[], ms(10)).0, 0);
+    }
+
+    #[test]
+    fn test_probe_wav_in_process() {
+        // One second of 8 kHz mono silence, tagged in a LIST/INFO chunk
+        let chunk = |id: &[u8], body: &[u8]| {
+            let mut chunk = id.to_vec();
+            chunk.extend((body.len() as u32).to_le_bytes());
+            chunk.extend(body);
+            chunk
+        };
+        let fmt = [
+            1u16.to_le_bytes().as_slice(),
+            &1u16.to_le_bytes(),
+            &8000u32.to_le_bytes(),
+            &16000u32.to_le_bytes(),
+            &2u16.to_le_bytes(),
+            &16u16.to_le_bytes(),
+        ]
+        .concat();
+        let info = [
+            b"INFO".as_slice(),
+            &chunk(b"INAM", b"Song\0\0"),
+            &chunk(b"IART", b"Band\0\0"),
+        ]
+        .concat();
+        let wave = [
+            b"WAVE".as_slice(),
+            &chunk(b"fmt ", &fmt),
+            &chunk(b"LIST", &info),
+            &chunk(b"data", &[0; 16000]),
+        ]
+        .concat();
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("tone.wav");
+        std::fs::write(&path, chunk(b"RIFF", &wave)).unwrap();
+
+        let info = probe_media(&path).unwrap();
+        assert_eq!(info.audio_codec.as_deref(), Some("pcm_s16le"));
+        assert_eq!(info.duration_secs, Some(1.0));
+        assert_eq!(info.title.as_deref(), Some("Song"));
+        assert_eq!(info.artist.as_deref(), Some("Band"));
+        assert_eq!(info.album, None);
+        assert!(info.bit_rate.is_some_and(|rate| rate >= 128_000));
+        assert!(probe_media(&tmp.path().join("missing.wav")).is_none());
+    }
+
+    #[test]
+    fn test_parse_probe_output() {
+        let json = br#"{
+            "streams": [
+                { "codec_name": "mp3", "codec_type": "audio" },
+                {
+                    "codec_name": "mjpeg", "codec_type": "video", "width": 500, "height": 500,
+                    "disposition": { "attached_pic": 1 }
+                }
+            ],
+            "format": {
+                "duration": "215.35",
+                "bit_rate": "320000",
+                "tags": { "title": "Song", "ARTIST": "Band", "album": " " }
+            }
+        }"#;
+        let info = parse_probe_output(json).unwrap();
+        assert_eq!(info.audio_codec.as_deref(), Some("mp3"));
+        assert_eq!(info.video_codec, None);
+        assert_eq!(info.width, None);
+        assert_eq!(info.duration_secs, Some(215.35));
+        assert_eq!(info.bit_rate, Some(320_000));
+        assert_eq!(info.title.as_deref(), Some("Song"));
+        assert_eq!(info.artist.as_deref(), Some("Band"));
+        assert_eq!(info.album, None);
+        assert_eq!(parse_probe_output(b"not json"), None);
+    }
+}
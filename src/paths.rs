@@ -0,0 +1,115 @@
+//! Paths module - Extended-length paths for deeply nested folders
+//!
+//! Windows rejects paths longer than MAX_PATH (260 characters) unless they
+//! carry the `\\?\` prefix, a limit that backups of deep folder trees easily
+//! exceed. std adds the prefix to its own filesystem calls, so only paths
+//! handed to the shell (the Recycle Bin, Explorer) are converted here;
+//! anything shown or stored keeps its usual form.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// Longest path handed to Windows unchanged. Creating a folder fails 12
+/// characters short of MAX_PATH, to leave room for an 8.3 file name inside.
+const MAX_SHORT_PATH: usize = 248;
+
+/// `path` in a form the filesystem accepts whatever its length.
+///
+/// On Windows, long absolute paths get the `\\?\` prefix; other paths, and
+/// every path elsewhere, are returned unchanged.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    // Byte length never undercounts the UTF-16 length Windows measures
+    if cfg!(windows) && path.as_os_str().len() >= MAX_SHORT_PATH {
+        if let Some(extended) = path.to_str().and_then(extended_form) {
+            return Cow::Owned(PathBuf::from(extended));
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// The `\\?\` form of an absolute Windows path, e.g. `\\?\C:\a` for `C:\a`
+/// and `\\?\UNC\server\share\a` for `\\server\share\a`.
+///
+/// The prefix turns off Windows' own clean-up of the path, so `/`
+/// separators, `.` and `..` are resolved here. `None` for relative paths
+/// and for paths already in device form.
+pub fn extended_form(path: &str) -> Option<String> {
+    let (mut extended, rest, root_parts) =
+        if let Some(unc) = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//")) {
+            if unc.starts_with(['?', '.']) {
+                return None;
+            }
+            // `..` can't climb above the server and share
+            (String::from(r"\\?\UNC"), unc, 2)
+        } else {
+            let bytes = path.as_bytes();
+            let is_drive = bytes.len() >= 3
+                && bytes[0].is_ascii_alphabetic()
+                && bytes[1] == b':'
+                && matches!(bytes[2], b'\\' | b'/');
+            if !is_drive {
+                return None;
+            }
+            (format!(r"\\?\{}", &path[..2]), &path[3..], 0)
+        };
+
+    let mut parts = Vec::new();
+    for part in rest.split(['\\', '/']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.len() > root_parts {
+                    parts.pop();
+                }
+            }
+            part => parts.push(part),
+        }
+    }
+    if parts.len() < root_parts {
+        return None;
+    }
+    if parts.is_empty() {
+        extended.push('\\');
+    }
+    for part in parts {
+        extended.push('\\');
+        extended.push_str(part);
+    }
+    Some(extended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_form() {
+        assert_eq!(
+            extended_form(r"C:\Backup\.\old\..\photos/a.jpg").as_deref(),
+            Some(r"\\?\C:\Backup\photos\a.jpg")
+        );
+        assert_eq!(extended_form(r"d:\").as_deref(), Some(r"\\?\d:\"));
+        assert_eq!(
+            extended_form(r"\\nas\share\..\..\x").as_deref(),
+            Some(r"\\?\UNC\nas\share\x")
+        );
+        assert_eq!(extended_form(r"\\nas"), None);
+        assert_eq!(extended_form(r"\\?\C:\already"), None);
+        assert_eq!(extended_form(r"relative\path"), None);
+        assert_eq!(extended_form("C:drive-relative"), None);
+
+        // Short paths are passed through untouched
+        assert!(matches!(long_path(Path::new(r"C:\a")), Cow::Borrowed(_)));
+
+        let deep = format!(r"C:\{}\a.jpg", ["folder"; 40].join(r"\"));
+        assert!(deep.len() > 260);
+        let expected = format!(r"\\?\{}", deep);
+        assert_eq!(extended_form(&deep).as_deref(), Some(expected.as_str()));
+        if cfg!(windows) {
+            assert_eq!(
+                long_path(Path::new(&deep)).to_str(),
+                Some(expected.as_str())
+            );
+        }
+    }
+}
@@ -3,27 +3,58 @@
 //! This module handles recursive/non-recursive directory traversal
 //! and file metadata collection.
 
-use std::collections::HashMap;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 
-const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024 * 1024;
-const MAX_PARALLEL_THREADS: usize = 8;
+use crate::folders::{find_duplicate_folders, DuplicateFolderGroup};
+use crate::hash_cache::HashCache;
+use crate::matcher::{
+    DuplicateMatcher, HashMatcher, MatchContext, MatchOutput, PerceptualMatcher, ReadPools,
+};
+use crate::similarity::{self, DEFAULT_SIMILARITY_THRESHOLD};
+use crate::volume::{drive_kind, DriveKind};
+
+/// Files larger than this are never hashed, whatever `ScannerConfig::max_size` says
+pub const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Default size from which files are memory-mapped for hashing
+pub const DEFAULT_MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Candidates handed to the matcher at once; groups are published after each batch
+const HASH_BATCH_FILES: usize = 512;
+
+/// Reason shown for a candidate that stayed locked by another process
+const LOCKED_FILE_ERROR: &str = "In use by another process";
+
+/// How often the `*_with_callback` scans report progress
+const PROGRESS_CALLBACK_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Represents a scanned file with metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub name: String,
     pub size: u64,
     pub hash: Option<String>,
+    /// NTFS sparse file; always hashed since its size can't be trusted for grouping
+    pub is_sparse: bool,
+    pub modified: Option<SystemTime>,
+    /// Index of the scanned root folder this file was found under
+    pub root_index: usize,
+    /// Difference hash of an image, set when images are matched by similarity
+    #[serde(default)]
+    pub perceptual_hash: Option<u64>,
 }
 
 impl FileEntry {
@@ -33,51 +64,915 @@ impl FileEntry {
             name,
             size,
             hash: None,
+            is_sparse: false,
+            modified: None,
+            root_index: 0,
+            perceptual_hash: None,
         }
     }
 }
 
 /// A group of duplicate files (same hash)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DuplicateGroup {
     pub hash: String,
     pub files: Vec<FileEntry>,
     pub total_size: u64,
+    /// Size of every file but the keeper, which is what deleting the copies
+    /// frees even when their sizes differ
     pub wasted_size: u64,
+    pub kind: GroupKind,
+    #[serde(default)]
+    pub confidence: MatchConfidence,
+}
+
+/// What kind of match produced a duplicate group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GroupKind {
+    /// Byte-identical files
+    #[default]
+    Exact,
+    /// Truncated copies whose content is a prefix of the keeper
+    PartialCopy,
+    /// Images that look alike but may differ in size, format or encoding
+    SimilarImage,
+}
+
+/// How certain it is that a group's files really are duplicates, most certain first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum MatchConfidence {
+    /// Identical full-content hash
+    #[default]
+    Exact,
+    /// Contents compared byte for byte
+    Verified,
+    /// Strong evidence short of identical content, such as a matching prefix
+    Probable,
+    /// Similar rather than identical, such as perceptual or name-based matches
+    Approximate,
+}
+
+impl MatchConfidence {
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchConfidence::Exact => "Exact",
+            MatchConfidence::Verified => "Verified",
+            MatchConfidence::Probable => "Probable",
+            MatchConfidence::Approximate => "Approximate",
+        }
+    }
+}
+
+/// File name suffixes left behind by interrupted downloads
+const PARTIAL_SUFFIXES: [&str; 5] = [".part", ".partial", ".crdownload", ".download", ".tmp"];
+
+impl DuplicateGroup {
+    /// Group `files`, with the first as the keeper
+    pub fn new(
+        hash: String,
+        files: Vec<FileEntry>,
+        kind: GroupKind,
+        confidence: MatchConfidence,
+    ) -> Self {
+        let mut group = Self {
+            hash,
+            files,
+            total_size: 0,
+            wasted_size: 0,
+            kind,
+            confidence,
+        };
+        group.recompute_sizes();
+        group
+    }
+
+    /// Update `total_size` and `wasted_size` after files or the keeper changed
+    pub fn recompute_sizes(&mut self) {
+        self.total_size = self.files.iter().map(|f| f.size).sum();
+        let keeper_size = self.files.first().map_or(0, |f| f.size);
+        self.wasted_size = self.total_size.saturating_sub(keeper_size);
+    }
+
+    /// Reorder files so the keeper chosen by `strategy` is at index 0
+    pub fn apply_keep_strategy(&mut self, strategy: KeepStrategy) {
+        self.apply_keep_rules(&strategy.criteria());
+    }
+
+    /// Make the file at `idx` the keeper by swapping it with the current one.
+    ///
+    /// Returns whether the keeper changed; partial-copy groups are left alone.
+    pub fn set_keeper(&mut self, idx: usize) -> bool {
+        if self.kind == GroupKind::PartialCopy || idx == 0 || idx >= self.files.len() {
+            return false;
+        }
+        self.files.swap(0, idx);
+        self.recompute_sizes();
+        true
+    }
+
+    /// Perceptual hash distance between the file at `idx` and the keeper, in bits.
+    ///
+    /// Only similar-image groups have one.
+    pub fn similarity_distance(&self, idx: usize) -> Option<u32> {
+        let keeper = self.files.first()?.perceptual_hash?;
+        let file = self.files.get(idx)?.perceptual_hash?;
+        Some(similarity::hamming_distance(keeper, file))
+    }
+
+    /// Whether any non-keeper copy sits outside the protected folders
+    pub fn is_actionable(&self, protected: &[PathBuf]) -> bool {
+        self.files
+            .iter()
+            .skip(1)
+            .any(|f| !is_protected(&f.path, protected))
+    }
+
+    /// Make the first file matching `pattern` the keeper, keeping the others in order.
+    ///
+    /// Returns whether the keeper changed. Partial-copy groups are left alone
+    /// since only the complete file can be kept.
+    pub fn set_keeper_matching(&mut self, pattern: &str) -> bool {
+        if self.kind == GroupKind::PartialCopy {
+            return false;
+        }
+        match self
+            .files
+            .iter()
+            .position(|f| matches_wildcard(&f.path, pattern))
+        {
+            Some(idx) if idx > 0 => {
+                let keeper = self.files.remove(idx);
+                self.files.insert(0, keeper);
+                self.recompute_sizes();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reorder files by applying `rules` lexicographically; the best file becomes the keeper
+    pub fn apply_keep_rules(&mut self, rules: &[KeepCriterion]) {
+        self.files.sort_by(|a, b| {
+            rules
+                .iter()
+                .map(|rule| rule.compare(a, b))
+                .find(|o| o.is_ne())
+                .unwrap_or(CmpOrdering::Equal)
+        });
+        self.recompute_sizes();
+    }
+}
+
+/// Content hash used to decide whether files are identical
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Sha256,
+    /// Several times faster than SHA-256 on modern CPUs
+    #[default]
+    Blake3,
+    /// Fast but not cryptographic; pre-filters candidates for SHA-256, and
+    /// confirms them alone when `ScannerConfig::verify_strong` is off
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Algorithms that can be picked to confirm matches
+    pub const ALL: [HashAlgorithm; 2] = [HashAlgorithm::Blake3, HashAlgorithm::Sha256];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Blake3 => "BLAKE3",
+            HashAlgorithm::Xxh3 => "XXH3",
+        }
+    }
+
+    /// Assumed for results saved before the algorithm was recorded
+    pub fn legacy() -> Self {
+        HashAlgorithm::Sha256
+    }
+}
+
+/// What counts as a duplicate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DetectionMode {
+    /// Byte-identical content
+    #[default]
+    Exact,
+    /// Images that look alike, by perceptual hash; other files still need identical content
+    PerceptualImage,
+}
+
+impl DetectionMode {
+    pub const ALL: [DetectionMode; 2] = [DetectionMode::Exact, DetectionMode::PerceptualImage];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DetectionMode::Exact => "Exact content",
+            DetectionMode::PerceptualImage => "Similar images",
+        }
+    }
+}
+
+/// How the file to keep is chosen within each duplicate group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum KeepStrategy {
+    /// Keep whichever file was found first
+    #[default]
+    First,
+    /// Keep the largest file (highest quality for approximate matches)
+    Largest,
+    /// Keep the smallest file
+    Smallest,
+    /// Keep the file modified longest ago, usually the original
+    Oldest,
+    /// Keep the most recently modified file
+    Newest,
+    ShortestPath,
+    LongestPath,
+    /// Apply the configured `keep_rules` in order
+    Rules,
+    /// Leave the order alone; the keeper is picked by hand in the results
+    Manual,
+}
+
+impl KeepStrategy {
+    pub const ALL: [KeepStrategy; 9] = [
+        KeepStrategy::First,
+        KeepStrategy::Oldest,
+        KeepStrategy::Newest,
+        KeepStrategy::ShortestPath,
+        KeepStrategy::LongestPath,
+        KeepStrategy::Largest,
+        KeepStrategy::Smallest,
+        KeepStrategy::Rules,
+        KeepStrategy::Manual,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeepStrategy::First => "First found",
+            KeepStrategy::Largest => "Largest",
+            KeepStrategy::Smallest => "Smallest",
+            KeepStrategy::Oldest => "Oldest",
+            KeepStrategy::Newest => "Newest",
+            KeepStrategy::ShortestPath => "Shortest path",
+            KeepStrategy::LongestPath => "Longest path",
+            KeepStrategy::Rules => "Custom rules",
+            KeepStrategy::Manual => "Manual",
+        }
+    }
+
+    /// The criterion behind a single-rule strategy; empty for the others
+    pub fn criteria(&self) -> Vec<KeepCriterion> {
+        match self {
+            KeepStrategy::Largest => vec![KeepCriterion::Largest],
+            KeepStrategy::Smallest => vec![KeepCriterion::Smallest],
+            KeepStrategy::Oldest => vec![KeepCriterion::Oldest],
+            KeepStrategy::Newest => vec![KeepCriterion::Newest],
+            KeepStrategy::ShortestPath => vec![KeepCriterion::ShortestPath],
+            KeepStrategy::LongestPath => vec![KeepCriterion::LongestPath],
+            KeepStrategy::First | KeepStrategy::Rules | KeepStrategy::Manual => Vec::new(),
+        }
+    }
+}
+
+/// A single tiebreaker used when keeper rules are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeepCriterion {
+    ShortestPath,
+    LongestPath,
+    Newest,
+    Oldest,
+    Largest,
+    Smallest,
+    /// Prefer files from folders added earlier
+    FolderPriority,
+    NameAlphabetical,
+}
+
+impl KeepCriterion {
+    pub const ALL: [KeepCriterion; 8] = [
+        KeepCriterion::ShortestPath,
+        KeepCriterion::LongestPath,
+        KeepCriterion::Newest,
+        KeepCriterion::Oldest,
+        KeepCriterion::Largest,
+        KeepCriterion::Smallest,
+        KeepCriterion::FolderPriority,
+        KeepCriterion::NameAlphabetical,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeepCriterion::ShortestPath => "Shorter path",
+            KeepCriterion::LongestPath => "Longer path",
+            KeepCriterion::Newest => "Newer",
+            KeepCriterion::Oldest => "Older",
+            KeepCriterion::Largest => "Larger",
+            KeepCriterion::Smallest => "Smaller",
+            KeepCriterion::FolderPriority => "Earlier folder",
+            KeepCriterion::NameAlphabetical => "Name A-Z",
+        }
+    }
+
+    /// Order two files so the preferred keeper sorts first
+    pub fn compare(&self, a: &FileEntry, b: &FileEntry) -> CmpOrdering {
+        let path_len = |f: &FileEntry| f.path.as_os_str().len();
+        match self {
+            KeepCriterion::ShortestPath => path_len(a).cmp(&path_len(b)),
+            KeepCriterion::LongestPath => path_len(b).cmp(&path_len(a)),
+            // Files with an unknown mtime sort last either way
+            KeepCriterion::Newest => b.modified.cmp(&a.modified),
+            KeepCriterion::Oldest => match (a.modified, b.modified) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => CmpOrdering::Less,
+                (None, Some(_)) => CmpOrdering::Greater,
+                (None, None) => CmpOrdering::Equal,
+            },
+            KeepCriterion::Largest => b.size.cmp(&a.size),
+            KeepCriterion::Smallest => a.size.cmp(&b.size),
+            KeepCriterion::FolderPriority => a.root_index.cmp(&b.root_index),
+            KeepCriterion::NameAlphabetical => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        }
+    }
+}
+
+/// Stage a running scan is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanPhase {
+    /// Walking folders; only the number of files found is known
+    Collecting,
+    /// Matching candidates against each other
+    Hashing,
+}
+
+impl ScanPhase {
+    pub fn label(self) -> &'static str {
+        match self {
+            ScanPhase::Collecting => "Collecting files",
+            ScanPhase::Hashing => "Hashing",
+        }
+    }
+}
+
+/// Only every this many updates of a `CurrentFile` is kept
+const CURRENT_FILE_STRIDE: usize = 64;
+
+/// File a scan is working on, for display. Only the first of every
+/// `CURRENT_FILE_STRIDE` updates is kept, so most files don't pay for the
+/// lock and the copy of their path.
+#[derive(Debug, Default)]
+pub struct CurrentFile {
+    updates: AtomicUsize,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl CurrentFile {
+    /// Record that a worker is starting on `path`
+    pub fn set(&self, path: &Path) {
+        if !self
+            .updates
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(CURRENT_FILE_STRIDE)
+        {
+            return;
+        }
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path.to_path_buf());
+        }
+    }
+
+    pub fn get(&self) -> Option<PathBuf> {
+        self.path.lock().ok()?.clone()
+    }
+
+    fn clear(&self) {
+        self.updates.store(0, Ordering::Relaxed);
+        if let Ok(mut current) = self.path.lock() {
+            *current = None;
+        }
+    }
 }
 
-/// Progress tracking for scan operations
-#[derive(Debug, Clone, Default)]
+/// Live progress of a scan, updated by the scanner and read from other threads
+#[derive(Debug, Default)]
 pub struct ScanProgress {
-    pub phase: String,
-    pub total_files: usize,
-    pub processed_files: usize,
-    pub current_file: String,
+    hashing: AtomicBool,
+    /// Files collected so far
+    pub files_found: AtomicUsize,
+    /// Candidates the matcher has processed
+    pub current: AtomicUsize,
+    /// Candidates to process, known once collection is done
+    pub total: AtomicUsize,
+    /// Groups confirmed so far, moved into the result when the scan ends
+    pub groups: Mutex<Vec<DuplicateGroup>>,
+    current_file: CurrentFile,
+}
+
+/// A copy of `ScanProgress` at one moment, as passed to progress callbacks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressUpdate {
+    pub phase: ScanPhase,
+    pub files_found: usize,
+    /// Candidates the matcher has processed
+    pub processed: usize,
+    /// Candidates to process, known once collection is done
+    pub total: usize,
+    pub groups_found: usize,
+    /// File most recently collected or started on by the matcher
+    pub current_file: Option<PathBuf>,
+}
+
+impl ScanProgress {
+    pub fn phase(&self) -> ScanPhase {
+        if self.hashing.load(Ordering::Relaxed) {
+            ScanPhase::Hashing
+        } else {
+            ScanPhase::Collecting
+        }
+    }
+
+    /// Number of groups confirmed so far
+    pub fn groups_found(&self) -> usize {
+        self.groups.lock().map_or(0, |groups| groups.len())
+    }
+
+    /// File most recently collected or started on by the matcher
+    pub fn current_file(&self) -> Option<PathBuf> {
+        self.current_file.get()
+    }
+
+    pub fn snapshot(&self) -> ProgressUpdate {
+        ProgressUpdate {
+            phase: self.phase(),
+            files_found: self.files_found.load(Ordering::Relaxed),
+            processed: self.current.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            groups_found: self.groups_found(),
+            current_file: self.current_file(),
+        }
+    }
+
+    fn start(&self, phase: ScanPhase) {
+        if phase == ScanPhase::Collecting {
+            self.files_found.store(0, Ordering::Relaxed);
+            self.total.store(0, Ordering::Relaxed);
+            self.take_groups();
+        }
+        self.current_file.clear();
+        self.current.store(0, Ordering::Relaxed);
+        self.hashing
+            .store(phase == ScanPhase::Hashing, Ordering::Relaxed);
+    }
+
+    fn publish(&self, found: Vec<DuplicateGroup>) {
+        if let Ok(mut groups) = self.groups.lock() {
+            groups.extend(found);
+        }
+    }
+
+    fn take_groups(&self) -> Vec<DuplicateGroup> {
+        self.groups
+            .lock()
+            .map(|mut groups| std::mem::take(&mut *groups))
+            .unwrap_or_default()
+    }
 }
 
 /// Result of a duplicate scan
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScanResult {
+    /// Folders the scan was asked to cover
+    #[serde(default)]
+    pub folders: Vec<PathBuf>,
     pub total_files: usize,
     pub total_size: u64,
     pub duplicate_groups: Vec<DuplicateGroup>,
+    /// Whole folders whose files all match another folder's
+    #[serde(default)]
+    pub duplicate_folders: Vec<DuplicateFolderGroup>,
     pub total_duplicates: usize,
     pub wasted_space: u64,
     pub errors: Vec<String>,
+    /// Files excluded up front because they could not be opened
+    pub unreadable_files: Vec<PathBuf>,
+    /// Zero-byte files seen while collecting, whether or not `min_size` let them be matched
+    #[serde(default)]
+    pub empty_files: usize,
+    #[serde(default)]
+    pub zero_byte_paths: Vec<PathBuf>,
+    /// Every collected file, only populated when `retain_all_files` is set
+    pub all_files: Vec<FileEntry>,
+    /// Sparse files that were hashed regardless of the size pre-filter
+    pub sparse_files: Vec<PathBuf>,
+    /// Files that survived the size pre-filter and were sent to hashing
+    pub hash_candidates: usize,
+    /// Size-match candidates whose hash couldn't be computed
+    pub unhashed_suspects: Vec<UnhashedSuspect>,
+    /// Candidates another process held open even after a retry; they are also
+    /// in `unhashed_suspects` so they can be retried once it lets go
+    pub locked_files: Vec<PathBuf>,
+    /// Scanned folders on network or removable drives, which were hashed
+    /// with fewer threads, or skipped
+    #[serde(default)]
+    pub slow_roots: Vec<SlowRoot>,
+    /// Hashing stopped at `time_budget`, so some duplicates may be missing
+    pub time_limit_reached: bool,
+    /// Algorithm behind the hashes in this result
+    #[serde(default = "HashAlgorithm::legacy")]
+    pub hash_algorithm: HashAlgorithm,
+    /// Wall-clock time the scan took
+    #[serde(default)]
+    pub elapsed: Duration,
+    /// Bytes read by full hashes, excluding files served from the hash cache
+    #[serde(default)]
+    pub bytes_hashed: u64,
+}
+
+/// Duplicate copies found in one folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderWaste {
+    pub folder: PathBuf,
+    /// Non-keeper copies directly inside `folder`
+    pub duplicates: usize,
+    pub wasted_size: u64,
+}
+
+/// What collecting files turned up besides the files themselves
+#[derive(Default)]
+struct CollectNotes {
+    errors: Vec<String>,
+    unreadable: Vec<PathBuf>,
+    empty: Vec<PathBuf>,
+}
+
+/// A file that matched another file's size but failed to hash, so it may be
+/// an unreported duplicate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnhashedSuspect {
+    pub file: FileEntry,
+    pub error: String,
+    /// Hashed files of the same size it could turn out to match
+    pub peers: Vec<FileEntry>,
+}
+
+/// A folder or file to scan that lives on a network or removable drive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowRoot {
+    pub path: PathBuf,
+    pub kind: DriveKind,
+    /// Left out because `skip_network_drives` is set
+    pub skipped: bool,
+}
+
+impl ScanResult {
+    /// "Scanned in 12.3s (450.00 MB/s)", or `None` for results saved before timing was recorded
+    pub fn timing_summary(&self) -> Option<String> {
+        if self.elapsed.is_zero() {
+            return None;
+        }
+        let secs = self.elapsed.as_secs_f64();
+        Some(format!(
+            "Scanned in {:.1}s ({}/s)",
+            secs,
+            format_size((self.bytes_hashed as f64 / secs) as u64)
+        ))
+    }
+
+    /// Wasted space of every group's non-keeper copies, summed by parent folder,
+    /// most wasted space first
+    pub fn wasted_by_folder(&self) -> Vec<FolderWaste> {
+        let mut by_folder: HashMap<&Path, FolderWaste> = HashMap::new();
+        for file in self
+            .duplicate_groups
+            .iter()
+            .flat_map(|g| g.files.iter().skip(1))
+        {
+            let folder = file.path.parent().unwrap_or(Path::new(""));
+            let entry = by_folder.entry(folder).or_insert_with(|| FolderWaste {
+                folder: folder.to_path_buf(),
+                duplicates: 0,
+                wasted_size: 0,
+            });
+            entry.duplicates += 1;
+            entry.wasted_size += file.size;
+        }
+        let mut folders: Vec<FolderWaste> = by_folder.into_values().collect();
+        folders.sort_by(|a, b| {
+            b.wasted_size
+                .cmp(&a.wasted_size)
+                .then_with(|| a.folder.cmp(&b.folder))
+        });
+        folders
+    }
+
+    /// Apply `set_keeper_matching` to every group, returning how many keepers changed
+    pub fn apply_keeper_pattern(&mut self, pattern: &str) -> usize {
+        self.duplicate_groups
+            .iter_mut()
+            .map(|g| g.set_keeper_matching(pattern))
+            .filter(|&changed| changed)
+            .count()
+    }
+
+    /// Drop files that no longer exist at their scanned location (deleted or moved),
+    /// counting everything inside a removed folder, and remove any groups left
+    /// with fewer than two files
+    pub fn remove_paths(&mut self, paths: &HashSet<PathBuf>) {
+        let removed = |path: &Path| path.ancestors().any(|p| paths.contains(p));
+        for group in &mut self.duplicate_groups {
+            group.files.retain(|f| !removed(&f.path));
+            group.recompute_sizes();
+        }
+        self.duplicate_groups.retain(|g| g.files.len() > 1);
+        self.all_files.retain(|f| !removed(&f.path));
+        self.zero_byte_paths.retain(|p| !removed(p));
+        self.empty_files = self.zero_byte_paths.len();
+        self.unhashed_suspects.retain(|s| !removed(&s.file.path));
+        // A folder that lost anything no longer matches the others
+        for group in &mut self.duplicate_folders {
+            group
+                .folders
+                .retain(|folder| !removed(folder) && !paths.iter().any(|p| p.starts_with(folder)));
+        }
+        self.duplicate_folders.retain(|g| g.folders.len() > 1);
+        self.recompute_totals();
+    }
+
+    /// Point files at their new paths after a rename, keys being the old paths
+    pub fn rename_paths(&mut self, renamed: &HashMap<PathBuf, PathBuf>) {
+        let groups = self.duplicate_groups.iter_mut().flat_map(|g| &mut g.files);
+        let suspects = self
+            .unhashed_suspects
+            .iter_mut()
+            .flat_map(|s| std::iter::once(&mut s.file).chain(&mut s.peers));
+        for file in groups.chain(&mut self.all_files).chain(suspects) {
+            if let Some(new_path) = renamed.get(&file.path) {
+                file.name = new_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                file.path = new_path.clone();
+            }
+        }
+        for path in self
+            .locked_files
+            .iter_mut()
+            .chain(&mut self.zero_byte_paths)
+        {
+            if let Some(new_path) = renamed.get(path) {
+                *path = new_path.clone();
+            }
+        }
+    }
+
+    /// Hash a suspect again and, if it now matches, add it to the matching group.
+    ///
+    /// Returns whether the file turned out to be a duplicate. On failure the
+    /// suspect stays in the list with the new error.
+    pub fn retry_suspect(&mut self, index: usize) -> Result<bool, String> {
+        let suspect = self
+            .unhashed_suspects
+            .get_mut(index)
+            .ok_or("No such file")?;
+        let hash = match compute_file_hash(&suspect.file.path, self.hash_algorithm) {
+            Ok(hash) => hash,
+            Err(e) => {
+                suspect.error = e.to_string();
+                return Err(format!(
+                    "Failed to hash {}: {}",
+                    suspect.file.path.display(),
+                    e
+                ));
+            }
+        };
+
+        let suspect = self.unhashed_suspects.remove(index);
+        self.locked_files.retain(|p| *p != suspect.file.path);
+        let mut file = suspect.file;
+        file.hash = Some(hash);
+        Ok(self.insert_hashed(file, suspect.peers))
+    }
+
+    /// Add a hashed file to the exact group with its hash, or start a group with a
+    /// matching peer. Returns false if it duplicates nothing.
+    pub fn insert_hashed(
+        &mut self,
+        file: FileEntry,
+        peers: impl IntoIterator<Item = FileEntry>,
+    ) -> bool {
+        let Some(hash) = file.hash.clone() else {
+            return false;
+        };
+
+        if let Some(group) = self
+            .duplicate_groups
+            .iter_mut()
+            .find(|g| g.kind == GroupKind::Exact && g.hash == hash)
+        {
+            group.files.push(file);
+            group.recompute_sizes();
+        } else if let Some(peer) = peers.into_iter().find(|p| p.hash == file.hash) {
+            self.duplicate_groups.push(DuplicateGroup::new(
+                hash,
+                vec![peer, file],
+                GroupKind::Exact,
+                MatchConfidence::Exact,
+            ));
+        } else {
+            return false;
+        }
+
+        self.recompute_totals();
+        true
+    }
+
+    /// Swap a group for the result of re-verifying it, in the same place so
+    /// the list keeps whatever order it was sorted in
+    pub fn replace_group(&mut self, hash: &str, groups: Vec<DuplicateGroup>) {
+        match self.duplicate_groups.iter().position(|g| g.hash == hash) {
+            Some(index) => {
+                self.duplicate_groups.splice(index..=index, groups);
+            }
+            None => self.duplicate_groups.extend(groups),
+        }
+        self.recompute_totals();
+    }
+
+    fn recompute_totals(&mut self) {
+        self.total_duplicates = self
+            .duplicate_groups
+            .iter()
+            .map(|g| g.files.len() - 1)
+            .sum();
+        self.wasted_space = self.duplicate_groups.iter().map(|g| g.wasted_size).sum();
+    }
 }
 
 /// Scanner configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScannerConfig {
     pub recursive: bool,
+    /// Walk into symlinked and junctioned folders
+    pub follow_symlinks: bool,
+    /// Folders that are never walked into
+    pub exclude_paths: Vec<PathBuf>,
+    /// Folder names that are never walked into wherever they appear, e.g. `node_modules`
+    pub exclude_dir_names: Vec<String>,
+    /// Skip files and folders marked hidden (Windows only)
+    pub skip_hidden: bool,
+    /// Skip files and folders marked as system files, e.g. desktop.ini (Windows only)
+    pub skip_system: bool,
     pub min_size: u64,
+    /// Skip files larger than this; capped at `MAX_FILE_SIZE`
+    pub max_size: u64,
+    /// Only collect files with one of these extensions (lowercase, no dot)
+    pub include_extensions: Option<Vec<String>>,
+    /// Never collect files with one of these extensions (lowercase, no dot)
+    pub exclude_extensions: Vec<String>,
+    /// Collect files that have no extension at all
+    pub include_no_extension: bool,
+    /// Open each file during collection and skip the ones that can't be read
+    pub verify_readable: bool,
+    pub keep_strategy: KeepStrategy,
+    /// Ordered tiebreakers used by `KeepStrategy::Rules`
+    pub keep_rules: Vec<KeepCriterion>,
+    /// Keep the full collected file list in the result for searching
+    pub retain_all_files: bool,
+    /// Look for truncated copies of files with the same name or in the same folder
+    pub detect_partial_copies: bool,
+    /// Folders whose files must never be deleted or moved
+    pub protected_folders: Vec<PathBuf>,
+    /// Run hashing threads below normal priority (Windows only)
+    pub low_priority: bool,
+    /// Leave out folders and files on network drives
+    pub skip_network_drives: bool,
+    /// Break keeper ties in favor of folders added earlier
+    pub prefer_first_folder: bool,
+    /// Stop hashing after this long and keep the groups found so far
+    pub time_budget: Option<Duration>,
+    pub hash_algorithm: HashAlgorithm,
+    /// Confirm files with matching XXH3 hashes using `hash_algorithm`; off trusts XXH3 alone
+    pub verify_strong: bool,
+    /// Compare hash-matched files byte for byte and split groups that differ
+    pub verify_bytes: bool,
+    /// Memory-map files at least this large when hashing; `None` always reads
+    /// through a buffer, as do files on network and removable drives
+    pub mmap_threshold: Option<u64>,
+    pub detection_mode: DetectionMode,
+    /// Largest perceptual hash distance, in bits out of 64, at which images count as similar
+    pub similarity_threshold: u32,
+    /// How the keeper of similar-image groups is chosen; their copies differ
+    /// in quality, so this defaults to the largest rather than `keep_strategy`
+    pub similar_keep_strategy: KeepStrategy,
+}
+
+impl ScannerConfig {
+    /// Algorithm behind the hashes that decide groups
+    pub fn effective_hash_algorithm(&self) -> HashAlgorithm {
+        if self.verify_strong {
+            self.hash_algorithm
+        } else {
+            HashAlgorithm::Xxh3
+        }
+    }
+
+    /// Whether a file of `size` bytes passes the size filter
+    pub fn size_in_range(&self, size: u64) -> bool {
+        size >= self.min_size && size <= self.max_size.min(MAX_FILE_SIZE)
+    }
+
+    /// Whether the extension filters let `path` through (case-insensitive)
+    pub fn extension_allowed(&self, path: &Path) -> bool {
+        let ext = normalized_extension(path);
+        if ext.is_empty() {
+            return self.include_no_extension;
+        }
+        // "gz" also covers compound extensions such as "tar.gz"
+        let matches = |wanted: &String| {
+            let wanted = wanted.to_lowercase();
+            ext == wanted || ext.ends_with(&format!(".{}", wanted))
+        };
+        if self.exclude_extensions.iter().any(matches) {
+            return false;
+        }
+        match &self.include_extensions {
+            Some(include) => include.iter().any(matches),
+            None => true,
+        }
+    }
+
+    /// Whether `dir` is an excluded folder, by path or by name (case-insensitive)
+    pub fn dir_excluded(&self, dir: &Path) -> bool {
+        if self.exclude_paths.iter().any(|p| dir.starts_with(p)) {
+            return true;
+        }
+        let Some(name) = dir.file_name() else {
+            return false;
+        };
+        let name = name.to_string_lossy().to_lowercase();
+        self.exclude_dir_names
+            .iter()
+            .any(|excluded| excluded.to_lowercase() == name)
+    }
+
+    /// Whether `skip_hidden`/`skip_system` exclude an entry with this metadata
+    #[cfg(windows)]
+    pub fn attributes_excluded(&self, metadata: &fs::Metadata) -> bool {
+        use std::os::windows::fs::MetadataExt;
+        use windows_sys::Win32::Storage::FileSystem::{
+            FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM,
+        };
+
+        let attributes = metadata.file_attributes();
+        (self.skip_hidden && attributes & FILE_ATTRIBUTE_HIDDEN != 0)
+            || (self.skip_system && attributes & FILE_ATTRIBUTE_SYSTEM != 0)
+    }
+
+    #[cfg(not(windows))]
+    pub fn attributes_excluded(&self, _metadata: &fs::Metadata) -> bool {
+        false
+    }
 }
 
 impl Default for ScannerConfig {
     fn default() -> Self {
         Self {
             recursive: true,
+            follow_symlinks: false,
+            exclude_paths: Vec::new(),
+            exclude_dir_names: Vec::new(),
+            skip_hidden: true,
+            skip_system: true,
             min_size: 1,
+            max_size: MAX_FILE_SIZE,
+            include_extensions: None,
+            exclude_extensions: Vec::new(),
+            include_no_extension: true,
+            verify_readable: false,
+            keep_strategy: KeepStrategy::First,
+            keep_rules: vec![
+                KeepCriterion::ShortestPath,
+                KeepCriterion::Newest,
+                KeepCriterion::Largest,
+            ],
+            retain_all_files: false,
+            detect_partial_copies: false,
+            protected_folders: Vec::new(),
+            low_priority: false,
+            skip_network_drives: false,
+            prefer_first_folder: true,
+            time_budget: None,
+            hash_algorithm: HashAlgorithm::default(),
+            verify_strong: true,
+            verify_bytes: false,
+            mmap_threshold: Some(DEFAULT_MMAP_THRESHOLD),
+            detection_mode: DetectionMode::default(),
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
+            similar_keep_strategy: KeepStrategy::Largest,
         }
     }
 }
@@ -85,21 +980,43 @@ impl Default for ScannerConfig {
 /// The main scanner struct
 pub struct Scanner {
     config: ScannerConfig,
+    matcher: Box<dyn DuplicateMatcher>,
     cancel_flag: Arc<AtomicBool>,
-    progress_total: Arc<AtomicUsize>,
-    progress_current: Arc<AtomicUsize>,
+    progress: Arc<ScanProgress>,
 }
 
 impl Scanner {
     pub fn new(config: ScannerConfig) -> Self {
         Self {
+            matcher: Box::new(HashMatcher {
+                algorithm: config.effective_hash_algorithm(),
+                mmap_threshold: config.mmap_threshold,
+                cache: None,
+            }),
             config,
             cancel_flag: Arc::new(AtomicBool::new(false)),
-            progress_total: Arc::new(AtomicUsize::new(0)),
-            progress_current: Arc::new(AtomicUsize::new(0)),
+            progress: Arc::new(ScanProgress::default()),
         }
     }
 
+    /// Compare content hashes, reusing and updating `cache`.
+    ///
+    /// Replaces any matcher set with `with_matcher`.
+    pub fn with_hash_cache(mut self, cache: Arc<Mutex<HashCache>>) -> Self {
+        self.matcher = Box::new(HashMatcher {
+            algorithm: self.config.effective_hash_algorithm(),
+            mmap_threshold: self.config.mmap_threshold,
+            cache: Some(cache),
+        });
+        self
+    }
+
+    /// Use a custom matcher instead of comparing content hashes
+    pub fn with_matcher(mut self, matcher: Box<dyn DuplicateMatcher>) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
     /// Get a cancellation handle
     pub fn get_cancel_flag(&self) -> Arc<AtomicBool> {
         Arc::clone(&self.cancel_flag)
@@ -108,8 +1025,8 @@ impl Scanner {
     /// Get progress info
     pub fn get_progress(&self) -> (usize, usize) {
         (
-            self.progress_current.load(Ordering::Relaxed),
-            self.progress_total.load(Ordering::Relaxed),
+            self.progress.current.load(Ordering::Relaxed),
+            self.progress.total.load(Ordering::Relaxed),
         )
     }
 
@@ -121,224 +1038,981 @@ impl Scanner {
     /// Reset cancellation flag
     pub fn reset(&self) {
         self.cancel_flag.store(false, Ordering::Relaxed);
-        self.progress_current.store(0, Ordering::Relaxed);
-        self.progress_total.store(0, Ordering::Relaxed);
+        self.progress.start(ScanPhase::Collecting);
     }
 
     /// Scan a directory for duplicate files
     pub fn scan_directory(&self, path: &Path) -> ScanResult {
         self.scan_directories_with_progress(
             &[path.to_path_buf()],
-            &self.progress_current,
-            &self.progress_total,
+            &self.progress,
             &self.cancel_flag,
         )
     }
 
     /// Scan multiple directories for duplicate files
     pub fn scan_directories(&self, paths: &[PathBuf]) -> ScanResult {
-        self.scan_directories_with_progress(
-            paths,
-            &self.progress_current,
-            &self.progress_total,
-            &self.cancel_flag,
-        )
+        self.scan_directories_with_progress(paths, &self.progress, &self.cancel_flag)
+    }
+
+    /// Check an explicit list of files for duplicates without walking any folders
+    pub fn scan_files(&self, files: &[PathBuf]) -> ScanResult {
+        self.scan_paths_with_progress(&[], files, &self.progress, &self.cancel_flag)
     }
 
     /// Scan multiple directories for duplicate files with external progress tracking
     pub fn scan_directories_with_progress(
         &self,
         paths: &[PathBuf],
-        progress_current: &AtomicUsize,
-        progress_total: &AtomicUsize,
+        progress: &ScanProgress,
         cancel_flag: &AtomicBool,
     ) -> ScanResult {
-        progress_current.store(0, Ordering::Relaxed);
-        progress_total.store(0, Ordering::Relaxed);
+        self.scan_paths_with_progress(paths, &[], progress, cancel_flag)
+    }
 
-        let mut result = ScanResult::default();
+    /// Scan multiple directories, calling `progress_callback` with a snapshot
+    /// of the progress about every 100 ms and once more when the scan ends
+    pub fn scan_directories_with_callback(
+        &self,
+        paths: &[PathBuf],
+        cancel_flag: &AtomicBool,
+        progress_callback: impl Fn(ProgressUpdate) + Send + Sync,
+    ) -> ScanResult {
+        self.scan_paths_with_callback(paths, &[], cancel_flag, progress_callback)
+    }
 
-        // Collect files from all directories
-        let mut files = Vec::new();
-        for path in paths.iter() {
-            if cancel_flag.load(Ordering::Relaxed) {
-                return result;
-            }
-            let mut dir_files =
-                self.collect_files_with_cancel(path, cancel_flag, &mut result.errors);
-            files.append(&mut dir_files);
-        }
+    /// Like `scan_paths_with_progress`, but reports to `progress_callback` from
+    /// a helper thread instead of exposing the live counters
+    pub fn scan_paths_with_callback(
+        &self,
+        folders: &[PathBuf],
+        files: &[PathBuf],
+        cancel_flag: &AtomicBool,
+        progress_callback: impl Fn(ProgressUpdate) + Send + Sync,
+    ) -> ScanResult {
+        let progress = ScanProgress::default();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let (progress, progress_callback) = (&progress, &progress_callback);
+        let result = std::thread::scope(|scope| {
+            scope.spawn(move || {
+                // Dropping the sender ends the wait early, so the scan never waits on a tick
+                while let Err(RecvTimeoutError::Timeout) =
+                    done_rx.recv_timeout(PROGRESS_CALLBACK_INTERVAL)
+                {
+                    progress_callback(progress.snapshot());
+                }
+            });
+            let result = self.scan_paths_with_progress(folders, files, progress, cancel_flag);
+            drop(done_tx);
+            result
+        });
+        // Found groups have been moved into the result by now
+        progress_callback(ProgressUpdate {
+            groups_found: result.duplicate_groups.len(),
+            ..progress.snapshot()
+        });
+        result
+    }
 
-        if cancel_flag.load(Ordering::Relaxed) {
-            return result;
-        }
+    /// Scan folders plus individually listed files with external progress tracking.
+    ///
+    /// Loose files rank after every folder for `KeepCriterion::FolderPriority`,
+    /// and ones already inside a scanned folder are skipped.
+    pub fn scan_paths_with_progress(
+        &self,
+        folders: &[PathBuf],
+        files: &[PathBuf],
+        progress: &ScanProgress,
+        cancel_flag: &AtomicBool,
+    ) -> ScanResult {
+        let started = Instant::now();
+        let mut result = self.scan_paths(folders, files, progress, cancel_flag);
+        result.elapsed = started.elapsed();
+        result
+    }
 
-        result.total_files = files.len();
-        result.total_size = files.iter().map(|f| f.size).sum();
+    fn scan_paths(
+        &self,
+        folders: &[PathBuf],
+        files: &[PathBuf],
+        progress: &ScanProgress,
+        cancel_flag: &AtomicBool,
+    ) -> ScanResult {
+        progress.start(ScanPhase::Collecting);
 
-        let size_groups = self.group_by_size(files);
+        let mut result = ScanResult {
+            folders: folders.to_vec(),
+            hash_algorithm: self.config.effective_hash_algorithm(),
+            ..Default::default()
+        };
+        let mut notes = CollectNotes::default();
 
-        let potential_duplicates: Vec<FileEntry> = size_groups
-            .into_iter()
-            .filter(|(_, files)| files.len() > 1)
-            .flat_map(|(_, files)| files)
-            .collect();
+        // Entries go straight into size buckets rather than a list sorted
+        // afterwards. Files of a unique size stay until collection ends,
+        // since a later file may still match them.
+        let mut size_groups: HashMap<u64, Vec<FileEntry>> = HashMap::new();
+        let mut real_paths = HashSet::new();
+        // Files directly in each directory, for spotting duplicated folders
+        let mut dir_counts: HashMap<PathBuf, usize> = HashMap::new();
+        let mut slow_roots = Vec::new();
+        let mut add_file = |mut file: FileEntry, root_index: usize| {
+            progress.current_file.set(&file.path);
+            // One physical file reached through two links must not match itself
+            if self.config.follow_symlinks {
+                if let Ok(real) = fs::canonicalize(&file.path) {
+                    if !real_paths.insert(real) {
+                        return;
+                    }
+                }
+            }
+            file.root_index = root_index;
+            result.total_files += 1;
+            result.total_size += file.size;
+            if file.is_sparse {
+                result.sparse_files.push(file.path.clone());
+            }
+            if self.config.retain_all_files {
+                result.all_files.push(file.clone());
+            }
+            if let Some(dir) = file.path.parent() {
+                *dir_counts.entry(dir.to_path_buf()).or_default() += 1;
+            }
+            size_groups.entry(file.size).or_default().push(file);
+        };
 
-        if potential_duplicates.is_empty() || cancel_flag.load(Ordering::Relaxed) {
-            return result;
+        for (root_index, path) in folders.iter().enumerate() {
+            if cancel_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            if self.skip_drive(path, &mut slow_roots) {
+                continue;
+            }
+            self.collect_files_with_cancel(
+                path,
+                cancel_flag,
+                &progress.files_found,
+                &mut notes,
+                |file| add_file(file, root_index),
+            );
         }
 
-        progress_total.store(potential_duplicates.len(), Ordering::Relaxed);
-        progress_current.store(0, Ordering::Relaxed);
-
-        let hashed_files = self.hash_files(
-            potential_duplicates,
-            progress_current,
-            cancel_flag,
-            &mut result.errors,
-        );
+        let mut seen_loose = HashSet::new();
+        for path in files {
+            if cancel_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            if folders.iter().any(|f| self.folder_covers(f, path))
+                || !seen_loose.insert(path)
+                || self.skip_drive(path, &mut slow_roots)
+            {
+                continue;
+            }
+            if let Some(file) = self.file_entry(path, &mut notes) {
+                progress.files_found.fetch_add(1, Ordering::Relaxed);
+                add_file(file, folders.len());
+            }
+        }
 
+        result.slow_roots = slow_roots;
+        result.errors = notes.errors;
+        result.unreadable_files = notes.unreadable;
+        result.empty_files = notes.empty.len();
+        result.zero_byte_paths = notes.empty;
         if cancel_flag.load(Ordering::Relaxed) {
             return result;
         }
 
-        let hash_groups = self.group_by_hash(hashed_files);
-
-        for (hash, files) in hash_groups {
-            if files.len() > 1 {
-                let total_size: u64 = files.iter().map(|f| f.size).sum();
-                let wasted_size = total_size - files[0].size;
+        // Hard links are one file under several names, and deleting one frees
+        // nothing, so only the first name found is matched
+        for files in size_groups.values_mut().filter(|files| files.len() > 1) {
+            let mut ids = HashSet::new();
+            let before = files.len();
+            files.retain(|f| hard_link_id(&f.path).is_none_or(|id| ids.insert(id)));
+            let dropped = before - files.len();
+            result.total_files -= dropped;
+            result.total_size -= dropped as u64 * files[0].size;
+        }
 
-                result.total_duplicates += files.len() - 1;
-                result.wasted_space += wasted_size;
+        if self.config.detect_partial_copies {
+            // Partial copies need no full hashes, so they show up before hashing starts
+            progress.publish(self.find_partial_copies(
+                size_groups.values().flatten(),
+                cancel_flag,
+                &mut result.errors,
+            ));
+        }
 
-                result.duplicate_groups.push(DuplicateGroup {
-                    hash,
-                    files,
-                    total_size,
-                    wasted_size,
-                });
+        // Resized or re-encoded copies differ in size, so images skip the size pre-filter
+        let mut images = Vec::new();
+        if self.config.detection_mode == DetectionMode::PerceptualImage {
+            for files in size_groups.values_mut() {
+                let (found, rest): (Vec<FileEntry>, Vec<FileEntry>) = std::mem::take(files)
+                    .into_iter()
+                    .partition(|f| similarity::is_image(&f.path));
+                images.extend(found);
+                *files = rest;
             }
         }
 
-        result
-            .duplicate_groups
-            .sort_by(|a, b| b.wasted_size.cmp(&a.wasted_size));
+        // Sparse files skip the size pre-filter, so they're hashed even without a size match
+        let buckets = hash_candidates(size_groups, result.sparse_files.len() > 1);
+
+        result.hash_candidates = buckets.iter().map(Vec::len).sum::<usize>() + images.len();
+        progress
+            .total
+            .store(result.hash_candidates, Ordering::Relaxed);
+        progress.start(ScanPhase::Hashing);
+
+        if !cancel_flag.load(Ordering::Relaxed) {
+            self.hash_and_group(buckets, images, progress, cancel_flag, &mut result);
+        }
+
+        // Groups confirmed before a cancel or the time limit are kept
+        result.duplicate_groups = progress.take_groups();
+        for group in &result.duplicate_groups {
+            result.total_duplicates += group.files.len() - 1;
+            result.wasted_space += group.wasted_size;
+        }
+
+        result
+            .duplicate_groups
+            .sort_by_key(|g| std::cmp::Reverse(g.wasted_size));
+        result.duplicate_folders =
+            find_duplicate_folders(&result.duplicate_groups, &dir_counts, folders);
 
         result
     }
 
-    /// Collect all files from directory with external cancel flag
-    fn collect_files_with_cancel(
+    /// Hash size buckets a batch at a time, publishing each batch's groups to
+    /// `progress` as soon as they are confirmed, then compare `images` by similarity
+    fn hash_and_group(
         &self,
-        path: &Path,
+        buckets: Vec<Vec<FileEntry>>,
+        images: Vec<FileEntry>,
+        progress: &ScanProgress,
         cancel_flag: &AtomicBool,
-        errors: &mut Vec<String>,
-    ) -> Vec<FileEntry> {
-        let mut files = Vec::new();
-
-        let walker = if self.config.recursive {
-            WalkDir::new(path).follow_links(false)
-        } else {
-            WalkDir::new(path).max_depth(1).follow_links(false)
+        result: &mut ScanResult,
+    ) {
+        let time_limit_reached = AtomicBool::new(false);
+        let bytes_hashed = AtomicU64::new(0);
+        let pools = match ReadPools::new(self.config.low_priority) {
+            Ok(pools) => pools,
+            Err(e) => {
+                result.errors.push(e);
+                return;
+            }
+        };
+        // Built once so the time budget and the pools cover every batch
+        let ctx = MatchContext {
+            progress_current: &progress.current,
+            bytes_hashed: &bytes_hashed,
+            current_file: &progress.current_file,
+            cancel_flag,
+            deadline: self
+                .config
+                .time_budget
+                .map(|budget| Instant::now() + budget),
+            time_limit_reached: &time_limit_reached,
+            pools: &pools,
         };
 
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
-            if cancel_flag.load(Ordering::Relaxed) {
+        for batch in batch_buckets(buckets, HASH_BATCH_FILES) {
+            if ctx.should_stop() {
                 break;
             }
+            let MatchOutput {
+                matched: hashed_files,
+                failed: failed_files,
+                locked: locked_files,
+            } = match self.matcher.assign_keys(batch, &ctx) {
+                Ok(output) => output,
+                Err(e) => {
+                    result.errors.push(e);
+                    break;
+                }
+            };
+            for (file, e) in &failed_files {
+                result
+                    .errors
+                    .push(format!("Failed to hash {}: {}", file.path.display(), e));
+            }
+            // Being open elsewhere isn't a read failure, so they're reported apart from errors
+            result
+                .locked_files
+                .extend(locked_files.iter().map(|f| f.path.clone()));
+            let failed_files = failed_files.into_iter().chain(
+                locked_files
+                    .into_iter()
+                    .map(|file| (file, LOCKED_FILE_ERROR.to_string())),
+            );
 
-            let entry_path = entry.path();
-
-            if entry_path.is_file() {
-                match fs::metadata(entry_path) {
-                    Ok(metadata) => {
-                        let size = metadata.len();
-                        if size >= self.config.min_size && size <= MAX_FILE_SIZE {
-                            let name = entry_path
-                                .file_name()
-                                .map(|n| n.to_string_lossy().to_string())
-                                .unwrap_or_default();
-
-                            files.push(FileEntry::new(entry_path.to_path_buf(), name, size));
-                        }
+            // Keep failed candidates visible; one of them may be a copy that was just locked
+            result
+                .unhashed_suspects
+                .extend(failed_files.map(|(file, error)| {
+                    UnhashedSuspect {
+                        peers: hashed_files
+                            .iter()
+                            .filter(|f| f.size == file.size)
+                            .cloned()
+                            .collect(),
+                        file,
+                        error,
                     }
-                    Err(e) => {
-                        errors.push(format!("Failed to read {}: {}", entry_path.display(), e));
+                }));
+
+            progress.publish(self.confirm_groups(hashed_files, cancel_flag, &mut result.errors));
+        }
+
+        // Similarity needs every image at once, so they're matched as a single batch
+        if !images.is_empty() && !ctx.should_stop() {
+            let matcher = PerceptualMatcher {
+                threshold: self.config.similarity_threshold,
+            };
+            match matcher.assign_keys(images, &ctx) {
+                Ok(output) => {
+                    for (file, e) in &output.failed {
+                        result.errors.push(format!(
+                            "Failed to read image {}: {}",
+                            file.path.display(),
+                            e
+                        ));
                     }
+                    progress.publish(self.similar_image_groups(output.matched));
                 }
+                Err(e) => result.errors.push(e),
             }
         }
 
-        files
+        result.time_limit_reached = time_limit_reached.into_inner();
+        result.bytes_hashed = bytes_hashed.into_inner();
     }
 
-    /// Hash files with thread limit and progress tracking
-    fn hash_files(
+    /// Turn hashed files into duplicate groups, byte-verifying them if configured
+    fn confirm_groups(
         &self,
-        files: Vec<FileEntry>,
-        progress_current: &AtomicUsize,
+        hashed_files: Vec<FileEntry>,
         cancel_flag: &AtomicBool,
         errors: &mut Vec<String>,
-    ) -> Vec<FileEntry> {
-        let results: Vec<Result<FileEntry, String>> = files
-            .par_iter()
-            .map(|file| {
+    ) -> Vec<DuplicateGroup> {
+        // Only exact hash matches are worth comparing byte for byte
+        let verify =
+            self.config.verify_bytes && self.matcher.confidence() == MatchConfidence::Exact;
+        let mut groups = Vec::new();
+
+        for (hash, files) in group_by_hash(hashed_files) {
+            if files.len() < 2 {
+                continue;
+            }
+            let (sets, confidence) = if verify {
                 if cancel_flag.load(Ordering::Relaxed) {
-                    return Err("Cancelled".to_string());
+                    break;
                 }
+                let sets = split_by_content(files, errors);
+                (sets, MatchConfidence::Verified)
+            } else {
+                (vec![files], self.matcher.confidence())
+            };
 
-                match compute_file_hash(&file.path) {
-                    Ok(hash) => {
-                        let mut hashed_file = file.clone();
-                        hashed_file.hash = Some(hash);
-                        progress_current.fetch_add(1, Ordering::Relaxed);
-                        Ok(hashed_file)
-                    }
-                    Err(e) => Err(format!("Failed to hash {}: {}", file.path.display(), e)),
+            for (idx, files) in sets.into_iter().enumerate() {
+                if files.len() < 2 {
+                    continue;
                 }
+                // Group hashes are used as keys, so split-off sets need their own
+                let hash = if idx == 0 {
+                    hash.clone()
+                } else {
+                    format!("{}#{}", hash, idx)
+                };
+                let mut group = DuplicateGroup::new(hash, files, GroupKind::Exact, confidence);
+                self.choose_keeper(&mut group);
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    /// Turn images keyed by `PerceptualMatcher` into similar-image groups
+    fn similar_image_groups(&self, images: Vec<FileEntry>) -> Vec<DuplicateGroup> {
+        group_by_hash(images)
+            .into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .map(|(hash, files)| {
+                let mut group = DuplicateGroup::new(
+                    hash,
+                    files,
+                    GroupKind::SimilarImage,
+                    MatchConfidence::Approximate,
+                );
+                group.apply_keep_rules(&self.keep_criteria(self.config.similar_keep_strategy));
+                group
             })
-            .collect();
+            .collect()
+    }
 
-        let mut hashed_files = Vec::new();
-        for result in results {
-            match result {
-                Ok(file) => hashed_files.push(file),
-                Err(e) if e != "Cancelled" => errors.push(e),
-                _ => {}
-            }
+    /// Re-hash a group's files as they are now and regroup them.
+    ///
+    /// Vanished or unreadable files are dropped, and files that no longer match
+    /// are split off; only sets that still share a hash are returned.
+    pub fn reverify_group(&self, group: &DuplicateGroup) -> Vec<DuplicateGroup> {
+        let mut by_hash: HashMap<String, Vec<FileEntry>> = HashMap::new();
+        for file in &group.files {
+            let Ok(metadata) = fs::metadata(&file.path) else {
+                continue;
+            };
+            let Ok(hash) = compute_file_hash(&file.path, self.config.effective_hash_algorithm())
+            else {
+                continue;
+            };
+            let mut file = file.clone();
+            file.size = metadata.len();
+            file.modified = metadata.modified().ok();
+            file.hash = Some(hash.clone());
+            by_hash.entry(hash).or_default().push(file);
         }
 
-        hashed_files
+        by_hash
+            .into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .map(|(hash, files)| {
+                let mut group =
+                    DuplicateGroup::new(hash, files, GroupKind::Exact, MatchConfidence::Exact);
+                self.choose_keeper(&mut group);
+                group
+            })
+            .collect()
     }
 
-    /// Group files by size
-    fn group_by_size(&self, files: Vec<FileEntry>) -> HashMap<u64, Vec<FileEntry>> {
-        let mut groups: HashMap<u64, Vec<FileEntry>> = HashMap::new();
+    /// Reorder a group so the configured keeper is at index 0
+    fn choose_keeper(&self, group: &mut DuplicateGroup) {
+        group.apply_keep_rules(&self.keep_criteria(self.config.keep_strategy));
+    }
 
-        for file in files {
-            groups.entry(file.size).or_default().push(file);
+    /// `strategy` as an ordered list of criteria
+    fn keep_criteria(&self, strategy: KeepStrategy) -> Vec<KeepCriterion> {
+        let mut criteria = match strategy {
+            KeepStrategy::Rules => self.config.keep_rules.clone(),
+            strategy => strategy.criteria(),
+        };
+        // Folder order is how people rank their inputs, so it's the implicit
+        // last word, except when the user picks every keeper themselves
+        if self.config.prefer_first_folder
+            && strategy != KeepStrategy::Manual
+            && !criteria.contains(&KeepCriterion::FolderPriority)
+        {
+            criteria.push(KeepCriterion::FolderPriority);
+        }
+        criteria
+    }
+
+    /// Find files whose content is a truncated prefix of a larger file.
+    ///
+    /// Only files sharing a name, or sharing a folder and a name once a
+    /// partial-download suffix is stripped, are compared.
+    fn find_partial_copies<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a FileEntry>,
+        cancel_flag: &AtomicBool,
+        errors: &mut Vec<String>,
+    ) -> Vec<DuplicateGroup> {
+        let mut candidate_sets: HashMap<String, Vec<&FileEntry>> = HashMap::new();
+        for file in files.into_iter().filter(|f| f.size > 0) {
+            let name = file.name.to_lowercase();
+            let base = PARTIAL_SUFFIXES
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+                .unwrap_or(&name);
+            let parent = file.path.parent().unwrap_or(Path::new(""));
+
+            candidate_sets
+                .entry(format!("name:{}", name))
+                .or_default()
+                .push(file);
+            candidate_sets
+                .entry(format!("dir:{}|{}", parent.display(), base))
+                .or_default()
+                .push(file);
+        }
+
+        let algorithm = self.config.effective_hash_algorithm();
+        let mut hash_cache: HashMap<(PathBuf, u64), Option<String>> = HashMap::new();
+        let mut prefix_hash = |path: &Path, len: u64, errors: &mut Vec<String>| {
+            hash_cache
+                .entry((path.to_path_buf(), len))
+                .or_insert_with(|| match compute_prefix_hash(path, len, algorithm) {
+                    Ok(hash) => Some(hash),
+                    Err(e) => {
+                        errors.push(format!("Failed to hash {}: {}", path.display(), e));
+                        None
+                    }
+                })
+                .clone()
+        };
+
+        let mut claimed: HashSet<PathBuf> = HashSet::new();
+        let mut groups = Vec::new();
+
+        for (_, mut set) in candidate_sets {
+            if set.len() < 2 {
+                continue;
+            }
+            set.sort_by_key(|f| std::cmp::Reverse(f.size));
+
+            for (i, complete) in set.iter().enumerate() {
+                if cancel_flag.load(Ordering::Relaxed) {
+                    return groups;
+                }
+                if claimed.contains(&complete.path) {
+                    continue;
+                }
+
+                let mut members = vec![(*complete).clone()];
+                let mut shared_hash: Option<String> = None;
+                for partial in &set[i + 1..] {
+                    if partial.size >= complete.size || claimed.contains(&partial.path) {
+                        continue;
+                    }
+                    let partial_hash = prefix_hash(&partial.path, partial.size, errors);
+                    if partial_hash.is_some()
+                        && partial_hash == prefix_hash(&complete.path, partial.size, errors)
+                    {
+                        if shared_hash.is_none() {
+                            shared_hash = partial_hash;
+                        }
+                        members.push((*partial).clone());
+                    }
+                }
+
+                if members.len() > 1 {
+                    for member in &members {
+                        claimed.insert(member.path.clone());
+                    }
+                    groups.push(DuplicateGroup::new(
+                        format!("partial:{}", shared_hash.unwrap_or_default()),
+                        members,
+                        GroupKind::PartialCopy,
+                        MatchConfidence::Probable,
+                    ));
+                }
+            }
         }
 
         groups
     }
 
-    /// Group files by hash
-    fn group_by_hash(&self, files: Vec<FileEntry>) -> HashMap<String, Vec<FileEntry>> {
-        let mut groups: HashMap<String, Vec<FileEntry>> = HashMap::new();
+    /// Walk a directory with external cancel flag, handing each file to `on_file`
+    /// and counting it in `files_found`
+    fn collect_files_with_cancel(
+        &self,
+        path: &Path,
+        cancel_flag: &AtomicBool,
+        files_found: &AtomicUsize,
+        notes: &mut CollectNotes,
+        mut on_file: impl FnMut(FileEntry),
+    ) {
+        let follow = self.config.follow_symlinks;
+        let mut walker = WalkDir::new(path).follow_links(follow);
+        if !self.config.recursive {
+            walker = walker.max_depth(1);
+        }
+
+        // Links can point back up the tree or at a sibling, so enter each real folder once
+        let mut visited_dirs = HashSet::new();
+        let mut entries = walker.into_iter();
+        // Checked before every entry, including skipped ones and errors, so a
+        // huge folder of files that are all filtered out still stops promptly
+        while let Some(entry) = entries.next() {
+            if cancel_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let is_dir = entry.file_type().is_dir();
+
+            // Excluded, hidden or system folders are pruned whole, but never the folder being scanned
+            let skipped = entry.depth() > 0
+                && ((is_dir && self.config.dir_excluded(entry.path()))
+                    || entry
+                        .metadata()
+                        .is_ok_and(|m| self.config.attributes_excluded(&m)));
+            let revisited = !skipped
+                && follow
+                && is_dir
+                && fs::canonicalize(entry.path()).is_ok_and(|real| !visited_dirs.insert(real));
+            if skipped || revisited {
+                if is_dir {
+                    entries.skip_current_dir();
+                }
+                continue;
+            }
+
+            let entry_path = entry.path();
+
+            if entry_path.is_file() && self.config.extension_allowed(entry_path) {
+                if let Some(file) = self.file_entry(entry_path, notes) {
+                    files_found.fetch_add(1, Ordering::Relaxed);
+                    on_file(file);
+                }
+            }
+        }
+    }
+
+    /// Note `path` in `slow_roots` if it's on a network or removable drive.
+    /// Returns whether it should be left out of the scan.
+    fn skip_drive(&self, path: &Path, slow_roots: &mut Vec<SlowRoot>) -> bool {
+        let kind = drive_kind(path);
+        if kind == DriveKind::Local {
+            return false;
+        }
+        let skipped = kind == DriveKind::Network && self.config.skip_network_drives;
+        slow_roots.push(SlowRoot {
+            path: path.to_path_buf(),
+            kind,
+            skipped,
+        });
+        skipped
+    }
 
-        for file in files {
-            if let Some(ref hash) = file.hash {
-                groups.entry(hash.clone()).or_default().push(file);
+    /// Build an entry for a single file, or `None` if it's filtered out or
+    /// unreadable. Zero-byte files are noted either way.
+    fn file_entry(&self, path: &Path, notes: &mut CollectNotes) -> Option<FileEntry> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                notes
+                    .errors
+                    .push(format!("Failed to read {}: {}", path.display(), e));
+                return None;
             }
+        };
+        let size = metadata.len();
+        if metadata.is_file() && size == 0 {
+            notes.empty.push(path.to_path_buf());
+        }
+        if !metadata.is_file() || !self.config.size_in_range(size) {
+            return None;
+        }
+        if self.config.verify_readable && !is_readable(path) {
+            notes.unreadable.push(path.to_path_buf());
+            return None;
         }
 
-        groups
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut file = FileEntry::new(path.to_path_buf(), name, size);
+        file.is_sparse = is_sparse(&metadata);
+        file.modified = metadata.modified().ok();
+        Some(file)
+    }
+
+    /// Whether walking `folder` with the current config would reach `path`
+    fn folder_covers(&self, folder: &Path, path: &Path) -> bool {
+        if self.config.recursive {
+            path.starts_with(folder)
+        } else {
+            path.parent() == Some(folder)
+        }
+    }
+}
+
+/// Bucket files by size, the first step in finding duplicates
+pub fn group_by_size(files: Vec<FileEntry>) -> HashMap<u64, Vec<FileEntry>> {
+    let mut groups: HashMap<u64, Vec<FileEntry>> = HashMap::new();
+    for file in files {
+        groups.entry(file.size).or_default().push(file);
     }
+    groups
 }
 
-/// Compute SHA-256 hash of a file with chunked reading and size limit
-fn compute_file_hash(path: &Path) -> std::io::Result<String> {
+/// Size buckets worth hashing, largest files first.
+///
+/// A file with no size match can't have a copy, unless it's sparse and
+/// `include_sparse` is set, since a sparse file's size can't be trusted.
+fn hash_candidates(
+    size_groups: HashMap<u64, Vec<FileEntry>>,
+    include_sparse: bool,
+) -> Vec<Vec<FileEntry>> {
+    let mut buckets: Vec<Vec<FileEntry>> = size_groups
+        .into_values()
+        .map(|files| {
+            if files.len() > 1 {
+                files
+            } else {
+                files
+                    .into_iter()
+                    .filter(|f| include_sparse && f.is_sparse)
+                    .collect()
+            }
+        })
+        .filter(|files| !files.is_empty())
+        .collect();
+    // Largest files first, so the groups wasting the most space are found early
+    buckets.sort_by_key(|files| std::cmp::Reverse(files[0].size));
+    buckets
+}
+
+/// Group files by hash; files without one are left out
+pub fn group_by_hash(files: Vec<FileEntry>) -> HashMap<String, Vec<FileEntry>> {
+    let mut groups: HashMap<String, Vec<FileEntry>> = HashMap::new();
+
+    for file in files {
+        if let Some(ref hash) = file.hash {
+            groups.entry(hash.clone()).or_default().push(file);
+        }
+    }
+
+    groups
+}
+
+/// Find exact duplicate groups among files whose `hash` is already filled in.
+///
+/// This is the scan pipeline without the disk: files are pruned by size,
+/// then grouped by size and hash, so only files of the same size can match.
+/// Each group keeps its files in input order, so the first listed is the
+/// keeper, and groups are sorted by wasted space.
+pub fn find_duplicate_groups(files: Vec<FileEntry>) -> Vec<DuplicateGroup> {
+    let candidates = hash_candidates(group_by_size(files), false);
+    let mut groups: Vec<DuplicateGroup> = candidates
+        .into_iter()
+        .flat_map(group_by_hash)
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, files)| {
+            DuplicateGroup::new(hash, files, GroupKind::Exact, MatchConfidence::Exact)
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.wasted_size
+            .cmp(&a.wasted_size)
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+    groups
+}
+
+/// Compute the hash of the first `len` bytes of a file
+fn compute_prefix_hash(path: &Path, len: u64, algorithm: HashAlgorithm) -> std::io::Result<String> {
+    let file = fs::File::open(path)?;
+    let mut reader = BufReader::new(file).take(len);
+    let mut hasher = ContentHasher::new(algorithm);
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Bytes read by `compute_quick_hash`
+pub const QUICK_HASH_SIZE: u64 = 4096;
+
+/// Cheap hash of the first `QUICK_HASH_SIZE` bytes, used to rule out same-sized files early
+pub(crate) fn compute_quick_hash(path: &Path) -> std::io::Result<String> {
+    compute_prefix_hash(path, QUICK_HASH_SIZE, HashAlgorithm::Xxh3)
+}
+
+/// Incremental hasher for whichever algorithm is configured
+enum ContentHasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<twox_hash::XxHash3_128>),
+}
+
+impl ContentHasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => ContentHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => ContentHasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Xxh3 => ContentHasher::Xxh3(Box::new(twox_hash::XxHash3_128::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            ContentHasher::Sha256(hasher) => hasher.update(data),
+            ContentHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            ContentHasher::Xxh3(hasher) => hasher.write(data),
+        }
+    }
+
+    /// Hex digest of everything hashed so far
+    fn finish(self) -> String {
+        match self {
+            ContentHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            ContentHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            ContentHasher::Xxh3(hasher) => format!("{:032x}", hasher.finish_128()),
+        }
+    }
+}
+
+impl std::io::Write for ContentHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Check whether a file uses sparse allocation
+#[cfg(windows)]
+fn is_sparse(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_SPARSE_FILE;
+
+    metadata.file_attributes() & FILE_ATTRIBUTE_SPARSE_FILE != 0
+}
+
+#[cfg(not(windows))]
+fn is_sparse(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Volume and file index of a file with more than one hard link
+#[cfg(windows)]
+fn hard_link_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+    };
+
+    let file = fs::File::open(path).ok()?;
+    // SAFETY: the handle stays open for the call and info is plain old data
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+        return None;
+    }
+    let index = (info.nFileIndexHigh as u64) << 32 | info.nFileIndexLow as u64;
+    (info.nNumberOfLinks > 1).then_some((info.dwVolumeSerialNumber as u64, index))
+}
+
+#[cfg(unix)]
+fn hard_link_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).ok()?;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(any(windows, unix)))]
+fn hard_link_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Check that a file can actually be opened and read from
+fn is_readable(path: &Path) -> bool {
+    let mut buffer = [0u8; 1];
+    fs::File::open(path)
+        .and_then(|mut file| file.read(&mut buffer))
+        .is_ok()
+}
+
+/// Pack size buckets into batches of at least `min_files` candidates.
+///
+/// Files of different sizes can never match, so a bucket is never split
+/// and each batch's groups are final once it's hashed.
+fn batch_buckets(buckets: Vec<Vec<FileEntry>>, min_files: usize) -> Vec<Vec<FileEntry>> {
+    let mut batches = Vec::new();
+    let mut batch: Vec<FileEntry> = Vec::new();
+    for bucket in buckets {
+        batch.extend(bucket);
+        if batch.len() >= min_files {
+            batches.push(std::mem::take(&mut batch));
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Split hash-matched files into sets whose contents are byte-identical.
+///
+/// Each file is compared against the first file of every set so far, which
+/// is a single comparison per file unless the hash really collided. Files
+/// that can't be read are dropped with an error.
+fn split_by_content(files: Vec<FileEntry>, errors: &mut Vec<String>) -> Vec<Vec<FileEntry>> {
+    let mut sets: Vec<Vec<FileEntry>> = Vec::new();
+    'files: for file in files {
+        for set in &mut sets {
+            match files_are_identical(&set[0].path, &file.path) {
+                Ok(true) => {
+                    set.push(file);
+                    continue 'files;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    errors.push(format!("Failed to verify {}: {}", file.path.display(), e));
+                    continue 'files;
+                }
+            }
+        }
+        sets.push(vec![file]);
+    }
+    sets
+}
+
+/// Compare two files byte for byte, stopping at the first difference
+pub fn files_are_identical(a: &Path, b: &Path) -> std::io::Result<bool> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let mut reader_a = BufReader::with_capacity(CHUNK_SIZE, fs::File::open(a)?);
+    let mut reader_b = BufReader::with_capacity(CHUNK_SIZE, fs::File::open(b)?);
+    let mut chunk_a = vec![0u8; CHUNK_SIZE];
+    let mut chunk_b = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        let len_a = read_chunk(&mut reader_a, &mut chunk_a)?;
+        let len_b = read_chunk(&mut reader_b, &mut chunk_b)?;
+        if chunk_a[..len_a] != chunk_b[..len_b] {
+            return Ok(false);
+        }
+        if len_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill `buffer` as far as the reader allows; shorter only at end of file
+fn read_chunk(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Whether an I/O error means another process has the file open or locked,
+/// a condition that usually clears once that program lets go
+pub fn is_lock_error(error: &std::io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    #[cfg(windows)]
+    if matches!(error.raw_os_error(), Some(32 | 33)) {
+        return true;
+    }
+    error.kind() == std::io::ErrorKind::ResourceBusy
+}
+
+/// Compute the hash of a file with chunked reading and size limit
+pub(crate) fn compute_file_hash(path: &Path, algorithm: HashAlgorithm) -> std::io::Result<String> {
+    compute_file_hash_with(path, algorithm, None)
+}
+
+/// Like `compute_file_hash`, but memory-maps local files of at least
+/// `mmap_threshold` bytes, falling back to buffered reads where mapping fails
+pub(crate) fn compute_file_hash_with(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    mmap_threshold: Option<u64>,
+) -> std::io::Result<String> {
     let metadata = fs::metadata(path)?;
 
     if metadata.len() > MAX_FILE_SIZE {
@@ -355,8 +2029,24 @@ fn compute_file_hash(path: &Path) -> std::io::Result<String> {
     const BUFFER_SIZE: usize = 1024 * 1024;
 
     let file = fs::File::open(path)?;
+    let mut hasher = ContentHasher::new(algorithm);
+    // A mapped file whose drive disappears mid-read faults (SIGBUS) rather
+    // than returning an error, so only map files on fixed local drives
+    let map = mmap_threshold.is_some_and(|threshold| metadata.len() >= threshold)
+        && drive_kind(path) == DriveKind::Local;
+    if map {
+        // SAFETY: the map is read-only and dropped before returning. Another
+        // process truncating the file meanwhile could fault the read, the
+        // usual caveat of mapping files that is accepted for hashing speed.
+        if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+            #[cfg(unix)]
+            let _ = map.advise(memmap2::Advice::Sequential);
+            hasher.update(&map);
+            return Ok(hasher.finish());
+        }
+    }
+
     let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
-    let mut hasher = Sha256::new();
     let mut buffer = vec![0u8; BUFFER_SIZE];
 
     loop {
@@ -367,8 +2057,96 @@ fn compute_file_hash(path: &Path) -> std::io::Result<String> {
         hasher.update(&buffer[..bytes_read]);
     }
 
-    let hash = hasher.finalize();
-    Ok(format!("{:x}", hash))
+    Ok(hasher.finish())
+}
+
+/// Multi-part extensions kept together when splitting file names
+const COMPOUND_EXTENSIONS: &[&str] = &["tar.gz", "tar.bz2", "tar.xz", "tar.zst"];
+
+/// Split a file name into stem and extension, keeping the original case.
+///
+/// Known compound extensions (`.tar.gz`) stay together and dotfiles such as
+/// `.bashrc` have no extension.
+pub fn split_extension(name: &str) -> (&str, &str) {
+    let lower = name.to_ascii_lowercase();
+    for compound in COMPOUND_EXTENSIONS {
+        let suffix_len = compound.len() + 1;
+        if lower.len() > suffix_len && lower.ends_with(&format!(".{}", compound)) {
+            let split = name.len() - suffix_len;
+            return (&name[..split], &name[split + 1..]);
+        }
+    }
+    match name.rfind('.') {
+        Some(0) | None => (name, ""),
+        Some(idx) => (&name[..idx], &name[idx + 1..]),
+    }
+}
+
+/// Lowercase extension of a path, used for file type detection and filtering
+pub fn normalized_extension(path: &Path) -> String {
+    path.file_name()
+        .map(|name| split_extension(&name.to_string_lossy()).1.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Parse a user-typed list such as `"JPG, .png *.gif"` into lowercase extensions
+pub fn parse_extension_list(text: &str) -> Vec<String> {
+    let mut extensions: Vec<String> = Vec::new();
+    for item in text.split([',', ';', ' ']) {
+        let ext = item.trim().trim_start_matches('*').trim_start_matches('.');
+        let ext = ext.to_lowercase();
+        if !ext.is_empty() && !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    extensions
+}
+
+/// Whether `path` lies inside any of the protected folders
+pub fn is_protected(path: &Path, protected: &[PathBuf]) -> bool {
+    protected.iter().any(|folder| path.starts_with(folder))
+}
+
+/// Case-insensitive wildcard match of `pattern` against a path.
+///
+/// `*` matches any run of characters and `?` a single one; `\` and `/` are
+/// interchangeable. A pattern without wildcards matches anywhere in the path.
+pub fn matches_wildcard(path: &Path, pattern: &str) -> bool {
+    let normalize = |s: &str| s.to_lowercase().replace('\\', "/");
+    let mut pattern = normalize(pattern.trim());
+    if pattern.is_empty() {
+        return false;
+    }
+    if !pattern.contains(['*', '?']) {
+        pattern = format!("*{}*", pattern);
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = normalize(&path.to_string_lossy()).chars().collect();
+
+    // Greedy scan that backtracks to the most recent `*` on a mismatch
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// Format bytes into human-readable size
@@ -400,4 +2178,939 @@ mod tests {
         assert_eq!(format_size(1048576), "1.00 MB");
         assert_eq!(format_size(1073741824), "1.00 GB");
     }
+
+    #[test]
+    fn test_hash_algorithms() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("abc");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            compute_file_hash(&path, HashAlgorithm::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            compute_file_hash(&path, HashAlgorithm::Blake3).unwrap(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(
+            compute_prefix_hash(&path, 3, HashAlgorithm::Blake3).unwrap(),
+            compute_file_hash(&path, HashAlgorithm::Blake3).unwrap()
+        );
+        for algorithm in HashAlgorithm::ALL.into_iter().chain([HashAlgorithm::Xxh3]) {
+            assert_eq!(
+                compute_file_hash_with(&path, algorithm, Some(0)).unwrap(),
+                compute_file_hash(&path, algorithm).unwrap()
+            );
+        }
+
+        // Results saved before the algorithm was recorded were hashed with SHA-256
+        let old: ScanResult = serde_json::from_str("{}").unwrap();
+        assert_eq!(old.hash_algorithm, HashAlgorithm::Sha256);
+    }
+
+    #[test]
+    fn test_extension_normalization() {
+        assert_eq!(split_extension("Backup.TAR.GZ"), ("Backup", "TAR.GZ"));
+        assert_eq!(
+            normalized_extension(Path::new("/a/Backup.TAR.GZ")),
+            "tar.gz"
+        );
+        assert_eq!(normalized_extension(Path::new("photo.v2.JPG")), "jpg");
+
+        // No extension, trailing dot and dotfiles
+        assert_eq!(split_extension("Makefile"), ("Makefile", ""));
+        assert_eq!(split_extension("notes."), ("notes", ""));
+        assert_eq!(split_extension(".bashrc"), (".bashrc", ""));
+        assert_eq!(split_extension(".tar.gz"), (".tar", "gz"));
+        assert_eq!(normalized_extension(Path::new(".config.JSON")), "json");
+        assert_eq!(normalized_extension(Path::new("/")), "");
+    }
+
+    #[test]
+    fn test_keep_strategy_orders_by_size() {
+        let files = vec![
+            FileEntry::new(PathBuf::from("a.mp3"), "a.mp3".into(), 300),
+            FileEntry::new(PathBuf::from("a.flac"), "a.flac".into(), 900),
+            FileEntry::new(PathBuf::from("a_small.mp3"), "a_small.mp3".into(), 100),
+        ];
+        let mut group = DuplicateGroup {
+            hash: "x".into(),
+            total_size: 1300,
+            wasted_size: 1000,
+            files,
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+
+        group.apply_keep_strategy(KeepStrategy::Largest);
+        assert_eq!(group.files[0].name, "a.flac");
+        assert_eq!(group.wasted_size, 400);
+
+        group.apply_keep_strategy(KeepStrategy::Smallest);
+        assert_eq!(group.files[0].name, "a_small.mp3");
+        assert_eq!(group.wasted_size, 1200);
+
+        group.apply_keep_strategy(KeepStrategy::LongestPath);
+        assert_eq!(group.files[0].name, "a_small.mp3");
+        assert!(group.set_keeper(2));
+        assert_eq!(group.files[0].size, 300);
+        assert_eq!(group.files[2].name, "a_small.mp3");
+        assert_eq!(group.wasted_size, 1000);
+        assert!(!group.set_keeper(0));
+    }
+
+    #[test]
+    fn test_wasted_size_follows_keeper_in_mixed_size_group() {
+        let file = |name: &str, size| FileEntry::new(PathBuf::from(name), name.into(), size);
+        let mut group = DuplicateGroup::new(
+            "similar:1".into(),
+            vec![
+                file("small.jpg", 100),
+                file("large.png", 700),
+                file("mid.webp", 200),
+            ],
+            GroupKind::SimilarImage,
+            MatchConfidence::Approximate,
+        );
+        assert_eq!((group.total_size, group.wasted_size), (1000, 900));
+
+        assert!(group.set_keeper_matching("*.png"));
+        assert_eq!(group.wasted_size, 300);
+        group.apply_keep_strategy(KeepStrategy::Smallest);
+        assert_eq!(group.wasted_size, 900);
+
+        // A stale total is corrected rather than underflowing
+        group.total_size = 0;
+        assert!(group.set_keeper(1));
+        assert_eq!((group.total_size, group.wasted_size), (1000, 800));
+
+        let mut result = ScanResult {
+            duplicate_groups: vec![group],
+            ..Default::default()
+        };
+        result.remove_paths(&HashSet::from([PathBuf::from("mid.webp")]));
+        let group = &result.duplicate_groups[0];
+        assert_eq!((group.total_size, group.wasted_size), (800, 700));
+    }
+
+    #[test]
+    fn test_keep_rules_break_ties_in_order() {
+        let at = |secs| Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        let entry = |path: &str, modified, root_index| {
+            let mut file = FileEntry::new(PathBuf::from(path), path.to_string(), 10);
+            file.modified = modified;
+            file.root_index = root_index;
+            file
+        };
+        let mut group = DuplicateGroup {
+            hash: "x".into(),
+            files: vec![
+                entry("/long/path/a", at(100), 0),
+                entry("/b/old", at(50), 1),
+                entry("/b/new", at(200), 1),
+                entry("/c/new", at(200), 0),
+            ],
+            total_size: 40,
+            wasted_size: 30,
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+
+        // Path length ties between the three short paths, newest wins, folder order breaks the rest
+        group.apply_keep_rules(&[
+            KeepCriterion::ShortestPath,
+            KeepCriterion::Newest,
+            KeepCriterion::FolderPriority,
+        ]);
+        let order: Vec<&str> = group.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(order, ["/c/new", "/b/new", "/b/old", "/long/path/a"]);
+
+        group.apply_keep_rules(&[KeepCriterion::Oldest]);
+        assert_eq!(group.files[0].name, "/b/old");
+
+        group.apply_keep_rules(&[KeepCriterion::LongestPath]);
+        assert_eq!(group.files[0].name, "/long/path/a");
+    }
+
+    #[test]
+    fn test_group_with_only_protected_copies_is_not_actionable() {
+        let files = vec![
+            FileEntry::new(PathBuf::from("/keep/a.jpg"), "a.jpg".into(), 10),
+            FileEntry::new(PathBuf::from("/archive/a.jpg"), "a.jpg".into(), 10),
+            FileEntry::new(PathBuf::from("/archive/old/a.jpg"), "a.jpg".into(), 10),
+        ];
+        let group = DuplicateGroup {
+            hash: "x".into(),
+            files,
+            total_size: 30,
+            wasted_size: 20,
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+
+        assert!(group.is_actionable(&[]));
+        assert!(!group.is_actionable(&[PathBuf::from("/archive")]));
+        // Protecting only the keeper's folder leaves the copies actionable
+        assert!(group.is_actionable(&[PathBuf::from("/keep")]));
+        // Prefix matching is by path component, not by string
+        assert!(group.is_actionable(&[PathBuf::from("/arch")]));
+    }
+
+    #[test]
+    fn test_keeper_pattern_override() {
+        assert!(matches_wildcard(
+            Path::new("D:/Backup/Archive/a.jpg"),
+            "\\archive\\"
+        ));
+        assert!(matches_wildcard(Path::new("/x/IMG_01.JPG"), "*/img_??.jpg"));
+        assert!(!matches_wildcard(
+            Path::new("/x/img_001.jpg"),
+            "*/img_??.jpg"
+        ));
+        assert!(!matches_wildcard(Path::new("/x/a.jpg"), "  "));
+
+        let group = |hash: &str, paths: &[&str]| DuplicateGroup {
+            hash: hash.into(),
+            files: paths
+                .iter()
+                .map(|p| FileEntry::new(PathBuf::from(p), "a.jpg".into(), 10))
+                .collect(),
+            total_size: 10 * paths.len() as u64,
+            wasted_size: 10 * (paths.len() as u64 - 1),
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+        let mut result = ScanResult {
+            duplicate_groups: vec![
+                group(
+                    "a",
+                    &[
+                        "/home/a.jpg",
+                        "/old/a.jpg",
+                        "/Archive/2/a.jpg",
+                        "/Archive/1/a.jpg",
+                    ],
+                ),
+                group("b", &["/Archive/b.jpg", "/home/b.jpg"]),
+                group("c", &["/home/c.jpg", "/tmp/c.jpg"]),
+            ],
+            ..Default::default()
+        };
+
+        // Only group "a" changes; "b" already keeps the archive copy
+        assert_eq!(result.apply_keeper_pattern("*/archive/*"), 1);
+        let order: Vec<_> = result.duplicate_groups[0]
+            .files
+            .iter()
+            .map(|f| f.path.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "/Archive/2/a.jpg",
+                "/home/a.jpg",
+                "/old/a.jpg",
+                "/Archive/1/a.jpg"
+            ]
+        );
+        assert_eq!(result.apply_keeper_pattern("*/archive/*"), 0);
+    }
+
+    #[test]
+    fn test_time_budget_keeps_partial_result() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a"), b"same").unwrap();
+        fs::write(dir.join("b"), b"same").unwrap();
+
+        let scan = |time_budget| {
+            Scanner::new(ScannerConfig {
+                time_budget,
+                ..Default::default()
+            })
+            .scan_directory(dir)
+        };
+        let limited = scan(Some(Duration::ZERO));
+        let unlimited = scan(Some(Duration::from_secs(600)));
+
+        assert!(limited.time_limit_reached);
+        assert!(limited.duplicate_groups.is_empty());
+        assert!(limited.errors.is_empty());
+        assert!(!unlimited.time_limit_reached);
+        assert_eq!(unlimited.duplicate_groups.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_paths_prunes_resolved_groups() {
+        let group = |hash: &str, names: &[&str]| DuplicateGroup {
+            hash: hash.into(),
+            files: names
+                .iter()
+                .map(|n| FileEntry::new(PathBuf::from(n), n.to_string(), 10))
+                .collect(),
+            total_size: 10 * names.len() as u64,
+            wasted_size: 10 * (names.len() as u64 - 1),
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+        let mut result = ScanResult {
+            duplicate_groups: vec![group("a", &["a1", "a2"]), group("b", &["b1", "b2", "b3"])],
+            ..Default::default()
+        };
+
+        result.remove_paths(&HashSet::from([PathBuf::from("a2"), PathBuf::from("b3")]));
+
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.duplicate_groups[0].hash, "b");
+        assert_eq!(result.total_duplicates, 1);
+        assert_eq!(result.wasted_space, 10);
+
+        // Renamed files stay in their group under the new name
+        result.rename_paths(&HashMap::from([(
+            PathBuf::from("b2"),
+            PathBuf::from("dir").join("copy.txt"),
+        )]));
+        let renamed = &result.duplicate_groups[0].files[1];
+        assert_eq!(renamed.path, PathBuf::from("dir").join("copy.txt"));
+        assert_eq!(renamed.name, "copy.txt");
+
+        // Removing a folder takes everything inside it along
+        result.remove_paths(&HashSet::from([PathBuf::from("dir")]));
+        assert!(result.duplicate_groups.is_empty());
+    }
+
+    #[test]
+    fn test_wasted_by_folder_counts_only_non_keepers() {
+        let group = |size: u64, paths: &[&str]| DuplicateGroup {
+            hash: paths[0].into(),
+            files: paths
+                .iter()
+                .map(|p| FileEntry::new(PathBuf::from(p), p.to_string(), size))
+                .collect(),
+            total_size: size * paths.len() as u64,
+            wasted_size: size * (paths.len() as u64 - 1),
+            kind: GroupKind::Exact,
+            confidence: MatchConfidence::Exact,
+        };
+        let result = ScanResult {
+            duplicate_groups: vec![
+                group(10, &["photos/a", "backup/a", "backup/old/a"]),
+                group(50, &["photos/b", "backup/b"]),
+                group(5, &["backup/c", "photos/c"]),
+            ],
+            ..Default::default()
+        };
+
+        let folders = result.wasted_by_folder();
+        let summary: Vec<(&str, usize, u64)> = folders
+            .iter()
+            .map(|f| (f.folder.to_str().unwrap(), f.duplicates, f.wasted_size))
+            .collect();
+        assert_eq!(
+            summary,
+            [("backup", 2, 60), ("backup/old", 1, 10), ("photos", 1, 5)]
+        );
+    }
+
+    #[test]
+    fn test_detects_truncated_download() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("video.mp4"), b"complete file contents").unwrap();
+        fs::write(dir.join("video.mp4.crdownload"), b"complete fi").unwrap();
+        fs::write(dir.join("other.bin"), b"unrelated").unwrap();
+
+        let scanner = Scanner::new(ScannerConfig {
+            detect_partial_copies: true,
+            ..Default::default()
+        });
+        let result = scanner.scan_directory(dir);
+
+        assert_eq!(result.duplicate_groups.len(), 1);
+        let group = &result.duplicate_groups[0];
+        assert_eq!(group.kind, GroupKind::PartialCopy);
+        assert_eq!(group.confidence, MatchConfidence::Probable);
+        assert_eq!(group.files[0].name, "video.mp4");
+        assert_eq!(group.files[1].name, "video.mp4.crdownload");
+        assert_eq!(group.wasted_size, 11);
+    }
+
+    #[test]
+    fn test_retry_suspect_joins_matching_peer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a.bin"), b"payload").unwrap();
+        fs::write(dir.join("b.bin"), b"payload").unwrap();
+        fs::write(dir.join("c.bin"), b"PAYLOAD").unwrap();
+
+        let entry = |name: &str| FileEntry::new(dir.join(name), name.into(), 7);
+        let mut peer = entry("a.bin");
+        peer.hash = Some(compute_file_hash(&peer.path, HashAlgorithm::default()).unwrap());
+        let suspect = |name: &str| UnhashedSuspect {
+            file: entry(name),
+            error: "locked".into(),
+            peers: vec![peer.clone()],
+        };
+        let mut result = ScanResult {
+            unhashed_suspects: vec![suspect("c.bin"), suspect("b.bin"), suspect("gone.bin")],
+            locked_files: vec![dir.join("b.bin"), dir.join("gone.bin")],
+            ..Default::default()
+        };
+
+        // Different content: resolved as unique
+        assert_eq!(result.retry_suspect(0), Ok(false));
+        assert_eq!(result.retry_suspect(0), Ok(true));
+        assert_eq!(result.locked_files, [dir.join("gone.bin")]);
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.duplicate_groups[0].files[0].name, "a.bin");
+        assert_eq!(result.wasted_space, 7);
+        // Still unreadable: stays listed
+        assert!(result.retry_suspect(0).is_err());
+        assert_eq!(result.unhashed_suspects.len(), 1);
+        assert_eq!(result.locked_files.len(), 1);
+
+        assert!(is_lock_error(&std::io::ErrorKind::ResourceBusy.into()));
+        assert!(!is_lock_error(&std::io::ErrorKind::NotFound.into()));
+        #[cfg(windows)]
+        assert!(is_lock_error(&std::io::Error::from_raw_os_error(32)));
+    }
+
+    #[test]
+    fn test_first_added_folder_wins_ties() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for sub in ["backup", "primary"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+            fs::write(dir.join(sub).join("photo.jpg"), b"same bytes").unwrap();
+        }
+        // Equal sizes tie under Largest, so folder order decides
+        let scanner = Scanner::new(ScannerConfig {
+            keep_strategy: KeepStrategy::Largest,
+            ..Default::default()
+        });
+        let result = scanner.scan_directories(&[dir.join("primary"), dir.join("backup")]);
+        assert_eq!(result.duplicate_groups[0].files[0].root_index, 0);
+        assert!(result.duplicate_groups[0].files[0]
+            .path
+            .starts_with(dir.join("primary")));
+
+        let scanner = Scanner::new(ScannerConfig {
+            keep_strategy: KeepStrategy::Rules,
+            keep_rules: vec![KeepCriterion::Newest],
+            prefer_first_folder: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            scanner.keep_criteria(KeepStrategy::Rules),
+            [KeepCriterion::Newest]
+        );
+
+        let scanner = Scanner::new(ScannerConfig {
+            keep_strategy: KeepStrategy::Manual,
+            ..Default::default()
+        });
+        assert!(scanner.config.prefer_first_folder);
+        assert!(scanner.keep_criteria(KeepStrategy::Manual).is_empty());
+    }
+
+    #[test]
+    fn test_reverify_splits_changed_and_drops_missing_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for name in ["a", "b", "c", "d"] {
+            fs::write(dir.join(name), b"original").unwrap();
+        }
+        let scanner = Scanner::new(ScannerConfig::default());
+        let mut result = scanner.scan_directory(dir);
+        let group = result.duplicate_groups[0].clone();
+        assert_eq!(group.files.len(), 4);
+
+        fs::write(dir.join("c"), b"modified").unwrap();
+        fs::write(dir.join("d"), b"modified").unwrap();
+        fs::remove_file(dir.join("b")).unwrap();
+        let regrouped = scanner.reverify_group(&group);
+
+        // "a" is now alone; "c" and "d" match each other under a new hash
+        assert_eq!(regrouped.len(), 1);
+        let names: HashSet<&str> = regrouped[0].files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, HashSet::from(["c", "d"]));
+
+        result.replace_group(&group.hash, regrouped);
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.total_duplicates, 1);
+        assert_eq!(result.wasted_space, 8);
+
+        // Replacements take the replaced group's place
+        let pair = |hash: &str, size| {
+            DuplicateGroup::new(
+                hash.into(),
+                vec![hashed("a", size, hash), hashed("b", size, hash)],
+                GroupKind::Exact,
+                MatchConfidence::Exact,
+            )
+        };
+        let mut result = ScanResult {
+            duplicate_groups: vec![pair("x", 1), pair("y", 2), pair("z", 3)],
+            ..Default::default()
+        };
+        result.replace_group("y", vec![pair("y1", 20), pair("y2", 10)]);
+        let order: Vec<&str> = result
+            .duplicate_groups
+            .iter()
+            .map(|g| g.hash.as_str())
+            .collect();
+        assert_eq!(order, ["x", "y1", "y2", "z"]);
+    }
+
+    #[test]
+    fn test_size_filter_skips_small_and_large_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for (name, len) in [("tiny_a", 2), ("tiny_b", 2), ("mid_a", 20), ("mid_b", 20)] {
+            fs::write(dir.join(name), vec![b'x'; len]).unwrap();
+        }
+        fs::write(dir.join("big_a"), vec![b'y'; 200]).unwrap();
+        fs::write(dir.join("big_b"), vec![b'y'; 200]).unwrap();
+        fs::write(dir.join("empty"), b"").unwrap();
+
+        let config = ScannerConfig {
+            min_size: 10,
+            max_size: 100,
+            ..Default::default()
+        };
+        let result = Scanner::new(config).scan_directory(dir);
+
+        // Filtered out, but still reported
+        assert_eq!(result.empty_files, 1);
+        assert_eq!(result.zero_byte_paths, [dir.join("empty")]);
+        assert_eq!(result.total_files, 2);
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert!(result.duplicate_groups[0]
+            .files
+            .iter()
+            .all(|f| f.size == 20));
+    }
+
+    #[test]
+    fn test_extension_filters() {
+        assert_eq!(
+            parse_extension_list("JPG, .png;*.GIF  jpg,"),
+            ["jpg", "png", "gif"]
+        );
+
+        let mut config = ScannerConfig {
+            include_extensions: Some(parse_extension_list("jpg, gz")),
+            ..Default::default()
+        };
+        assert!(config.extension_allowed(Path::new("C:/Photos/IMG_1.JPG")));
+        assert!(config.extension_allowed(Path::new("backup.tar.gz")));
+        assert!(!config.extension_allowed(Path::new("notes.txt")));
+        assert!(config.extension_allowed(Path::new("README")));
+        config.include_no_extension = false;
+        assert!(!config.extension_allowed(Path::new("README")));
+
+        let config = ScannerConfig {
+            exclude_extensions: parse_extension_list("tmp"),
+            ..Default::default()
+        };
+        assert!(!config.extension_allowed(Path::new("a.TMP")));
+        assert!(config.extension_allowed(Path::new("a.txt")));
+    }
+
+    #[test]
+    fn test_excluded_folders_are_pruned() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for sub in ["keep", "Node_Modules/pkg", "backup"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+            fs::write(dir.join(sub).join("a.txt"), b"same").unwrap();
+        }
+        fs::write(dir.join("b.txt"), b"same").unwrap();
+
+        let result = Scanner::new(ScannerConfig {
+            exclude_paths: vec![dir.join("backup")],
+            exclude_dir_names: vec!["node_modules".into()],
+            ..Default::default()
+        })
+        .scan_directory(dir);
+
+        assert_eq!(result.total_files, 2);
+        assert_eq!(result.duplicate_groups.len(), 1);
+    }
+
+    /// Gives every candidate the same key, like a hash collision would
+    struct CollidingMatcher;
+
+    impl DuplicateMatcher for CollidingMatcher {
+        fn assign_keys(
+            &self,
+            candidates: Vec<FileEntry>,
+            _ctx: &MatchContext,
+        ) -> Result<crate::matcher::MatchOutput, String> {
+            Ok(crate::matcher::MatchOutput {
+                matched: candidates
+                    .into_iter()
+                    .map(|mut file| {
+                        file.hash = Some("collision".into());
+                        file
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_verify_bytes_splits_colliding_group() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a"), b"same").unwrap();
+        fs::write(dir.join("b"), b"same").unwrap();
+        fs::write(dir.join("c"), b"diff").unwrap();
+        assert!(files_are_identical(&dir.join("a"), &dir.join("b")).unwrap());
+        assert!(!files_are_identical(&dir.join("a"), &dir.join("c")).unwrap());
+
+        let scan = |verify_bytes| {
+            let config = ScannerConfig {
+                verify_bytes,
+                ..Default::default()
+            };
+            Scanner::new(config)
+                .with_matcher(Box::new(CollidingMatcher))
+                .scan_directory(dir)
+        };
+        let unverified = scan(false);
+        let verified = scan(true);
+
+        assert_eq!(unverified.duplicate_groups[0].files.len(), 3);
+        assert_eq!(verified.duplicate_groups.len(), 1);
+        let group = &verified.duplicate_groups[0];
+        assert_eq!(group.files.len(), 2);
+        assert_eq!(group.confidence, MatchConfidence::Verified);
+        assert!(group.files.iter().all(|f| f.name != "c"));
+    }
+
+    /// Matches every candidate, then cancels the scan
+    struct CancellingMatcher;
+
+    impl DuplicateMatcher for CancellingMatcher {
+        fn assign_keys(
+            &self,
+            candidates: Vec<FileEntry>,
+            ctx: &MatchContext,
+        ) -> Result<crate::matcher::MatchOutput, String> {
+            ctx.cancel_flag.store(true, Ordering::Relaxed);
+            CollidingMatcher.assign_keys(candidates, ctx)
+        }
+    }
+
+    #[test]
+    fn test_groups_found_before_cancel_are_kept() {
+        let sized = |size: u64| FileEntry::new(PathBuf::from("f"), "f".into(), size);
+        let batches = batch_buckets(
+            vec![vec![sized(3); 2], vec![sized(2); 3], vec![sized(1); 2]],
+            4,
+        );
+        let lens: Vec<usize> = batches.iter().map(Vec::len).collect();
+        assert_eq!(lens, [5, 2]);
+
+        // The larger files fill the first batch; the small pair is never reached
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for i in 0..HASH_BATCH_FILES {
+            fs::write(dir.join(format!("big{}", i)), b"big").unwrap();
+        }
+        fs::write(dir.join("small1"), b"s").unwrap();
+        fs::write(dir.join("small2"), b"s").unwrap();
+
+        let progress = ScanProgress::default();
+        let result = Scanner::new(ScannerConfig::default())
+            .with_matcher(Box::new(CancellingMatcher))
+            .scan_directories_with_progress(
+                &[dir.to_path_buf()],
+                &progress,
+                &AtomicBool::new(false),
+            );
+
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.duplicate_groups[0].files.len(), HASH_BATCH_FILES);
+        assert_eq!(result.total_duplicates, HASH_BATCH_FILES - 1);
+        assert_eq!(progress.groups_found(), 0);
+    }
+
+    #[test]
+    fn test_scan_loose_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), b"same").unwrap();
+        fs::write(dir.join("sub").join("b.txt"), b"same").unwrap();
+        fs::write(dir.join("sub").join("c.txt"), b"same").unwrap();
+
+        // Only the listed files are compared; c.txt is never walked
+        let scanner = Scanner::new(ScannerConfig::default());
+        let listed = [dir.join("a.txt"), dir.join("sub").join("b.txt")];
+        let result = scanner.scan_files(&listed);
+
+        // A loose file inside a scanned folder isn't counted twice
+        let progress = ScanProgress::default();
+        let overlap = scanner.scan_paths_with_progress(
+            &[dir.join("sub")],
+            &[dir.join("sub").join("b.txt")],
+            &progress,
+            &AtomicBool::new(false),
+        );
+
+        assert_eq!(result.total_files, 2);
+        assert!(result.folders.is_empty());
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.duplicate_groups[0].files.len(), 2);
+        assert_eq!(overlap.folders, [dir.join("sub")]);
+        assert_eq!(overlap.total_files, 2);
+        assert_eq!(overlap.duplicate_groups[0].files.len(), 2);
+        assert_eq!(progress.phase(), ScanPhase::Hashing);
+        assert_eq!(progress.files_found.load(Ordering::Relaxed), 2);
+        assert_eq!(progress.current.load(Ordering::Relaxed), 2);
+        assert_eq!(overlap.bytes_hashed, 8);
+        assert!(overlap.timing_summary().is_some());
+    }
+
+    #[test]
+    fn test_streamed_collection_matches_content_grouping() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        // A deep chain of folders, each holding a few files that repeat across levels
+        let mut level = dir.to_path_buf();
+        for depth in 0..40 {
+            level = level.join(format!("d{}", depth));
+            fs::create_dir_all(&level).unwrap();
+            fs::write(level.join("a.txt"), format!("content {}", depth % 3)).unwrap();
+            fs::write(level.join("b.txt"), format!("unique {}", depth)).unwrap();
+        }
+
+        let scanner = Scanner::new(ScannerConfig {
+            retain_all_files: true,
+            ..Default::default()
+        });
+        let result = scanner.scan_directory(dir);
+
+        // Brute-force reference: hash every retained file and group by content
+        let mut expected: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for file in &result.all_files {
+            let hash = compute_file_hash(&file.path, HashAlgorithm::default()).unwrap();
+            expected.entry(hash).or_default().push(file.path.clone());
+        }
+
+        let mut expected: Vec<Vec<PathBuf>> = expected
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .collect();
+        let mut actual: Vec<Vec<PathBuf>> = result
+            .duplicate_groups
+            .iter()
+            .map(|g| g.files.iter().map(|f| f.path.clone()).collect())
+            .collect();
+        for paths in expected.iter_mut().chain(actual.iter_mut()) {
+            paths.sort();
+        }
+        expected.sort();
+        actual.sort();
+
+        assert_eq!(result.total_files, 80);
+        assert_eq!(result.all_files.len(), 80);
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_skips_loops_and_aliases() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("real")).unwrap();
+        fs::write(dir.join("real").join("a.txt"), b"same").unwrap();
+        fs::write(dir.join("real").join("b.txt"), b"same").unwrap();
+        // One link back up the tree and one alias of a sibling folder
+        std::os::unix::fs::symlink(dir, dir.join("real").join("up")).unwrap();
+        std::os::unix::fs::symlink(dir.join("real"), dir.join("alias")).unwrap();
+
+        let scan = |follow_symlinks| {
+            Scanner::new(ScannerConfig {
+                follow_symlinks,
+                ..Default::default()
+            })
+            .scan_directory(dir)
+        };
+        let followed = scan(true);
+        let unfollowed = scan(false);
+
+        assert_eq!(followed.total_files, 2);
+        assert_eq!(followed.duplicate_groups.len(), 1);
+        assert_eq!(followed.duplicate_groups[0].files.len(), 2);
+        assert_eq!(unfollowed.total_files, 2);
+    }
+
+    #[test]
+    fn test_hard_links_are_one_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a.txt"), b"same").unwrap();
+        fs::hard_link(dir.join("a.txt"), dir.join("b.txt")).unwrap();
+
+        let result = Scanner::new(ScannerConfig::default()).scan_directory(dir);
+        assert_eq!(result.total_files, 1);
+        assert!(result.duplicate_groups.is_empty());
+
+        fs::write(dir.join("c.txt"), b"same").unwrap();
+        let result = Scanner::new(ScannerConfig::default()).scan_directory(dir);
+        assert_eq!(result.duplicate_groups.len(), 1);
+        assert_eq!(result.duplicate_groups[0].files.len(), 2);
+    }
+
+    #[test]
+    fn test_progress_callback_reports_final_state() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a"), b"same").unwrap();
+        fs::write(dir.join("b"), b"same").unwrap();
+        fs::write(dir.join("c"), b"other").unwrap();
+
+        let updates = Mutex::new(Vec::new());
+        let result = Scanner::new(ScannerConfig::default()).scan_directories_with_callback(
+            &[dir.to_path_buf()],
+            &AtomicBool::new(false),
+            |update| updates.lock().unwrap().push(update),
+        );
+
+        assert_eq!(result.duplicate_groups.len(), 1);
+        let last = updates.into_inner().unwrap().pop().unwrap();
+        assert_eq!(last.phase, ScanPhase::Hashing);
+        assert_eq!(last.files_found, 3);
+        assert_eq!((last.processed, last.total), (2, 2));
+        assert_eq!(last.groups_found, 1);
+        assert!(last.current_file.is_some_and(|f| f.starts_with(dir)));
+    }
+
+    #[test]
+    fn test_perceptual_mode_groups_resized_images() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let gradient = |w: u32, h: u32| {
+            image::GrayImage::from_fn(w, h, |x, _| image::Luma([(x * 255 / w) as u8]))
+        };
+        gradient(200, 100).save(dir.join("photo.png")).unwrap();
+        gradient(80, 40).save(dir.join("photo_small.png")).unwrap();
+        image::imageops::flip_horizontal(&gradient(200, 100))
+            .save(dir.join("other.png"))
+            .unwrap();
+        fs::write(dir.join("a.txt"), b"same").unwrap();
+        fs::write(dir.join("b.txt"), b"same").unwrap();
+
+        let scan = |detection_mode| {
+            Scanner::new(ScannerConfig {
+                detection_mode,
+                ..Default::default()
+            })
+            .scan_directory(dir)
+        };
+        let exact = scan(DetectionMode::Exact);
+        let similar = scan(DetectionMode::PerceptualImage);
+
+        assert_eq!(exact.duplicate_groups.len(), 1);
+        assert_eq!(similar.duplicate_groups.len(), 2);
+        let images = similar
+            .duplicate_groups
+            .iter()
+            .find(|g| g.kind == GroupKind::SimilarImage)
+            .unwrap();
+        let mut names: Vec<&str> = images.files.iter().map(|f| f.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["photo.png", "photo_small.png"]);
+        // Whatever the exact groups use, the full-size image is kept
+        assert_eq!(images.files[0].name, "photo.png");
+        assert_eq!(images.confidence, MatchConfidence::Approximate);
+        assert_eq!(images.similarity_distance(1), Some(0));
+        assert_eq!(similar.hash_candidates, 5);
+    }
+
+    /// A file that has already been hashed, without touching the disk
+    fn hashed(name: &str, size: u64, hash: &str) -> FileEntry {
+        let mut file = FileEntry::new(PathBuf::from(name), name.into(), size);
+        file.hash = Some(hash.into());
+        file
+    }
+
+    #[test]
+    fn test_find_duplicate_groups_without_duplicates() {
+        assert!(find_duplicate_groups(Vec::new()).is_empty());
+        let files = vec![
+            hashed("a", 10, "x"),
+            hashed("b", 10, "y"),
+            hashed("c", 20, "z"),
+        ];
+        assert!(find_duplicate_groups(files).is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicate_groups_sizes_and_waste() {
+        let files = vec![
+            hashed("small_a", 10, "s"),
+            hashed("big_a", 1000, "b"),
+            hashed("small_b", 10, "s"),
+            hashed("small_c", 10, "s"),
+            hashed("big_b", 1000, "b"),
+            // Same size as the big pair but different content
+            hashed("big_other", 1000, "o"),
+            // Never hashed, e.g. it failed to read
+            FileEntry::new(PathBuf::from("unhashed"), "unhashed".into(), 10),
+        ];
+        let groups = find_duplicate_groups(files);
+
+        assert_eq!(groups.len(), 2);
+        let names = |group: &DuplicateGroup| -> Vec<String> {
+            group.files.iter().map(|f| f.name.clone()).collect()
+        };
+        assert_eq!(names(&groups[0]), ["big_a", "big_b"]);
+        assert_eq!((groups[0].total_size, groups[0].wasted_size), (2000, 1000));
+        assert_eq!(names(&groups[1]), ["small_a", "small_b", "small_c"]);
+        assert_eq!((groups[1].total_size, groups[1].wasted_size), (30, 20));
+        assert!(groups
+            .iter()
+            .all(|g| g.kind == GroupKind::Exact && g.confidence == MatchConfidence::Exact));
+    }
+
+    #[test]
+    fn test_unique_sizes_are_pruned_before_hashing() {
+        // A file with a size of its own is dropped before its hash is compared
+        let files = vec![hashed("a", 10, "h"), hashed("b", 11, "h")];
+        assert!(find_duplicate_groups(files).is_empty());
+        // Pairs that only share a hash across sizes stay apart
+        let files = vec![
+            hashed("a", 10, "h"),
+            hashed("b", 10, "h"),
+            hashed("c", 11, "h"),
+            hashed("d", 11, "h"),
+        ];
+        let groups = find_duplicate_groups(files);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|g| g.files.len() == 2));
+
+        let mut sparse = hashed("sparse", 5, "h");
+        sparse.is_sparse = true;
+        let size_groups = group_by_size(vec![
+            hashed("lone", 7, "l"),
+            sparse,
+            hashed("pair_a", 3, "p"),
+            hashed("pair_b", 3, "p"),
+        ]);
+        let sizes = |include_sparse| -> Vec<Vec<u64>> {
+            hash_candidates(size_groups.clone(), include_sparse)
+                .iter()
+                .map(|bucket| bucket.iter().map(|f| f.size).collect())
+                .collect()
+        };
+        assert_eq!(sizes(false), [vec![3, 3]]);
+        assert_eq!(sizes(true), [vec![5], vec![3, 3]]);
+    }
 }
@@ -0,0 +1,115 @@
+//! Session module - Saving the last scan so it survives a restart
+//!
+//! A completed scan is written to the user's data directory and can be
+//! restored on startup instead of re-hashing everything.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::scanner::ScanResult;
+use crate::volume::VolumePath;
+
+/// Maximum number of files checked for existence when restoring
+const STALENESS_SAMPLE: usize = 50;
+
+/// A scan result along with what was scanned and when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedScan {
+    /// Unix timestamp of when the scan finished
+    pub saved_at: i64,
+    pub folders: Vec<VolumePath>,
+    pub loose_files: Vec<PathBuf>,
+    pub result: ScanResult,
+}
+
+/// Outcome of spot-checking a restored result against the disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Staleness {
+    pub checked: usize,
+    pub missing: usize,
+}
+
+impl SavedScan {
+    /// Default location of the saved scan
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileXSorter").join("last_scan.json"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| format!("Failed to encode: {}", e))?;
+        // Write then rename so a crash mid-write doesn't leave a truncated file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to save: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Failed to read: {}", e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to decode: {}", e))
+    }
+
+    /// Check whether a spread-out sample of the grouped files still exists
+    pub fn check_staleness(&self) -> Staleness {
+        let files: Vec<&Path> = self
+            .result
+            .duplicate_groups
+            .iter()
+            .flat_map(|g| g.files.iter().map(|f| f.path.as_path()))
+            .collect();
+        let step = (files.len() / STALENESS_SAMPLE).max(1);
+        let sample: Vec<&Path> = files
+            .into_iter()
+            .step_by(step)
+            .take(STALENESS_SAMPLE)
+            .collect();
+
+        Staleness {
+            checked: sample.len(),
+            missing: sample.iter().filter(|p| !p.exists()).count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{Scanner, ScannerConfig};
+
+    #[test]
+    fn test_round_trip_and_staleness() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let data = dir.join("data");
+        fs::create_dir_all(&data).unwrap();
+        fs::write(data.join("a"), b"same").unwrap();
+        fs::write(data.join("b"), b"same").unwrap();
+
+        let saved = SavedScan {
+            saved_at: 1_700_000_000,
+            folders: vec![VolumePath::from_path(&data)],
+            loose_files: Vec::new(),
+            result: Scanner::new(ScannerConfig::default()).scan_directory(&data),
+        };
+        let file = dir.join("state").join("last_scan.json");
+        saved.save(&file).unwrap();
+        let loaded = SavedScan::load(&file).unwrap();
+
+        assert_eq!(loaded.saved_at, saved.saved_at);
+        assert_eq!(loaded.result.duplicate_groups.len(), 1);
+        assert_eq!(
+            loaded.check_staleness(),
+            Staleness {
+                checked: 2,
+                missing: 0
+            }
+        );
+
+        fs::remove_file(data.join("b")).unwrap();
+        assert_eq!(loaded.check_staleness().missing, 1);
+    }
+}
@@ -0,0 +1,112 @@
+//! Similarity module - Perceptual hashes for finding near-duplicate images
+//!
+//! Each image is shrunk to a 9x8 grayscale thumbnail and every pixel is
+//! compared with its right-hand neighbour, giving a 64-bit difference hash
+//! (dHash). Re-saved, resized or lightly edited copies of a photo end up only
+//! a few bits apart, measured as the Hamming distance between hashes.
+
+use std::path::Path;
+
+use image::{ImageReader, Limits};
+
+use crate::scanner::normalized_extension;
+
+/// Largest distance offered in the UI; beyond this unrelated photos start to match
+pub const MAX_SIMILARITY_THRESHOLD: u32 = 20;
+
+/// Default distance, which tolerates re-encoding and resizing but not crops
+pub const DEFAULT_SIMILARITY_THRESHOLD: u32 = 8;
+
+/// Images larger than this in either dimension aren't decoded
+const MAX_IMAGE_DIMENSIONS: u32 = 16384;
+
+/// Extensions of the still-image formats that can be decoded
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "bmp", "ico", "webp", "tiff", "tif"];
+
+/// Whether `path` looks like a still image that can be compared perceptually
+pub fn is_image(path: &Path) -> bool {
+    IMAGE_EXTENSIONS.contains(&normalized_extension(path).as_str())
+}
+
+/// Compute the 64-bit difference hash of an image file
+pub fn difference_hash(path: &Path) -> Result<u64, String> {
+    let mut reader = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSIONS);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSIONS);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|e| e.to_string())?;
+
+    let small = image.thumbnail_exact(9, 8).into_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+/// Number of bits that differ between two perceptual hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Cluster `hashes` into sets whose members are all within `threshold` of
+/// the set's first member. Returns indices into `hashes`; every index
+/// appears in exactly one set.
+pub fn cluster(hashes: &[u64], threshold: u32) -> Vec<Vec<usize>> {
+    let mut assigned = vec![false; hashes.len()];
+    let mut clusters = Vec::new();
+    for seed in 0..hashes.len() {
+        if assigned[seed] {
+            continue;
+        }
+        assigned[seed] = true;
+        let mut members = vec![seed];
+        for other in seed + 1..hashes.len() {
+            if !assigned[other] && hamming_distance(hashes[seed], hashes[other]) <= threshold {
+                assigned[other] = true;
+                members.push(other);
+            }
+        }
+        clusters.push(members);
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resized_copy_is_close_and_other_image_is_not() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let gradient = |w: u32, h: u32| {
+            image::GrayImage::from_fn(w, h, |x, _| image::Luma([(x * 255 / w) as u8]))
+        };
+        gradient(256, 128).save(dir.join("photo.png")).unwrap();
+        gradient(96, 48).save(dir.join("small.jpg")).unwrap();
+        image::imageops::flip_horizontal(&gradient(256, 128))
+            .save(dir.join("flipped.png"))
+            .unwrap();
+
+        let hash = |name: &str| difference_hash(&dir.join(name)).unwrap();
+        let (photo, small, flipped) = (hash("photo.png"), hash("small.jpg"), hash("flipped.png"));
+
+        assert!(hamming_distance(photo, small) <= DEFAULT_SIMILARITY_THRESHOLD);
+        assert!(hamming_distance(photo, flipped) > MAX_SIMILARITY_THRESHOLD);
+        assert_eq!(
+            cluster(&[photo, flipped, small], DEFAULT_SIMILARITY_THRESHOLD),
+            [vec![0, 2], vec![1]]
+        );
+        assert!(is_image(Path::new("a/B.JPG")));
+        assert!(!is_image(Path::new("a/b.gif")));
+    }
+}
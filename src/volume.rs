@@ -0,0 +1,433 @@
+//! Volume module - Stable path identity across drive letter changes
+//!
+//! This module records which volume a path lives on so persisted paths
+//! keep working when a removable drive is mounted under a new letter,
+//! and reports free space so file operations can warn before filling a drive.
+//! It also tells network and removable drives apart, since those are too
+//! slow or unreliable to read as many files from at once.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A path stored together with the identity of the volume it lives on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumePath {
+    /// The literal path as it was when recorded
+    pub path: PathBuf,
+    /// Volume GUID path (`\\?\Volume{...}\`), if it could be determined
+    pub volume_guid: Option<String>,
+    /// Path relative to the volume's mount point
+    pub relative: Option<PathBuf>,
+}
+
+impl VolumePath {
+    /// Record a path along with its volume identity
+    pub fn from_path(path: &Path) -> Self {
+        match volume_identity(path) {
+            Some((guid, mount_point)) => Self {
+                path: path.to_path_buf(),
+                volume_guid: Some(guid),
+                relative: path.strip_prefix(&mount_point).ok().map(Path::to_path_buf),
+            },
+            None => Self {
+                path: path.to_path_buf(),
+                volume_guid: None,
+                relative: None,
+            },
+        }
+    }
+
+    /// Resolve to the path under the volume's current mount point.
+    ///
+    /// Falls back to the literal path when the volume isn't mounted or
+    /// its identity wasn't recorded.
+    pub fn resolve(&self) -> PathBuf {
+        if let (Some(guid), Some(relative)) = (&self.volume_guid, &self.relative) {
+            if let Some(mount_point) = current_mount_point(guid) {
+                return mount_point.join(relative);
+            }
+        }
+        self.path.clone()
+    }
+}
+
+impl From<&Path> for VolumePath {
+    fn from(path: &Path) -> Self {
+        Self::from_path(path)
+    }
+}
+
+/// Free and total capacity of a volume, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub available: u64,
+    pub total: u64,
+}
+
+impl DiskSpace {
+    /// Free space left after `incoming` bytes are written to this volume
+    pub fn available_after(&self, incoming: u64) -> u64 {
+        self.available.saturating_sub(incoming)
+    }
+
+    /// Whether writing `incoming` bytes would leave the volume near full
+    /// (under 10% or under 1 GiB free)
+    pub fn near_full_after(&self, incoming: u64) -> bool {
+        const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+        let remaining = self.available_after(incoming);
+        remaining < MIN_FREE_BYTES || remaining < self.total / 10
+    }
+
+    /// Free space once `freed` bytes are removed from this volume
+    pub fn available_after_freeing(&self, freed: u64) -> u64 {
+        self.available.saturating_add(freed).min(self.total)
+    }
+}
+
+/// What deleting a set of files would free on one volume
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeFreeing {
+    /// Drive or mount point the files live on
+    pub mount_point: PathBuf,
+    pub space: DiskSpace,
+    pub freed: u64,
+}
+
+/// Sum `(path, size)` pairs by the volume they live on, with each volume's
+/// current free space. Files whose folder or volume can't be queried are left out.
+pub fn space_freed_by(files: &[(PathBuf, u64)]) -> Vec<VolumeFreeing> {
+    let mut volumes: Vec<VolumeFreeing> = Vec::new();
+    // Looked up once per folder, since a selection is often many files in a few
+    let mut folders: HashMap<&Path, Option<PathBuf>> = HashMap::new();
+    for (path, size) in files {
+        let folder = path.parent().unwrap_or(path);
+        let found = folders.entry(folder).or_insert_with(|| mount_point(folder));
+        let Some(mount_point) = found.clone() else {
+            continue;
+        };
+        match volumes.iter_mut().find(|v| v.mount_point == mount_point) {
+            Some(volume) => volume.freed += size,
+            None => {
+                if let Some(space) = disk_space(&mount_point) {
+                    volumes.push(VolumeFreeing {
+                        mount_point,
+                        space,
+                        freed: *size,
+                    });
+                }
+            }
+        }
+    }
+    volumes
+}
+
+/// Root of the volume holding an existing path, e.g. `C:\`, or `Z:\` for a
+/// mapped network drive, which has no volume GUID
+#[cfg(windows)]
+fn mount_point(path: &Path) -> Option<PathBuf> {
+    use windows_sys::Win32::Storage::FileSystem::GetVolumePathNameW;
+
+    let wide_path = to_wide(path.as_os_str());
+    let mut mount_buf = vec![0u16; 1024];
+    // SAFETY: wide_path is NUL-terminated and mount_buf is sized as passed
+    let ok = unsafe {
+        GetVolumePathNameW(
+            wide_path.as_ptr(),
+            mount_buf.as_mut_ptr(),
+            mount_buf.len() as u32,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+    Some(PathBuf::from(from_wide(&mount_buf)))
+}
+
+#[cfg(unix)]
+fn mount_point(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = std::fs::metadata(path).ok()?.dev();
+    path.ancestors()
+        .take_while(|dir| std::fs::metadata(dir).is_ok_and(|m| m.dev() == device))
+        .last()
+        .map(Path::to_path_buf)
+}
+
+#[cfg(not(any(windows, unix)))]
+fn mount_point(_path: &Path) -> Option<PathBuf> {
+    None
+}
+
+/// Query free and total space for the volume containing `path`
+#[cfg(windows)]
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide_path = to_wide(path.as_os_str());
+    let mut available = 0u64;
+    let mut total = 0u64;
+    // SAFETY: wide_path is NUL-terminated and the out pointers are valid u64s
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide_path.as_ptr(),
+            &mut available,
+            &mut total,
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(DiskSpace { available, total })
+}
+
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain old data and c_path is NUL-terminated
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some(DiskSpace {
+        available: stat.f_bavail as u64 * block,
+        total: stat.f_blocks as u64 * block,
+    })
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn disk_space(_path: &Path) -> Option<DiskSpace> {
+    None
+}
+
+/// Whether two existing paths live on the same volume
+#[cfg(windows)]
+pub fn same_volume(a: &Path, b: &Path) -> bool {
+    match (volume_identity(a), volume_identity(b)) {
+        (Some((x, _)), Some((y, _))) => x == y,
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+pub fn same_volume(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(x), Ok(y)) => x.dev() == y.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn same_volume(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+/// Look up the volume GUID and mount point for a path
+#[cfg(windows)]
+fn volume_identity(path: &Path) -> Option<(String, PathBuf)> {
+    use windows_sys::Win32::Storage::FileSystem::GetVolumeNameForVolumeMountPointW;
+
+    let root = mount_point(path)?;
+    let wide_mount = to_wide(root.as_os_str());
+    let mut guid_buf = vec![0u16; 64];
+    // SAFETY: wide_mount is NUL-terminated and guid_buf is sized as passed
+    let ok = unsafe {
+        GetVolumeNameForVolumeMountPointW(
+            wide_mount.as_ptr(),
+            guid_buf.as_mut_ptr(),
+            guid_buf.len() as u32,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+
+    Some((from_wide(&guid_buf), root))
+}
+
+#[cfg(not(windows))]
+fn volume_identity(_path: &Path) -> Option<(String, PathBuf)> {
+    None
+}
+
+/// Find where a volume is currently mounted, if anywhere
+#[cfg(windows)]
+fn current_mount_point(guid: &str) -> Option<PathBuf> {
+    use windows_sys::Win32::Storage::FileSystem::GetVolumePathNamesForVolumeNameW;
+
+    let wide_guid = to_wide(std::ffi::OsStr::new(guid));
+    let mut names_buf = vec![0u16; 1024];
+    let mut returned = 0u32;
+    // SAFETY: buffers are sized as passed; the result is a NUL-separated list
+    let ok = unsafe {
+        GetVolumePathNamesForVolumeNameW(
+            wide_guid.as_ptr(),
+            names_buf.as_mut_ptr(),
+            names_buf.len() as u32,
+            &mut returned,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+
+    let first = from_wide(&names_buf);
+    if first.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(first))
+    }
+}
+
+#[cfg(not(windows))]
+fn current_mount_point(_guid: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(windows)]
+fn to_wide(s: &std::ffi::OsStr) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+    s.encode_wide().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn from_wide(buf: &[u16]) -> String {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..len])
+}
+
+/// What sort of drive a path lives on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DriveKind {
+    Local,
+    Removable,
+    Network,
+}
+
+impl DriveKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            DriveKind::Local => "local drive",
+            DriveKind::Removable => "removable drive",
+            DriveKind::Network => "network drive",
+        }
+    }
+}
+
+/// The kind of drive `path` lives on, `Local` when it can't be told
+#[cfg(windows)]
+pub fn drive_kind(path: &Path) -> DriveKind {
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+    use windows_sys::Win32::System::WindowsProgramming::{
+        DRIVE_CDROM, DRIVE_REMOTE, DRIVE_REMOVABLE,
+    };
+
+    // Mapped drives have a root but no volume GUID, so only the root is needed
+    let Some(root) = mount_point(path) else {
+        // Unreachable shares still deserve the cautious treatment
+        return if path.to_string_lossy().starts_with(r"\") {
+            DriveKind::Network
+        } else {
+            DriveKind::Local
+        };
+    };
+    let wide_root = to_wide(root.as_os_str());
+    // SAFETY: wide_root is NUL-terminated
+    match unsafe { GetDriveTypeW(wide_root.as_ptr()) } {
+        DRIVE_REMOTE => DriveKind::Network,
+        DRIVE_REMOVABLE | DRIVE_CDROM => DriveKind::Removable,
+        _ => DriveKind::Local,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn drive_kind(path: &Path) -> DriveKind {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // NFS, SMB, CIFS and SMB2 filesystem magic numbers
+    const NETWORK_FS: [u32; 4] = [0x6969, 0x517b, 0xff53_4d42, 0xfe53_4d42];
+    // FAT and exFAT, which USB sticks and SD cards are formatted with
+    const REMOVABLE_FS: [u32; 2] = [0x4d44, 0x2011_bab0];
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return DriveKind::Local;
+    };
+    // SAFETY: statfs is plain old data and c_path is NUL-terminated
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return DriveKind::Local;
+    }
+    let kind = stat.f_type as u32;
+    if NETWORK_FS.contains(&kind) {
+        DriveKind::Network
+    } else if REMOVABLE_FS.contains(&kind) {
+        DriveKind::Removable
+    } else {
+        DriveKind::Local
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn drive_kind(_path: &Path) -> DriveKind {
+    DriveKind::Local
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmounted_volume_falls_back_to_literal_path() {
+        let stored = VolumePath {
+            path: PathBuf::from("/media/usb/photos"),
+            volume_guid: Some("\\\\?\\Volume{00000000-0000-0000-0000-000000000000}\\".into()),
+            relative: Some(PathBuf::from("photos")),
+        };
+        assert_eq!(stored.resolve(), PathBuf::from("/media/usb/photos"));
+    }
+
+    #[test]
+    fn test_near_full_threshold() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        let space = DiskSpace {
+            available: 20 * GIB,
+            total: 100 * GIB,
+        };
+        assert!(!space.near_full_after(5 * GIB));
+        assert!(space.near_full_after(15 * GIB));
+        assert_eq!(space.available_after(30 * GIB), 0);
+
+        // Small drives still warn once less than a gigabyte would remain
+        let small = DiskSpace {
+            available: 2 * GIB,
+            total: 4 * GIB,
+        };
+        assert!(small.near_full_after(GIB + 1));
+        assert_eq!(small.available_after_freeing(GIB), 3 * GIB);
+        assert_eq!(small.available_after_freeing(10 * GIB), 4 * GIB);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_space_freed_by_sums_per_volume() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let (a, b) = (dir.join("a.bin"), dir.join("b.bin"));
+        std::fs::write(&a, [0u8; 10]).unwrap();
+        std::fs::write(&b, [0u8; 20]).unwrap();
+
+        let missing = dir.join("gone").join("missing");
+        let volumes = space_freed_by(&[(a, 10), (b, 20), (missing, 5)]);
+        assert_eq!(drive_kind(dir), DriveKind::Local);
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].freed, 30);
+        assert!(dir.starts_with(&volumes[0].mount_point));
+    }
+}
@@ -0,0 +1,252 @@
+//! Watcher module - Flag new duplicates as files appear in scanned folders
+//!
+//! After a scan, the scanned roots can be watched for created or modified
+//! files. Each one is checked on a background thread against the files the
+//! scan already hashed, and reported if it duplicates one of them.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::scanner::{
+    compute_file_hash, FileEntry, GroupKind, HashAlgorithm, ScanResult, ScannerConfig,
+};
+
+/// How long a file must go without events before it's checked, so files
+/// still being written aren't hashed half-finished
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Changed paths waiting to settle; further events are dropped past this
+const MAX_PENDING: usize = 10_000;
+
+/// A newly seen file with the same content as a file from the scan
+#[derive(Debug, Clone)]
+pub struct NewDuplicate {
+    /// The new file, already hashed
+    pub file: FileEntry,
+    /// A known file with the same hash
+    pub peer: FileEntry,
+}
+
+/// Known hashed files, keyed by size so unrelated files never get hashed
+type KnownFiles = HashMap<u64, Vec<FileEntry>>;
+
+/// Watches folders for files that duplicate an existing scan result.
+///
+/// Watching stops when this is dropped.
+pub struct DuplicateWatcher {
+    _watcher: RecommendedWatcher,
+    found: mpsc::Receiver<NewDuplicate>,
+}
+
+impl DuplicateWatcher {
+    /// Start watching `roots` for new copies of files in `result`
+    pub fn start(
+        roots: &[PathBuf],
+        result: &ScanResult,
+        config: &ScannerConfig,
+    ) -> Result<Self, String> {
+        let (event_tx, event_rx) = mpsc::channel::<PathBuf>();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        let _ = event_tx.send(path);
+                    }
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start watcher: {}", e))?;
+
+        let mode = if config.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        for root in roots {
+            watcher
+                .watch(root, mode)
+                .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+        }
+
+        let known = known_files(result);
+        // Files already in the result are never reported again
+        let seen: HashSet<PathBuf> = result
+            .duplicate_groups
+            .iter()
+            .flat_map(|g| &g.files)
+            .chain(&result.all_files)
+            .map(|f| f.path.clone())
+            .collect();
+        let filter = CheckFilter {
+            config: config.clone(),
+            algorithm: result.hash_algorithm,
+        };
+        let (found_tx, found) = mpsc::channel();
+        thread::Builder::new()
+            .name("fxs-watch".to_string())
+            .spawn(move || check_changes(event_rx, found_tx, known, seen, filter))
+            .map_err(|e| format!("Failed to start watcher thread: {}", e))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            found,
+        })
+    }
+
+    /// Duplicates detected since the last poll
+    pub fn poll(&self) -> Vec<NewDuplicate> {
+        self.found.try_iter().collect()
+    }
+}
+
+/// Every hashed file from the scan that a new file could duplicate
+fn known_files(result: &ScanResult) -> KnownFiles {
+    let mut known: KnownFiles = HashMap::new();
+    let grouped = result
+        .duplicate_groups
+        .iter()
+        .filter(|g| g.kind == GroupKind::Exact)
+        .filter_map(|g| g.files.first());
+    for file in grouped.chain(&result.all_files) {
+        if file.hash.is_some() {
+            known.entry(file.size).or_default().push(file.clone());
+        }
+    }
+    known
+}
+
+/// Worker loop: debounce changed paths, then hash the ones whose size matches a known file
+fn check_changes(
+    events: mpsc::Receiver<PathBuf>,
+    found: mpsc::Sender<NewDuplicate>,
+    mut known: KnownFiles,
+    mut seen: HashSet<PathBuf>,
+    filter: CheckFilter,
+) {
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        match events.recv_timeout(SETTLE_TIME / 2) {
+            Ok(path) => {
+                // A steady stream of events must not hold back files that
+                // settled meanwhile, so fall through to the settle pass
+                if pending.len() < MAX_PENDING || pending.contains_key(&path) {
+                    pending.insert(path, Instant::now());
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // The watcher was dropped
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= SETTLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            pending.remove(&path);
+            if seen.contains(&path) {
+                continue;
+            }
+            let Some(duplicate) = check_file(&path, &known, &filter) else {
+                continue;
+            };
+            seen.insert(path);
+            known
+                .entry(duplicate.file.size)
+                .or_default()
+                .push(duplicate.file.clone());
+            if found.send(duplicate).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// How changed files are checked
+#[derive(Debug, Clone)]
+struct CheckFilter {
+    /// Size and extension filters of the scan
+    config: ScannerConfig,
+    /// Must match the algorithm of the known hashes
+    algorithm: HashAlgorithm,
+}
+
+/// Hash `path` if a known file has its size and return the match, if any
+fn check_file(path: &Path, known: &KnownFiles, filter: &CheckFilter) -> Option<NewDuplicate> {
+    if !filter.config.extension_allowed(path)
+        || path
+            .ancestors()
+            .skip(1)
+            .any(|dir| filter.config.dir_excluded(dir))
+    {
+        return None;
+    }
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file()
+        || !filter.config.size_in_range(metadata.len())
+        || filter.config.attributes_excluded(&metadata)
+    {
+        return None;
+    }
+    let candidates = known.get(&metadata.len())?;
+    let hash = compute_file_hash(path, filter.algorithm).ok()?;
+    let peer = candidates
+        .iter()
+        .find(|f| f.hash.as_deref() == Some(hash.as_str()))?
+        .clone();
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut file = FileEntry::new(path.to_path_buf(), name, metadata.len());
+    file.hash = Some(hash);
+    file.modified = metadata.modified().ok();
+    Some(NewDuplicate { file, peer })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_check_file_matches_known_content() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a"), b"same").unwrap();
+        fs::write(dir.join("b"), b"same").unwrap();
+        let result = Scanner::new(ScannerConfig::default()).scan_directory(dir);
+        let known = known_files(&result);
+
+        fs::write(dir.join("c"), b"same").unwrap();
+        fs::write(dir.join("d"), b"diff").unwrap();
+        fs::write(dir.join("e"), b"longer").unwrap();
+
+        let filter = |min_size| CheckFilter {
+            config: ScannerConfig {
+                min_size,
+                ..Default::default()
+            },
+            algorithm: result.hash_algorithm,
+        };
+        let duplicate = check_file(&dir.join("c"), &known, &filter(0)).unwrap();
+        assert_eq!(duplicate.peer.hash, duplicate.file.hash);
+        assert!(check_file(&dir.join("d"), &known, &filter(0)).is_none());
+        assert!(check_file(&dir.join("e"), &known, &filter(0)).is_none());
+        assert!(check_file(&dir.join("c"), &known, &filter(10)).is_none());
+
+        let mut result = result;
+        assert!(result.insert_hashed(duplicate.file, [duplicate.peer]));
+        assert_eq!(result.duplicate_groups[0].files.len(), 3);
+        assert_eq!(result.total_duplicates, 2);
+    }
+}
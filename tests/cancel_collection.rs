@@ -0,0 +1,56 @@
+//! Cancelling a scan while it is still collecting files
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use filexsorter::scanner::{ScanProgress, Scanner, ScannerConfig};
+
+#[test]
+fn cancel_returns_promptly_when_most_entries_are_filtered_out() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    let (folders, wanted, skipped) = (10, 10, 490);
+    for folder in 0..folders {
+        let dir = root.join(folder.to_string());
+        // An excluded folder is pruned without reaching the file checks
+        fs::create_dir_all(dir.join("cache")).unwrap();
+        for file in 0..wanted {
+            fs::write(dir.join(format!("{}.jpg", file)), b"x").unwrap();
+        }
+        for file in 0..skipped {
+            fs::write(dir.join(format!("{}.tmp", file)), b"x").unwrap();
+            fs::write(dir.join("cache").join(format!("{}.jpg", file)), b"x").unwrap();
+        }
+    }
+
+    let scanner = Scanner::new(ScannerConfig {
+        include_extensions: Some(vec!["jpg".to_string()]),
+        exclude_dir_names: vec!["cache".to_string()],
+        ..Default::default()
+    });
+    let progress = ScanProgress::default();
+    let (cancel, done) = (AtomicBool::new(false), AtomicBool::new(false));
+    let (result, latency) = thread::scope(|scope| {
+        let canceller = scope.spawn(|| {
+            while progress.files_found.load(Ordering::Relaxed) < wanted
+                && !done.load(Ordering::Relaxed)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            cancel.store(true, Ordering::Relaxed);
+            Instant::now()
+        });
+        let result =
+            scanner.scan_directories_with_progress(&[root.to_path_buf()], &progress, &cancel);
+        let returned = Instant::now();
+        done.store(true, Ordering::Relaxed);
+        let cancelled = canceller.join().unwrap();
+        (result, returned.saturating_duration_since(cancelled))
+    });
+
+    assert!(result.total_files < folders * wanted);
+    assert!(result.duplicate_groups.is_empty());
+    assert!(latency < Duration::from_secs(1), "took {:?}", latency);
+}